@@ -0,0 +1,153 @@
+use alloc::alloc::{handle_alloc_error, Layout};
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::hash::BuildHasher;
+
+use crate::{CacheEntity, HashMap, HashSet, Key, LocalCache, Lookup};
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// Looks up all `keys`, locking each shard once. Misses, negative and
+    /// expired entries are left out of the result.
+    pub fn get_many<I, K>(&self, keys: I) -> HashMap<String, Arc<T>>
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<str>,
+    {
+        let mut found = HashMap::new();
+        let buckets = self.bucket(keys.into_iter().map(|key| key.as_ref().to_string()), |key| key);
+        for (shard, keys) in buckets.into_iter().enumerate() {
+            if keys.is_empty() {
+                continue;
+            }
+            let mut local_cache = self.shards[shard].lock();
+            for key in keys {
+                if let Lookup::Hit(value) = local_cache.get(&key) {
+                    found.insert(key, value);
+                }
+            }
+        }
+        found
+    }
+
+    /// Like [`get_many`](Self::get_many), loading the keys that aren't cached
+    /// with a single call to `f`, which gets each of them once. The values
+    /// it returns for them are cached and included in the result; keys it
+    /// leaves out stay missing, and keys that weren't asked for are ignored.
+    pub fn get_many_with<I, K, F>(&self, keys: I, f: F) -> HashMap<String, Arc<T>>
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<str>,
+        F: FnOnce(Vec<String>) -> HashMap<String, T>,
+    {
+        let keys: HashSet<String> = keys.into_iter().map(|key| key.as_ref().to_string()).collect();
+        let mut found = self.get_many(&keys);
+        let missing: Vec<String> = keys.into_iter().filter(|key| !found.contains_key(key)).collect();
+        if missing.is_empty() {
+            return found;
+        }
+        let mut loaded = f(missing.clone());
+        let loaded = missing.into_iter().filter_map(|key| loaded.remove(&key).map(|value| (key, Arc::new(value))));
+        for (shard, items) in self.bucket(loaded, |item| &item.0).into_iter().enumerate() {
+            if items.is_empty() {
+                continue;
+            }
+            let mut local_cache = self.shards[shard].lock();
+            for (key, value) in items {
+                found.insert(key.clone(), value.clone());
+                local_cache.insert(key.into(), Some(value), None, "loader");
+            }
+        }
+        found
+    }
+
+    /// Inserts all `items` with the default TTL, locking each shard once and
+    /// evicting for capacity once per shard at the end rather than per item.
+    /// Returns the number of entries inserted.
+    pub fn put_many<I: IntoIterator<Item = (String, Arc<T>)>>(&self, items: I) -> usize {
+        let mut inserted = 0;
+        for (shard, items) in self.bucket(items, |item| &item.0).into_iter().enumerate() {
+            if items.is_empty() {
+                continue;
+            }
+            let mut local_cache = self.shards[shard].lock();
+            let ttl_ns = local_cache.max_age_ns;
+            for (key, value) in items {
+                let key = Key::from(key);
+                if local_cache.insert_entry(key.clone(), Some(value.clone()), ttl_ns, false).is_err() {
+                    handle_alloc_error(Layout::new::<CacheEntity<T>>());
+                }
+                local_cache.write_through(&key, &value, ttl_ns);
+                inserted += 1;
+            }
+            let now = local_cache.now();
+            if local_cache.hot_len() > local_cache.max_numbers {
+                local_cache.evict_expired(now, usize::MAX);
+            }
+            local_cache.trim();
+        }
+        inserted
+    }
+
+    // Groups items by the shard of their key.
+    pub(crate) fn bucket<V, I, F>(&self, items: I, key: F) -> Vec<Vec<V>>
+    where
+        I: IntoIterator<Item = V>,
+        F: Fn(&V) -> &str,
+    {
+        let mut buckets: Vec<Vec<_>> = (0..self.shards.len()).map(|_| Vec::new()).collect();
+        for item in items {
+            buckets[self.shard_index(key(&item))].push(item);
+        }
+        buckets
+    }
+}
+
+impl<T, S: BuildHasher> Extend<(String, Arc<T>)> for LocalCache<T, S> {
+    fn extend<I: IntoIterator<Item = (String, Arc<T>)>>(&mut self, iter: I) {
+        self.put_many(iter);
+    }
+}
+
+#[test]
+fn test_get_many() {
+    let local_cache: LocalCache<usize> = LocalCache::builder().shards(4).build();
+    for i in 0..10 {
+        local_cache.put(i.to_string(), Arc::new(i));
+    }
+    local_cache.put_negative(String::from("n"));
+    let found = local_cache.get_many(["1", "5", "9", "n", "x"]);
+    assert_eq!(3, found.len());
+    assert_eq!(Some(&Arc::new(5)), found.get("5"));
+}
+
+#[test]
+fn test_put_many() {
+    let mut local_cache: LocalCache<usize> = LocalCache::new(4, 360);
+    assert_eq!(6, local_cache.put_many((0..6).map(|i| (i.to_string(), Arc::new(i)))));
+    assert_eq!(4, local_cache.shards[0].lock().map.len());
+    assert_eq!(None, local_cache.get("1"));
+    assert_eq!(Some(Arc::new(5)), local_cache.get("5"));
+
+    local_cache.extend([(String::from("x"), Arc::new(10))]);
+    assert_eq!(Some(Arc::new(10)), local_cache.get("x"));
+}
+
+#[test]
+fn test_get_many_with() {
+    let local_cache: LocalCache<usize> = LocalCache::builder().shards(4).build();
+    local_cache.put(String::from("1"), Arc::new(1));
+    let mut calls = Vec::new();
+    let found = local_cache.get_many_with(["1", "2", "3", "2"], |mut missing| {
+        missing.sort();
+        calls.push(missing);
+        HashMap::from([(String::from("2"), 20), (String::from("x"), 0)])
+    });
+    assert_eq!(vec![vec![String::from("2"), String::from("3")]], calls);
+    assert_eq!(HashMap::from([(String::from("1"), Arc::new(1)), (String::from("2"), Arc::new(20))]), found);
+    assert_eq!(Some(Arc::new(20)), local_cache.get("2"));
+    assert_eq!(None, local_cache.get("x"));
+
+    let found = local_cache.get_many_with(["1", "2"], |_| unreachable!());
+    assert_eq!(2, found.len());
+}
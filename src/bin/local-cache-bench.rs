@@ -0,0 +1,161 @@
+//! Replays a key trace (or a synthetic Zipf workload) against a cache and
+//! reports hit ratio, throughput and peak memory.
+//!
+//! Every access is a `get`; misses are followed by a `put`, as a
+//! read-through caller would do. Peak memory is the whole process's,
+//! so it includes the trace itself.
+
+use std::env;
+use std::fs;
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use local_cache::LocalCache;
+
+const USAGE: &str = "usage: local-cache-bench [options]
+  --trace PATH     replay keys from PATH, one per line
+  --keys N         synthetic key space size (default 100000)
+  --ops N          synthetic operation count (default 1000000)
+  --zipf S         synthetic Zipf exponent (default 1.0)
+  --seed N         synthetic workload seed (default 1)
+  --capacity N     cache max entries (default 10000)
+  --ttl-ms N       cache TTL in milliseconds (default 60000)
+  --shards N       cache shard count (default 1)
+  --threads N      replaying threads (default 1)";
+
+struct Options {
+    trace: Option<String>,
+    keys: usize,
+    ops: usize,
+    zipf: f64,
+    seed: u64,
+    capacity: usize,
+    ttl_ms: u64,
+    shards: usize,
+    threads: usize,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        trace: None,
+        keys: 100_000,
+        ops: 1_000_000,
+        zipf: 1.0,
+        seed: 1,
+        capacity: 10_000,
+        ttl_ms: 60_000,
+        shards: 1,
+        threads: 1,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(flag) = args.next() {
+        if flag == "-h" || flag == "--help" {
+            return Err(String::new());
+        }
+        let value = args.next().ok_or_else(|| format!("missing value for {}", flag))?;
+        let number = || value.parse::<u64>().map_err(|_| format!("invalid value for {}: {}", flag, value));
+        match flag.as_str() {
+            "--trace" => options.trace = Some(value.clone()),
+            "--keys" => options.keys = number()? as usize,
+            "--ops" => options.ops = number()? as usize,
+            "--zipf" => options.zipf = value.parse().map_err(|_| format!("invalid value for {}: {}", flag, value))?,
+            "--seed" => options.seed = number()?,
+            "--capacity" => options.capacity = number()? as usize,
+            "--ttl-ms" => options.ttl_ms = number()?,
+            "--shards" => options.shards = number()? as usize,
+            "--threads" => options.threads = (number()? as usize).max(1),
+            _ => return Err(format!("unknown option {}", flag)),
+        }
+    }
+    Ok(options)
+}
+
+// xorshift64*, good enough to drive a synthetic workload.
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state >> 12;
+    *state ^= *state << 25;
+    *state ^= *state >> 27;
+    state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+}
+
+fn zipf_trace(options: &Options) -> Vec<String> {
+    let mut cdf = Vec::with_capacity(options.keys);
+    let mut total = 0.0;
+    for rank in 1..=options.keys {
+        total += 1.0 / (rank as f64).powf(options.zipf);
+        cdf.push(total);
+    }
+    let mut state = options.seed.max(1);
+    (0..options.ops)
+        .map(|_| {
+            let sample = (next_random(&mut state) >> 11) as f64 / (1u64 << 53) as f64 * total;
+            format!("key-{}", cdf.partition_point(|&c| c < sample))
+        })
+        .collect()
+}
+
+// Peak resident set size in KiB, where the platform exposes it.
+fn peak_rss_kib() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+fn main() {
+    let options = parse_options().unwrap_or_else(|err| {
+        if !err.is_empty() {
+            eprintln!("{}", err);
+        }
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    });
+    let trace = match &options.trace {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(text) => text.lines().map(str::to_string).collect(),
+            Err(err) => {
+                eprintln!("cannot read {}: {}", path, err);
+                process::exit(1);
+            }
+        },
+        None => zipf_trace(&options),
+    };
+    let cache: LocalCache<()> = LocalCache::builder()
+        .max_entries(options.capacity)
+        .ttl(Duration::from_millis(options.ttl_ms))
+        .shards(options.shards)
+        .build();
+
+    let hits = AtomicU64::new(0);
+    let started = Instant::now();
+    let chunk = trace.len().div_ceil(options.threads).max(1);
+    thread::scope(|scope| {
+        for keys in trace.chunks(chunk) {
+            let (cache, hits) = (&cache, &hits);
+            scope.spawn(move || {
+                let mut local_hits = 0;
+                for key in keys {
+                    if cache.get(key).is_some() {
+                        local_hits += 1;
+                    } else {
+                        cache.put(key.clone(), Arc::new(()));
+                    }
+                }
+                hits.fetch_add(local_hits, Ordering::Relaxed);
+            });
+        }
+    });
+    let elapsed = started.elapsed();
+
+    let ops = trace.len() as f64;
+    let hits = hits.load(Ordering::Relaxed) as f64;
+    println!("operations: {}", trace.len());
+    println!("hit ratio:  {:.4}", if ops > 0.0 { hits / ops } else { 0.0 });
+    println!("throughput: {:.0} ops/s", ops / elapsed.as_secs_f64());
+    match peak_rss_kib() {
+        Some(kib) => println!("peak rss:   {} KiB", kib),
+        None => println!("peak rss:   n/a"),
+    }
+}
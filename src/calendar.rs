@@ -0,0 +1,77 @@
+//! Expirations aligned to wall-clock boundaries in a time zone.
+//!
+//! Works with any [`chrono::TimeZone`], e.g. `chrono_tz::Tz` for named zones
+//! or `chrono::Local` for the host's zone.
+
+use std::hash::BuildHasher;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use chrono::{DateTime, TimeDelta, TimeZone, Timelike, Utc};
+
+use crate::{Key, LocalCache};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Boundary {
+    /// The next local midnight, or the first valid time after it on days
+    /// where a DST change skips midnight.
+    Midnight,
+    /// The next time the local clock shows a whole hour.
+    Hour,
+}
+
+/// The first `boundary` in `tz` strictly after `now`.
+pub fn next_boundary<Tz: TimeZone>(boundary: Boundary, tz: &Tz, now: SystemTime) -> SystemTime {
+    let local = DateTime::<Utc>::from(now).with_timezone(tz);
+    match boundary {
+        Boundary::Midnight => {
+            let mut midnight = local.date_naive().succ_opt().unwrap().and_hms_opt(0, 0, 0).unwrap();
+            // Zones move their clocks in steps of at least 15 minutes, so
+            // stepping by that lands on the end of any gap.
+            loop {
+                if let Some(next) = tz.from_local_datetime(&midnight).earliest() {
+                    return next.into();
+                }
+                midnight += TimeDelta::minutes(15);
+            }
+        }
+        Boundary::Hour => {
+            // Work on instants rather than local times, so an hour repeated
+            // by a DST change isn't skipped.
+            let into_hour = TimeDelta::seconds(i64::from(local.minute() * 60 + local.second()))
+                + TimeDelta::nanoseconds(i64::from(local.nanosecond()));
+            let mut next = local - into_hour + TimeDelta::hours(1);
+            let minute = next.minute();
+            if minute != 0 {
+                // Half-hour DST changes shift the wall clock off the hour.
+                next += TimeDelta::minutes(i64::from(60 - minute));
+            }
+            next.into()
+        }
+    }
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// Stores `value` until the next `boundary` in `tz`.
+    pub fn put_until_next<Tz: TimeZone>(&self, key: impl Into<Key>, value: Arc<T>, boundary: Boundary, tz: &Tz) {
+        let now = UNIX_EPOCH + Duration::from_nanos(u64::try_from(self.clock.now_ns()).unwrap_or(u64::MAX));
+        let ttl = next_boundary(boundary, tz, now).duration_since(now).unwrap_or(Duration::ZERO);
+        self.put_with_ttl(key, value, ttl)
+    }
+}
+
+#[test]
+fn test_next_boundary() {
+    use chrono_tz::{America::New_York, Asia::Kolkata, Australia::Lord_Howe};
+
+    let at = |s: &str| SystemTime::from(DateTime::parse_from_rfc3339(s).unwrap());
+
+    assert_eq!(at("2024-03-11T00:00:00-04:00"), next_boundary(Boundary::Midnight, &New_York, at("2024-03-10T12:00:00-04:00")));
+    // 23 hours long on the spring-forward day.
+    assert_eq!(at("2024-03-11T04:00:00Z"), next_boundary(Boundary::Midnight, &New_York, at("2024-03-10T05:00:00Z")));
+    assert_eq!(at("2024-03-10T03:00:00-04:00"), next_boundary(Boundary::Hour, &New_York, at("2024-03-10T01:30:00-05:00")));
+    // 01:00 happens twice on the fall-back day.
+    assert_eq!(at("2024-11-03T01:00:00-05:00"), next_boundary(Boundary::Hour, &New_York, at("2024-11-03T01:30:00-04:00")));
+    assert_eq!(at("2024-01-01T11:00:00+05:30"), next_boundary(Boundary::Hour, &Kolkata, at("2024-01-01T10:59:59+05:30")));
+    assert_eq!(at("2024-10-06T03:00:00+11:00"), next_boundary(Boundary::Hour, &Lord_Howe, at("2024-10-06T01:45:00+10:30")));
+}
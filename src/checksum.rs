@@ -0,0 +1,30 @@
+// CRC-32 (IEEE 802.3), used to detect corrupted values.
+
+const TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+pub(crate) fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0, |crc, &b| (crc >> 8) ^ TABLE[((crc ^ b as u32) & 0xff) as usize])
+}
+
+pub(crate) fn checksum_of<T: AsRef<[u8]>>(value: &T) -> u32 {
+    crc32(value.as_ref())
+}
+
+#[test]
+fn test_crc32() {
+    assert_eq!(0xcbf4_3926, crc32(b"123456789"));
+}
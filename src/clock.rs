@@ -0,0 +1,87 @@
+#[cfg(feature = "std")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where the cache reads the current time from for expiry, see
+/// [`LocalCacheBuilder::clock`](crate::LocalCacheBuilder::clock). Lets
+/// targets without a usable `SystemTime` supply their own, and tests
+/// control time.
+pub trait Clock: Send + Sync {
+    /// Nanoseconds since the Unix epoch.
+    fn now_ns(&self) -> u128;
+}
+
+/// Reads [`SystemTime::now`]; the default.
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    fn now_ns(&self) -> u128 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos()
+    }
+}
+
+/// Reads JavaScript's `Date.now()`, for wasm targets without a working
+/// `SystemTime`. Millisecond resolution. Only on wasm32; elsewhere the
+/// feature leaves [`SystemClock`] in place.
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct JsClock;
+
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+impl Clock for JsClock {
+    fn now_ns(&self) -> u128 {
+        (js_sys::Date::now() * 1e6) as u128
+    }
+}
+
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub(crate) type DefaultClock = JsClock;
+#[cfg(all(feature = "std", not(all(feature = "wasm", target_arch = "wasm32"))))]
+pub(crate) type DefaultClock = SystemClock;
+#[cfg(not(feature = "std"))]
+pub(crate) type DefaultClock = StoppedClock;
+
+// Without `std` there is no time to read until a clock is set: it stays at
+// 0, so nothing expires by time.
+#[cfg(not(feature = "std"))]
+#[derive(Default)]
+pub(crate) struct StoppedClock;
+
+#[cfg(not(feature = "std"))]
+impl Clock for StoppedClock {
+    fn now_ns(&self) -> u128 {
+        0
+    }
+}
+
+impl<F: Fn() -> u128 + Send + Sync> Clock for F {
+    fn now_ns(&self) -> u128 {
+        self()
+    }
+}
+
+#[test]
+fn test_clock() {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::LocalCache;
+
+    let secs = Arc::new(AtomicU64::new(1_000));
+    let now = secs.clone();
+    let local_cache: LocalCache<usize> = LocalCache::builder()
+        .ttl(Duration::from_secs(10))
+        .clock(move || Duration::from_secs(now.load(Ordering::Relaxed)).as_nanos())
+        .build();
+    local_cache.put(String::from("x"), Arc::new(1));
+    secs.store(1_009, Ordering::Relaxed);
+    assert_eq!(Some(Arc::new(1)), local_cache.get("x"));
+    secs.store(1_011, Ordering::Relaxed);
+    assert_eq!(None, local_cache.get("x"));
+    // The lookup removed the expired entry.
+    assert_eq!(0, local_cache.evict_expired());
+    assert_eq!(1, local_cache.stats().expirations);
+}
@@ -0,0 +1,58 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+/// Converts values to and from the compact form kept for cold entries.
+pub trait ValueCodec<T>: Send + Sync {
+    /// Returning `None` keeps the value out of the cold tier and the stores:
+    /// it is evicted as if there were neither.
+    fn encode(&self, value: &T) -> Option<Vec<u8>>;
+    /// Returning `None` drops the entry as if it had never been cached.
+    fn decode(&self, bytes: &[u8]) -> Option<T>;
+}
+
+/// Configuration for the cold tier, see [`LocalCacheBuilder::cold_storage`](crate::LocalCacheBuilder::cold_storage).
+///
+/// Cold hits decode a fresh value every time; an entry that gets
+/// `promote_after` hits while cold is decoded once and moved back to the hot set.
+pub struct ColdStorage<T> {
+    pub(crate) codec: Arc<dyn ValueCodec<T>>,
+    pub(crate) max_entries: usize,
+    pub(crate) promote_hits: u32,
+}
+
+impl<T> ColdStorage<T> {
+    pub fn new<C: ValueCodec<T> + 'static>(codec: C, max_entries: usize) -> Self {
+        Self {
+            codec: Arc::new(codec),
+            max_entries,
+            promote_hits: 2,
+        }
+    }
+    pub fn promote_after(mut self, hits: u32) -> Self {
+        self.promote_hits = hits;
+        self
+    }
+}
+
+impl<T> Clone for ColdStorage<T> {
+    fn clone(&self) -> Self {
+        Self {
+            codec: self.codec.clone(),
+            max_entries: self.max_entries,
+            promote_hits: self.promote_hits,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+pub struct JsonCodec;
+
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize + serde::de::DeserializeOwned> ValueCodec<T> for JsonCodec {
+    fn encode(&self, value: &T) -> Option<Vec<u8>> {
+        serde_json::to_vec(value).ok()
+    }
+    fn decode(&self, bytes: &[u8]) -> Option<T> {
+        serde_json::from_slice(bytes).ok()
+    }
+}
@@ -0,0 +1,99 @@
+use std::hash::BuildHasher;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::{DefaultHashBuilder, Key, LocalCache};
+
+/// A value of a [`CompressedCache`]: its bytes, LZ4-compressed if they were
+/// longer than the cache's threshold and compressing made them smaller.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompressedBytes {
+    bytes: Box<[u8]>,
+    compressed: bool,
+}
+
+impl CompressedBytes {
+    /// Bytes held for the value, for
+    /// [`value_size`](crate::LocalCacheBuilder::value_size).
+    pub fn size(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.compressed
+    }
+
+    fn decompress(&self) -> Vec<u8> {
+        if !self.compressed {
+            return self.bytes.to_vec();
+        }
+        lz4_flex::decompress_size_prepended(&self.bytes).expect("compressed on insert")
+    }
+}
+
+/// A byte cache that compresses values longer than `threshold` on insert
+/// and decompresses them on every read.
+pub struct CompressedCache<S = DefaultHashBuilder> {
+    local_cache: LocalCache<CompressedBytes, S>,
+    threshold: usize,
+}
+
+impl<S: BuildHasher> CompressedCache<S> {
+    /// Wraps `local_cache`, which is configured as usual; give it
+    /// `.value_size(CompressedBytes::size)` to account compressed sizes.
+    pub fn new(local_cache: LocalCache<CompressedBytes, S>, threshold: usize) -> Self {
+        Self { local_cache, threshold }
+    }
+
+    /// The wrapped cache, for stats and anything not mirrored here.
+    pub fn cache(&self) -> &LocalCache<CompressedBytes, S> {
+        &self.local_cache
+    }
+
+    fn compress(&self, value: &[u8]) -> CompressedBytes {
+        if value.len() > self.threshold {
+            let compressed = lz4_flex::compress_prepend_size(value);
+            if compressed.len() < value.len() {
+                return CompressedBytes { bytes: compressed.into_boxed_slice(), compressed: true };
+            }
+        }
+        CompressedBytes { bytes: value.into(), compressed: false }
+    }
+
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        self.local_cache.get(key).map(|value| value.decompress())
+    }
+
+    pub fn put(&self, key: impl Into<Key>, value: &[u8]) {
+        self.local_cache.put(key, Arc::new(self.compress(value)))
+    }
+
+    pub fn put_with_ttl(&self, key: impl Into<Key>, value: &[u8], ttl: Duration) {
+        self.local_cache.put_with_ttl(key, Arc::new(self.compress(value)), ttl)
+    }
+
+    pub fn remove(&self, key: &str) -> Option<Vec<u8>> {
+        self.local_cache.remove(key).map(|value| value.decompress())
+    }
+}
+
+#[test]
+fn test_compressed_cache() {
+    let json = br#"{"id":1,"tags":["a","b"],"body":"lorem ipsum lorem ipsum lorem ipsum"}"#.repeat(20);
+    let local_cache = LocalCache::builder().value_size(CompressedBytes::size).build();
+    let compressed_cache = CompressedCache::new(local_cache, 64);
+    compressed_cache.put(String::from("big"), &json);
+    compressed_cache.put(String::from("small"), b"{}");
+    compressed_cache.put_with_ttl(String::from("noise"), &[7, 200, 13, 99, 1], Duration::from_secs(5));
+
+    let big = compressed_cache.cache().get("big").unwrap();
+    assert!(big.is_compressed());
+    assert!(big.size() * 3 < json.len());
+    assert!(!compressed_cache.cache().get("small").unwrap().is_compressed());
+    assert!(compressed_cache.cache().memory_usage() < json.len());
+
+    assert_eq!(Some(json.clone()), compressed_cache.get("big"));
+    assert_eq!(Some(b"{}".to_vec()), compressed_cache.get("small"));
+    assert_eq!(Some(vec![7, 200, 13, 99, 1]), compressed_cache.remove("noise"));
+    assert_eq!(None, compressed_cache.get("noise"));
+}
@@ -0,0 +1,132 @@
+use alloc::sync::Arc;
+use core::hash::BuildHasher;
+
+use crate::{remaining, ttl_ns, Key, LocalCache, Lookup};
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// Like [`put`](Self::put), but returns the live value it replaced.
+    /// Always applied directly, even with an insert queue.
+    pub fn replace(&self, key: impl Into<Key>, value: impl Into<Arc<T>>) -> Option<Arc<T>> {
+        let (key, value) = (key.into(), value.into());
+        let mut local_cache = self.lock(&key);
+        let previous = local_cache.peek(&key);
+        local_cache.put(key, Some(value));
+        previous
+    }
+
+    /// Inserts `value` unless `key` already has a live value, which is
+    /// returned instead.
+    pub fn put_if_absent(&self, key: impl Into<Key>, value: impl Into<Arc<T>>) -> Option<Arc<T>> {
+        let (key, value) = (key.into(), value.into());
+        let mut local_cache = self.lock(&key);
+        if let Lookup::Hit(existing) = local_cache.get(&key) {
+            return Some(existing);
+        }
+        local_cache.put(key, Some(value));
+        None
+    }
+
+    /// Replaces the value of `key` with `new` if it is still `expected` (the
+    /// same allocation, compared with [`Arc::ptr_eq`]); `None` expects no
+    /// live value. On failure returns the current value.
+    pub fn compare_and_swap(&self, key: impl Into<Key>, expected: Option<&Arc<T>>, new: Arc<T>) -> Result<(), Option<Arc<T>>> {
+        let key = key.into();
+        let mut local_cache = self.lock(&key);
+        let current = match local_cache.get(&key) {
+            Lookup::Hit(current) => Some(current),
+            Lookup::Negative | Lookup::Miss | Lookup::Corrupted => None,
+        };
+        match (&current, expected) {
+            (Some(current), Some(expected)) if Arc::ptr_eq(current, expected) => {}
+            (None, None) => {}
+            _ => return Err(current),
+        }
+        local_cache.put(key, Some(new));
+        Ok(())
+    }
+
+    /// Replaces the live value of `key` (or its absence) with `f`'s result,
+    /// all under the shard lock, so concurrent read-modify-writes don't lose
+    /// updates. `None` removes the entry, like [`remove`](Self::remove).
+    /// A replaced live value hands its expiry on to the new one, and the
+    /// read isn't counted in the stats. Returns the new value. `f` must not
+    /// use the cache itself.
+    pub fn compute<F>(&self, key: impl Into<Key>, f: F) -> Option<Arc<T>>
+    where
+        F: FnOnce(Option<Arc<T>>) -> Option<Arc<T>>,
+    {
+        let key = key.into();
+        let mut local_cache = self.lock(&key);
+        let current = match local_cache.find(&key) {
+            Lookup::Hit(current) => Some(current),
+            Lookup::Negative | Lookup::Miss | Lookup::Corrupted => None,
+        };
+        let had_value = current.is_some();
+        let now = local_cache.now();
+        let ttl_left = local_cache.map.get(&key).filter(|_| had_value).map(|e| ttl_ns(remaining(e.exp, now)));
+        match f(current) {
+            Some(new) => {
+                match ttl_left {
+                    Some(ttl) => local_cache.write(key, new.clone(), ttl),
+                    None => local_cache.put(key, Some(new.clone())),
+                }
+                Some(new)
+            }
+            None => {
+                if had_value {
+                    local_cache.take(&key);
+                }
+                None
+            }
+        }
+    }
+}
+
+#[test]
+fn test_replace() {
+    let local_cache: LocalCache<usize> = LocalCache::new(4, 360);
+    assert_eq!(None, local_cache.replace(String::from("x"), Arc::new(1)));
+    assert_eq!(Some(Arc::new(1)), local_cache.replace(String::from("x"), Arc::new(2)));
+    local_cache.put_negative(String::from("y"));
+    assert_eq!(None, local_cache.replace(String::from("y"), Arc::new(3)));
+    assert_eq!(Some(Arc::new(2)), local_cache.get("x"));
+}
+
+#[test]
+fn test_conditional_put() {
+    let local_cache: LocalCache<usize> = LocalCache::new(4, 360);
+    assert_eq!(None, local_cache.put_if_absent(String::from("x"), Arc::new(1)));
+    assert_eq!(Some(Arc::new(1)), local_cache.put_if_absent(String::from("x"), Arc::new(2)));
+
+    let current = local_cache.get("x").unwrap();
+    assert_eq!(Err(Some(current.clone())), local_cache.compare_and_swap(String::from("x"), Some(&Arc::new(1)), Arc::new(3)));
+    assert_eq!(Ok(()), local_cache.compare_and_swap(String::from("x"), Some(&current), Arc::new(3)));
+    assert_eq!(Err(Some(Arc::new(3))), local_cache.compare_and_swap(String::from("x"), None, Arc::new(4)));
+    assert_eq!(Ok(()), local_cache.compare_and_swap(String::from("y"), None, Arc::new(5)));
+    assert_eq!(Some(Arc::new(5)), local_cache.get("y"));
+}
+
+#[test]
+fn test_compute() {
+    let local_cache: LocalCache<usize> = LocalCache::builder().shards(4).build();
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                for _ in 0..100 {
+                    local_cache.compute(String::from("n"), |n| Some(Arc::new(n.map_or(1, |n| *n + 1))));
+                }
+            });
+        }
+    });
+    assert_eq!(Some(Arc::new(400)), local_cache.get("n"));
+    assert_eq!(1, local_cache.stats().hits);
+    assert_eq!(None, local_cache.compute(String::from("n"), |_| None));
+    assert_eq!(None, local_cache.get("n"));
+
+    // The new value expires when the old one would have.
+    local_cache.put_with_ttl("t", 1, std::time::Duration::from_millis(20));
+    local_cache.compute("t", |n| n.map(|n| Arc::new(*n + 1)));
+    assert_eq!(Some(Arc::new(2)), local_cache.get("t"));
+    std::thread::sleep(std::time::Duration::from_millis(30));
+    assert_eq!(None, local_cache.get("t"));
+}
@@ -0,0 +1,88 @@
+use std::hash::BuildHasher;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{InnerLocalCache, Key, LocalCache, DEFAULT_SOURCE};
+
+fn clock_ns(at: SystemTime) -> u128 {
+    at.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos()
+}
+
+impl<T, S: BuildHasher> InnerLocalCache<T, S> {
+    // Moves the live entry's expiry to `deadline`, in clock ns. Returns
+    // false if there is no such entry.
+    fn expire_at(&mut self, key: &str, deadline: u128) -> bool {
+        let now = self.now();
+        let Some(id) = self.map.find(key) else {
+            return false;
+        };
+        let entity = &mut self.map[id];
+        if now > entity.exp {
+            return false;
+        }
+        entity.deadline = deadline;
+        entity.ttl_ns = deadline.saturating_sub(now);
+        let exp = self.idle_ns.map_or(deadline, |idle_ns| deadline.min(now + idle_ns));
+        self.set_exp(id, exp);
+        true
+    }
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// Like [`put`](Self::put), expiring at `expires_at` by the cache's clock
+    /// rather than after a TTL. The expiry is set under the shard's lock, so
+    /// the insert skips the insert queue. An `expires_at` already passed
+    /// removes `key` instead, like [`remove`](Self::remove).
+    pub fn put_until(&self, key: impl Into<Key>, value: impl Into<Arc<T>>, expires_at: SystemTime) {
+        let key = key.into();
+        let value = value.into();
+        let deadline = clock_ns(expires_at);
+        let mut local_cache = self.lock(&key);
+        let now = local_cache.now();
+        if deadline <= now {
+            local_cache.take(&key);
+            return;
+        }
+        let ttl_ns = deadline - now;
+        local_cache.insert(key.clone(), Some(value), Some(ttl_ns), DEFAULT_SOURCE);
+        // The TTL was counted from a slightly earlier now.
+        local_cache.expire_at(&key, deadline);
+    }
+
+    /// Makes the live entry for `key` expire at `expires_at` instead. With
+    /// sliding expiration, reads then extend it by the time that was left.
+    /// Returns false if there is no such entry.
+    pub fn expire_at(&self, key: &str, expires_at: SystemTime) -> bool {
+        self.lock(key).expire_at(key, clock_ns(expires_at))
+    }
+}
+
+#[test]
+fn test_put_until() {
+    use std::time::Duration;
+
+    use crate::testing::Db;
+
+    let local_cache: LocalCache<usize> = LocalCache::new(4, 360);
+    let now = SystemTime::now();
+    local_cache.put_until(String::from("a"), Arc::new(1), now + Duration::from_secs(30));
+    let info = local_cache.get_entry_info("a").unwrap();
+    assert_eq!(now + Duration::from_secs(30), info.expires_at);
+    local_cache.put_until(String::from("b"), Arc::new(2), now - Duration::from_secs(1));
+    assert_eq!(None, local_cache.get("b"));
+
+    assert!(local_cache.expire_at("a", now + Duration::from_secs(1000)));
+    assert_eq!(now + Duration::from_secs(1000), local_cache.get_entry_info("a").unwrap().expires_at);
+    assert!(local_cache.expire_at("a", now + Duration::from_millis(5)));
+    std::thread::sleep(Duration::from_millis(10));
+    assert_eq!(None, local_cache.get("a"));
+    assert!(!local_cache.expire_at("a", now + Duration::from_secs(1)));
+
+    // A deadline already passed removes the key everywhere.
+    let db = Db::default();
+    let local_cache: LocalCache<usize> = LocalCache::builder().backing_store(db.clone()).build();
+    local_cache.put("b", 2);
+    local_cache.put_until("b", 3, now - Duration::from_secs(1));
+    assert_eq!(None, local_cache.get("b"));
+    assert_eq!(None, db.0.lock().unwrap().get("b"));
+}
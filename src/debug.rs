@@ -0,0 +1,63 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::BuildHasher;
+use core::time::Duration;
+
+use crate::{remaining, LocalCache};
+
+// Keys listed by `Debug`.
+const KEYS_SHOWN: usize = 8;
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    fn summary(&self) -> (usize, usize, Duration) {
+        let (mut entries, mut max_entries, mut ttl_ns) = (0, 0, 0);
+        for shard in self.shards.iter() {
+            let local_cache = shard.lock();
+            entries += local_cache.map.len();
+            max_entries += local_cache.max_numbers;
+            ttl_ns = local_cache.max_age_ns;
+        }
+        (entries, max_entries, remaining(ttl_ns, 0))
+    }
+}
+
+/// Sizes, the default TTL and the first few keys; values aren't shown.
+impl<T, S: BuildHasher> fmt::Debug for LocalCache<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (entries, max_entries, ttl) = self.summary();
+        let mut keys = Vec::with_capacity(KEYS_SHOWN);
+        for shard in self.shards.iter() {
+            let local_cache = shard.lock();
+            keys.extend(local_cache.map.keys().take(KEYS_SHOWN - keys.len()).map(String::from));
+        }
+        f.debug_struct("LocalCache")
+            .field("entries", &entries)
+            .field("max_entries", &max_entries)
+            .field("ttl", &ttl)
+            .field("shards", &self.shards.len())
+            .field("keys", &keys)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T, S: BuildHasher> fmt::Display for LocalCache<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (entries, max_entries, ttl) = self.summary();
+        write!(f, "LocalCache({}/{} entries, ttl {:?})", entries, max_entries, ttl)
+    }
+}
+
+#[test]
+fn test_debug() {
+    use std::sync::Arc;
+
+    struct Opaque;
+    let local_cache: LocalCache<Opaque> = LocalCache::new(4, 60);
+    local_cache.put(String::from("a"), Arc::new(Opaque));
+    assert_eq!(
+        r#"LocalCache { entries: 1, max_entries: 4, ttl: 60s, shards: 1, keys: ["a"], .. }"#,
+        format!("{:?}", local_cache)
+    );
+    assert_eq!("LocalCache(1/4 entries, ttl 60s)", local_cache.to_string());
+}
@@ -0,0 +1,165 @@
+use alloc::sync::Arc;
+use core::hash::BuildHasher;
+use core::time::Duration;
+
+use crate::{remaining, ttl_ns, DefaultHashBuilder, InnerLocalCache, Key, LocalCache, Lookup, ShardGuard};
+
+/// A view into one key of the cache, see [`LocalCache::entry`]. Holds the
+/// key's shard lock until dropped, so everything done through it is atomic.
+pub enum Entry<'a, T, S = DefaultHashBuilder> {
+    Occupied(OccupiedEntry<'a, T, S>),
+    Vacant(VacantEntry<'a, T, S>),
+}
+
+pub struct OccupiedEntry<'a, T, S = DefaultHashBuilder> {
+    local_cache: ShardGuard<'a, InnerLocalCache<T, S>>,
+    key: Key,
+    value: Arc<T>,
+}
+
+pub struct VacantEntry<'a, T, S = DefaultHashBuilder> {
+    local_cache: ShardGuard<'a, InnerLocalCache<T, S>>,
+    key: Key,
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// The entry for `key`, for in-place insert-or-update flows. Negative
+    /// and expired entries are vacant. The shard stays locked until the
+    /// entry is dropped; don't use the cache in the meantime.
+    pub fn entry(&self, key: impl Into<Key>) -> Entry<'_, T, S> {
+        let key = key.into();
+        let mut local_cache = self.lock(&key);
+        match local_cache.get(&key) {
+            Lookup::Hit(value) => Entry::Occupied(OccupiedEntry { local_cache, key, value }),
+            Lookup::Negative | Lookup::Miss | Lookup::Corrupted => Entry::Vacant(VacantEntry { local_cache, key }),
+        }
+    }
+}
+
+impl<'a, T, S: BuildHasher> Entry<'a, T, S> {
+    pub fn key(&self) -> &str {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Returns the value, inserting `value` first if vacant.
+    pub fn or_insert(self, value: impl Into<Arc<T>>) -> Arc<T> {
+        self.or_insert_with(|| value.into())
+    }
+
+    /// Returns the value, inserting `f()` first if vacant.
+    pub fn or_insert_with<F: FnOnce() -> Arc<T>>(self, f: F) -> Arc<T> {
+        match self {
+            Entry::Occupied(entry) => entry.value,
+            Entry::Vacant(entry) => entry.insert(f()),
+        }
+    }
+
+    /// Replaces an occupied entry's value with `f(value)`, keeping its
+    /// remaining TTL.
+    pub fn and_modify<F: FnOnce(&Arc<T>) -> Arc<T>>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                let value = f(&entry.value);
+                let ttl = entry.ttl();
+                entry.insert_with_ttl(value, ttl);
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+}
+
+impl<T, S: BuildHasher> OccupiedEntry<'_, T, S> {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn get(&self) -> &Arc<T> {
+        &self.value
+    }
+
+    /// Time left until the entry expires.
+    pub fn ttl(&self) -> Duration {
+        let now = self.local_cache.now();
+        // Gone only if an insert through this entry was evicted immediately.
+        let exp = self.local_cache.map.get(&self.key).map_or(0, |e| e.exp);
+        remaining(exp, now)
+    }
+
+    /// Restarts the entry's TTL at `ttl` from now.
+    pub fn set_ttl(&mut self, ttl: Duration) {
+        let now = self.local_cache.now();
+        let Some(id) = self.local_cache.map.find(&self.key) else {
+            return;
+        };
+        let entity = &mut self.local_cache.map[id];
+        entity.ttl_ns = ttl_ns(ttl);
+        entity.deadline = now.saturating_add(entity.ttl_ns);
+        let deadline = entity.deadline;
+        self.local_cache.set_exp(id, deadline);
+    }
+
+    /// Replaces the value with the default TTL, returning the old one.
+    pub fn insert(&mut self, value: impl Into<Arc<T>>) -> Arc<T> {
+        let value = value.into();
+        self.local_cache.put(self.key.clone(), Some(value.clone()));
+        core::mem::replace(&mut self.value, value)
+    }
+
+    /// Replaces the value with `ttl`, returning the old one.
+    pub fn insert_with_ttl(&mut self, value: impl Into<Arc<T>>, ttl: Duration) -> Arc<T> {
+        let value = value.into();
+        self.local_cache.write(self.key.clone(), value.clone(), ttl_ns(ttl));
+        core::mem::replace(&mut self.value, value)
+    }
+
+    /// Removes the entry, like [`LocalCache::remove`], returning its value.
+    pub fn remove(mut self) -> Arc<T> {
+        self.local_cache.take(&self.key);
+        self.value
+    }
+}
+
+impl<T, S: BuildHasher> VacantEntry<'_, T, S> {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn insert(self, value: impl Into<Arc<T>>) -> Arc<T> {
+        let value = value.into();
+        let Self { mut local_cache, key } = self;
+        local_cache.put(key, Some(value.clone()));
+        value
+    }
+
+    pub fn insert_with_ttl(self, value: impl Into<Arc<T>>, ttl: Duration) -> Arc<T> {
+        let value = value.into();
+        let Self { mut local_cache, key } = self;
+        local_cache.write(key, value.clone(), ttl_ns(ttl));
+        value
+    }
+}
+
+#[test]
+fn test_entry() {
+    let local_cache: LocalCache<usize> = LocalCache::new(4, 360);
+    assert_eq!(Arc::new(1), local_cache.entry(String::from("x")).or_insert(Arc::new(1)));
+    assert_eq!(Arc::new(1), local_cache.entry(String::from("x")).or_insert_with(|| Arc::new(2)));
+    let value = local_cache.entry(String::from("x")).and_modify(|n| Arc::new(**n + 10)).or_insert(Arc::new(0));
+    assert_eq!(Arc::new(11), value);
+
+    match local_cache.entry(String::from("x")) {
+        Entry::Occupied(mut entry) => {
+            assert!(entry.ttl() > Duration::from_secs(350));
+            entry.set_ttl(Duration::from_secs(5));
+            assert!(entry.ttl() <= Duration::from_secs(5));
+            assert_eq!(Arc::new(11), entry.remove());
+        }
+        Entry::Vacant(_) => unreachable!(),
+    }
+    assert!(matches!(local_cache.entry(String::from("x")), Entry::Vacant(_)));
+    assert_eq!(None, local_cache.get("x"));
+}
@@ -0,0 +1,19 @@
+use core::fmt;
+
+/// Error returned by the fallible insert methods, such as
+/// [`LocalCache::try_put`](crate::LocalCache::try_put).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheError {
+    /// Memory for the new entry couldn't be allocated.
+    AllocFailed,
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheError::AllocFailed => f.write_str("cache entry allocation failed"),
+        }
+    }
+}
+
+impl core::error::Error for CacheError {}
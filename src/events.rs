@@ -0,0 +1,184 @@
+use std::hash::BuildHasher;
+use std::sync::mpsc::{self, Receiver, TrySendError};
+use std::sync::Arc;
+
+use crate::slab::NodeId;
+use crate::{InnerLocalCache, LocalCache, Slot};
+
+// Events a subscriber may fall behind by before further ones are dropped.
+const SUBSCRIBER_CAPACITY: usize = 1024;
+
+// Which keys a subscriber is sent events of, see `LocalCache::subscribe_filtered`.
+pub(crate) type KeyFilter = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// A change to the cache, see [`LocalCache::subscribe`]. `value` is `None`
+/// for negative and cold entries.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CacheEvent<T> {
+    /// An entry was inserted for a key that had none.
+    Insert { key: String, value: Option<Arc<T>> },
+    /// An entry replaced the one already cached for its key.
+    Update { key: String, value: Option<Arc<T>> },
+    /// An entry was removed to make room.
+    Evict { key: String },
+    /// An expired entry was cleaned up.
+    Expire { key: String },
+}
+
+impl<T> CacheEvent<T> {
+    pub fn key(&self) -> &str {
+        match self {
+            Self::Insert { key, .. } | Self::Update { key, .. } | Self::Evict { key } | Self::Expire { key } => key,
+        }
+    }
+}
+
+impl<T> Clone for CacheEvent<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Insert { key, value } => Self::Insert { key: key.clone(), value: value.clone() },
+            Self::Update { key, value } => Self::Update { key: key.clone(), value: value.clone() },
+            Self::Evict { key } => Self::Evict { key: key.clone() },
+            Self::Expire { key } => Self::Expire { key: key.clone() },
+        }
+    }
+}
+
+impl<T, S> InnerLocalCache<T, S> {
+    // Sends an event to every subscriber with room for it whose filter it
+    // passes, forgetting those that went away. The event is only built if
+    // someone is listening.
+    pub(crate) fn emit(&mut self, event: impl FnOnce() -> CacheEvent<T>) {
+        if self.subscribers.is_empty() {
+            return;
+        }
+        let event = event();
+        self.subscribers.retain(|(subscriber, filter)| {
+            if filter.as_ref().is_some_and(|filter| !filter(event.key())) {
+                return true;
+            }
+            !matches!(subscriber.try_send(event.clone()), Err(TrySendError::Disconnected(_)))
+        });
+    }
+
+    pub(crate) fn emit_insert(&mut self, id: NodeId, replaced: bool) {
+        if self.subscribers.is_empty() {
+            return;
+        }
+        let entity = &self.map[id];
+        let key = entity.key.to_string();
+        let value = match &entity.value {
+            Slot::Hot(value) => Some(value.clone()),
+            Slot::Weak(value) => value.upgrade(),
+            Slot::Cold(_) | Slot::Negative => None,
+        };
+        self.emit(|| {
+            if replaced {
+                CacheEvent::Update { key, value }
+            } else {
+                CacheEvent::Insert { key, value }
+            }
+        });
+    }
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// A channel receiving every insert, update, eviction and expiry from now
+    /// on. Events of one key arrive in order. Sending never blocks the cache:
+    /// while 1024 events are waiting, further ones are dropped for this
+    /// subscriber. Dropping the receiver unsubscribes.
+    pub fn subscribe(&self) -> Receiver<CacheEvent<T>> {
+        self.add_subscriber(None)
+    }
+
+    /// Like [`subscribe`](Self::subscribe), for the keys matching `pattern`,
+    /// in which `*` stands for any run of characters, as in `"user:*"`.
+    pub fn subscribe_matching(&self, pattern: &str) -> Receiver<CacheEvent<T>> {
+        let pattern = pattern.to_string();
+        self.subscribe_filtered(move |key| glob_match(&pattern, key))
+    }
+
+    /// Like [`subscribe`](Self::subscribe), for the keys `filter` accepts.
+    /// It is called under the shard's lock before each event is sent; only
+    /// a failed send unsubscribes, so a receiver dropped while no matching
+    /// events come is forgotten at the next one.
+    pub fn subscribe_filtered<F: Fn(&str) -> bool + Send + Sync + 'static>(&self, filter: F) -> Receiver<CacheEvent<T>> {
+        self.add_subscriber(Some(Arc::new(filter)))
+    }
+
+    fn add_subscriber(&self, filter: Option<KeyFilter>) -> Receiver<CacheEvent<T>> {
+        let (sender, receiver) = mpsc::sync_channel(SUBSCRIBER_CAPACITY);
+        for shard in self.shards.iter() {
+            shard.lock().subscribers.push((sender.clone(), filter.clone()));
+        }
+        receiver
+    }
+}
+
+// Whether `key` matches `pattern`, `*` matching any run of characters.
+fn glob_match(pattern: &str, key: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = key.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts: Vec<_> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // No `*` at all.
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[test]
+fn test_subscribe() {
+    use std::time::Duration;
+
+    let local_cache: LocalCache<usize> = LocalCache::new(1, 360);
+    let events = local_cache.subscribe();
+    local_cache.put(String::from("a"), Arc::new(1));
+    local_cache.put(String::from("a"), Arc::new(2));
+    local_cache.put_with_ttl(String::from("b"), Arc::new(3), Duration::from_millis(5));
+    std::thread::sleep(Duration::from_millis(10));
+    local_cache.evict_expired();
+    let received: Vec<_> = events.try_iter().collect();
+    assert_eq!(
+        vec![
+            CacheEvent::Insert { key: String::from("a"), value: Some(Arc::new(1)) },
+            CacheEvent::Update { key: String::from("a"), value: Some(Arc::new(2)) },
+            CacheEvent::Evict { key: String::from("a") },
+            CacheEvent::Insert { key: String::from("b"), value: Some(Arc::new(3)) },
+            CacheEvent::Expire { key: String::from("b") },
+        ],
+        received
+    );
+
+    drop(events);
+    local_cache.put(String::from("c"), Arc::new(4));
+    assert!(local_cache.shards[0].lock().subscribers.is_empty());
+}
+
+#[test]
+fn test_subscribe_matching() {
+    assert!(glob_match("user:*", "user:42"));
+    assert!(glob_match("*:name", "user:42:name"));
+    assert!(glob_match("a*b*c", "aXbYbc"));
+    assert!(!glob_match("a*b*c", "abcb"));
+    assert!(!glob_match("user", "user:1"));
+
+    let local_cache: LocalCache<usize> = LocalCache::builder().shards(2).build();
+    let users = local_cache.subscribe_matching("user:*");
+    let odd = local_cache.subscribe_filtered(|key| key.len() % 2 == 1);
+    local_cache.put(String::from("user:1"), Arc::new(1));
+    local_cache.put(String::from("order:1"), Arc::new(2));
+    local_cache.remove("user:1");
+    let keys = |events: &Receiver<CacheEvent<usize>>| events.try_iter().map(|event| event.key().to_string()).collect::<Vec<_>>();
+    assert_eq!(vec!["user:1"], keys(&users));
+    assert_eq!(vec!["order:1"], keys(&odd));
+}
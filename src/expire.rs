@@ -0,0 +1,64 @@
+use std::hash::BuildHasher;
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use crate::{InnerLocalCache, LocalCacheBuilder, ShardLock};
+
+pub(crate) type ExpireHook<T> = Arc<dyn Fn(String, Arc<T>) + Send + Sync>;
+
+// Starts the thread calling `hook` and points every shard at it. It stops
+// once the last shard, and with it the last sender, is dropped.
+fn spawn<T: Send + Sync + 'static, S>(shards: &Arc<[ShardLock<InnerLocalCache<T, S>>]>, hook: ExpireHook<T>) {
+    let (sender, receiver) = mpsc::channel::<(String, Arc<T>)>();
+    for shard in shards.iter() {
+        shard.lock().on_expire = Some(sender.clone());
+    }
+    thread::Builder::new()
+        .name("local-cache-expire".to_string())
+        .spawn(move || {
+            for (key, value) in receiver {
+                hook(key, value);
+            }
+        })
+        .expect("failed to spawn expiry hook thread");
+}
+
+impl<T: Send + Sync + 'static, S: BuildHasher> LocalCacheBuilder<T, S> {
+    /// Calls `f` with the key and last value of every entry the expiry sweep
+    /// removes, on a thread of its own so the cache is never blocked on it.
+    /// Entries replaced, removed or evicted for capacity before the sweep
+    /// gets to them don't count, and neither do negative ones. For async
+    /// cleanup, spawn it onto a runtime from `f`, e.g. through a
+    /// `tokio::runtime::Handle`.
+    pub fn on_expire<F: Fn(String, Arc<T>) + Send + Sync + 'static>(mut self, f: F) -> Self {
+        self.on_expire = Some((Arc::new(f), spawn));
+        self
+    }
+}
+
+#[test]
+fn test_on_expire() {
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use crate::{lock, LocalCache};
+
+    let (sender, expired) = mpsc::channel();
+    let sender = Mutex::new(sender);
+    let local_cache: LocalCache<usize> = LocalCache::builder()
+        .ttl(Duration::from_millis(5))
+        .max_entries(2)
+        .on_expire(move |key, value| lock(&sender).send((key, *value)).unwrap())
+        .build();
+    local_cache.put(String::from("a"), Arc::new(1));
+    local_cache.put(String::from("b"), Arc::new(2));
+    local_cache.put(String::from("b"), Arc::new(3));
+    local_cache.put_negative(String::from("n"));
+    local_cache.put(String::from("c"), Arc::new(4));
+    thread::sleep(Duration::from_millis(10));
+    local_cache.evict_expired();
+    let mut received: Vec<_> = expired.recv_timeout(Duration::from_secs(5)).into_iter().collect();
+    received.extend(expired.recv_timeout(Duration::from_millis(100)));
+    received.sort();
+    assert_eq!(vec![(String::from("c"), 4)], received);
+}
@@ -0,0 +1,71 @@
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::hash::BuildHasher;
+use core::time::Duration;
+
+use crate::{remaining, ttl_ns, LocalCache, Slot};
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// All live entries with the TTL each has left, shard by shard and
+    /// coldest first, taken while holding every shard's lock. Values are shared, not cloned, and
+    /// negative entries are left out. See [`import`](Self::import).
+    pub fn export(&self) -> Vec<(String, Arc<T>, Duration)> {
+        let shards: Vec<_> = self.shards.iter().map(|shard| shard.lock()).collect();
+        let now = self.clock.now_ns();
+        let mut entries = Vec::with_capacity(shards.iter().map(|s| s.map.len()).sum());
+        for (local_cache, tail) in shards.iter().flat_map(|s| s.lru_tails().map(|tail| (s, tail))) {
+            let mut cur = tail;
+            while let Some(e) = cur {
+                let b = &local_cache.map[e];
+                cur = b.lru_prev;
+                if b.exp <= now {
+                    continue;
+                }
+                let value = match &b.value {
+                    Slot::Hot(value) => Some(value.clone()),
+                    Slot::Weak(value) => value.upgrade(),
+                    Slot::Cold(bytes) => local_cache.cold.as_ref().and_then(|cold| cold.codec.decode(bytes)).map(Arc::new),
+                    Slot::Negative => None,
+                };
+                if let Some(value) = value {
+                    entries.push((b.key.to_string(), value, remaining(b.exp, now)));
+                }
+            }
+        }
+        entries
+    }
+
+    /// Inserts entries from [`export`](Self::export), e.g. of another cache,
+    /// each with the TTL given, in order. Like [`warm`](Self::warm), they
+    /// only go into memory. Returns the number inserted.
+    pub fn import<I: IntoIterator<Item = (String, Arc<T>, Duration)>>(&self, entries: I) -> usize {
+        let mut imported = 0;
+        for (key, value, ttl) in entries {
+            let mut local_cache = self.lock(&key);
+            local_cache.fill(key.into(), Some(value), ttl_ns(ttl), "import");
+            imported += 1;
+        }
+        imported
+    }
+}
+
+#[test]
+fn test_export_import() {
+    let blue: LocalCache<usize> = LocalCache::builder().shards(2).ttl(Duration::from_secs(60)).build();
+    for i in 0..5 {
+        blue.put(i.to_string(), Arc::new(i));
+    }
+    blue.put_negative(String::from("n"));
+    let exported = blue.export();
+    assert_eq!(5, exported.len());
+    assert!(exported.iter().all(|(_, _, ttl)| *ttl <= Duration::from_secs(60) && *ttl > Duration::from_secs(59)));
+
+    let green: LocalCache<usize> = LocalCache::new(16, 1);
+    assert_eq!(5, green.import(exported));
+    for i in 0..5 {
+        assert_eq!(Some(Arc::new(i)), green.get(&i.to_string()));
+    }
+    assert_eq!(Some("import"), green.source("4"));
+    assert!(green.get_entry_info("4").unwrap().expires_at > green.get_entry_info("4").unwrap().inserted_at + Duration::from_secs(59));
+}
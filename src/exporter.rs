@@ -0,0 +1,114 @@
+use std::hash::BuildHasher;
+
+use crate::{LocalCache, LocalCacheBuilder};
+
+impl<T, S> LocalCacheBuilder<T, S> {
+    /// Labels the metrics published by [`LocalCache::publish_metrics`] with
+    /// `cache = name`. Unnamed caches are labelled `cache = "default"`.
+    pub fn metrics_name(mut self, name: impl Into<String>) -> Self {
+        self.metrics_name = Some(name.into());
+        self
+    }
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// Publishes [`stats`](Self::stats) to the installed `metrics` recorder
+    /// (a Prometheus exporter, say): the `local_cache_hits_total`,
+    /// `_misses_total`, `_inserts_total`, `_evictions_total` and
+    /// `_expirations_total` counters, and the `local_cache_entries` and
+    /// `local_cache_max_entries` gauges, all labelled with the cache's
+    /// [name](LocalCacheBuilder::metrics_name).
+    pub fn publish_metrics(&self) {
+        let stats = self.stats();
+        let name = self.metrics_name.as_deref().unwrap_or("default");
+        let counters = [
+            ("local_cache_hits_total", stats.hits),
+            ("local_cache_misses_total", stats.misses),
+            ("local_cache_inserts_total", stats.inserts),
+            ("local_cache_evictions_total", stats.evictions),
+            ("local_cache_expirations_total", stats.expirations),
+        ];
+        for (metric, value) in counters {
+            metrics::counter!(metric, "cache" => name.to_string()).absolute(value);
+        }
+        metrics::gauge!("local_cache_entries", "cache" => name.to_string()).set(stats.entries as f64);
+        metrics::gauge!("local_cache_max_entries", "cache" => name.to_string()).set(stats.max_entries as f64);
+    }
+}
+
+#[test]
+fn test_publish_metrics() {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use metrics::{Counter, CounterFn, Gauge, GaugeFn, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit};
+
+    // Keeps the last value of every metric, by name and labels.
+    #[derive(Default)]
+    struct Values(Mutex<HashMap<String, Arc<AtomicU64>>>);
+
+    struct Value(Arc<AtomicU64>);
+
+    impl CounterFn for Value {
+        fn increment(&self, value: u64) {
+            self.0.fetch_add(value, Ordering::Relaxed);
+        }
+        fn absolute(&self, value: u64) {
+            self.0.fetch_max(value, Ordering::Relaxed);
+        }
+    }
+
+    impl GaugeFn for Value {
+        fn increment(&self, _: f64) {}
+        fn decrement(&self, _: f64) {}
+        fn set(&self, value: f64) {
+            self.0.store(value as u64, Ordering::Relaxed);
+        }
+    }
+
+    impl Values {
+        fn value(&self, key: &Key) -> Arc<Value> {
+            let labels: Vec<_> = key.labels().map(|label| format!("{}={}", label.key(), label.value())).collect();
+            let name = format!("{}{{{}}}", key.name(), labels.join(","));
+            Arc::new(Value(self.0.lock().unwrap().entry(name).or_default().clone()))
+        }
+        fn get(&self, name: &str) -> u64 {
+            self.0.lock().unwrap()[name].load(Ordering::Relaxed)
+        }
+    }
+
+    impl Recorder for Values {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            Counter::from_arc(self.value(key))
+        }
+        fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::from_arc(self.value(key))
+        }
+        fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    let local_cache: LocalCache<usize> = LocalCache::builder().max_entries(2).metrics_name("sessions").build();
+    for i in 0..3 {
+        local_cache.put(i.to_string(), Arc::new(i));
+    }
+    local_cache.get("2");
+    local_cache.get("0");
+    let values = Values::default();
+    metrics::with_local_recorder(&values, || local_cache.publish_metrics());
+    assert_eq!(1, values.get("local_cache_hits_total{cache=sessions}"));
+    assert_eq!(1, values.get("local_cache_misses_total{cache=sessions}"));
+    assert_eq!(3, values.get("local_cache_inserts_total{cache=sessions}"));
+    assert_eq!(1, values.get("local_cache_evictions_total{cache=sessions}"));
+    assert_eq!(2, values.get("local_cache_entries{cache=sessions}"));
+    assert_eq!(2, values.get("local_cache_max_entries{cache=sessions}"));
+
+    let local_cache: LocalCache<usize> = LocalCache::new(4, 360);
+    metrics::with_local_recorder(&values, || local_cache.run_maintenance(16));
+    assert_eq!(4, values.get("local_cache_max_entries{cache=default}"));
+}
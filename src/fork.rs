@@ -0,0 +1,152 @@
+use alloc::sync::Arc;
+use core::hash::BuildHasher;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use crate::{CacheEntity, HashMap, InnerLocalCache, LocalCache};
+
+impl<T, S: BuildHasher + Clone> InnerLocalCache<T, S> {
+    // Copies every node under the id it has here, so the links into and
+    // between the copies need no changes and list order, expiry order and
+    // segment counts carry over as they are.
+    fn fork(&self) -> Self {
+        let map = self.map.fork(|entity| CacheEntity {
+            key: entity.key.clone(),
+            value: entity.value.clone(),
+            tags: entity.tags.clone(),
+            ..*entity
+        });
+        Self {
+            max_numbers: self.max_numbers,
+            max_age_ns: self.max_age_ns,
+            negative_ttl_ns: self.negative_ttl_ns,
+            negative_caching: self.negative_caching,
+            idle_ns: self.idle_ns,
+            sliding: self.sliding,
+            sweep_on_read: self.sweep_on_read,
+            clock: self.clock.clone(),
+            cold: self.cold.clone(),
+            cold_len: self.cold_len,
+            #[cfg(feature = "std")]
+            store: self.store.clone(),
+            backing: self.backing.clone(),
+            checksum: self.checksum,
+            value_size: self.value_size,
+            max_bytes: self.max_bytes,
+            bytes: self.bytes,
+            weak_values: self.weak_values,
+            policy: self.policy,
+            lfu: self.lfu.clone(),
+            tick: self.tick,
+            pool: self.pool.clone(),
+            sketch: self.sketch.clone(),
+            window_len: self.window_len,
+            window_max: self.window_max,
+            protected_len: self.protected_len,
+            protected_max: self.protected_max,
+            lru_head: self.lru_head,
+            lru_tail: self.lru_tail,
+            window_head: self.window_head,
+            window_tail: self.window_tail,
+            protected_head: self.protected_head,
+            protected_tail: self.protected_tail,
+            pinned_head: self.pinned_head,
+            pinned_tail: self.pinned_tail,
+            low_head: self.low_head,
+            low_tail: self.low_tail,
+            high_head: self.high_head,
+            high_tail: self.high_tail,
+            pinned_len: self.pinned_len,
+            pinned_count: self.pinned_count,
+            cold_head: self.cold_head,
+            cold_tail: self.cold_tail,
+            wheel: self.wheel.clone(),
+            map,
+            prefix_index: self.prefix_index.clone(),
+            tags: self.tags.clone(),
+            leases: HashMap::new(),
+            counters: self.counters,
+            #[cfg(feature = "std")]
+            repair: None,
+            evicted: None,
+            #[cfg(feature = "std")]
+            subscribers: Vec::new(),
+            #[cfg(feature = "std")]
+            bus: None,
+            #[cfg(feature = "std")]
+            on_expire: self.on_expire.clone(),
+            #[cfg(feature = "std")]
+            write_behind: None,
+            front_generation: None,
+            promotions: self.promotions.as_ref().map(|promotions| Arc::new(promotions.fork())),
+            #[cfg(feature = "read-mostly")]
+            snapshot: None,
+            #[cfg(feature = "read-mostly")]
+            stale_reads: 0,
+            #[cfg(feature = "tracing")]
+            trace_keys: self.trace_keys,
+        }
+    }
+}
+
+impl<T, S: BuildHasher + Clone> LocalCache<T, S> {
+    /// An independent copy of the cache: the same entries, expiry times, LRU
+    /// order, settings and stats. Values are shared, not cloned, and so are
+    /// the spill and backing stores. The copy has no insert queue, read
+    /// repair thread, invalidation bus or leases of its own, and writes to
+    /// the backing store directly; expired entries go to the same
+    /// [`on_expire`](crate::LocalCacheBuilder::on_expire) hook.
+    pub fn fork(&self) -> Self {
+        let shards: Arc<[_]> = self.shards.iter().map(|shard| shard.sibling(shard.lock().fork())).collect();
+        #[cfg(feature = "std")]
+        let front = self.front.as_ref().map(|front| front.fork(&shards));
+        #[cfg(feature = "read-mostly")]
+        let snapshots = self.snapshots.as_ref().map(|_| crate::read_mostly::ReadSnapshot::attach(&shards));
+        LocalCache {
+            shards,
+            max_numbers: AtomicUsize::new(self.max_numbers.load(Ordering::Relaxed)),
+            shard_weights: self.shard_weights.clone(),
+            router: self.router.clone(),
+            clock: self.clock.clone(),
+            lease_ids: AtomicU64::new(0),
+            created: self.created,
+            #[cfg(feature = "std")]
+            queues: None,
+            #[cfg(feature = "std")]
+            _repair: None,
+            #[cfg(feature = "std")]
+            invalidation: None,
+            #[cfg(feature = "std")]
+            write_behind: None,
+            loader: self.loader.clone(),
+            async_loader: self.async_loader.clone(),
+            #[cfg(feature = "std")]
+            front,
+            #[cfg(feature = "read-mostly")]
+            snapshots,
+            #[cfg(feature = "metrics")]
+            metrics_name: self.metrics_name.clone(),
+            #[cfg(feature = "tracing")]
+            trace_keys: self.trace_keys,
+        }
+    }
+}
+
+#[test]
+fn test_fork() {
+    use std::sync::Arc;
+
+    let local_cache: LocalCache<usize> = LocalCache::new(3, 360);
+    for i in 0..3 {
+        local_cache.put(i.to_string(), Arc::new(i));
+    }
+    local_cache.get("0");
+
+    let fork = local_cache.fork();
+    local_cache.remove("0");
+    assert_eq!(Some(Arc::new(0)), fork.get("0"));
+    // "1" is least recently used in the fork too.
+    fork.put(String::from("x"), Arc::new(10));
+    assert_eq!(None, fork.get("1"));
+    assert_eq!(Some(Arc::new(2)), fork.get("2"));
+    assert_eq!(Some(Arc::new(1)), local_cache.get("1"));
+}
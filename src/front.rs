@@ -0,0 +1,208 @@
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use crate::{ttl_ns, InnerLocalCache, LocalCache, LocalCacheBuilder, Lookup, ShardLock};
+
+// Tells caches apart in each thread's front maps.
+static FRONT_IDS: AtomicU64 = AtomicU64::new(0);
+
+// A thread's front map of a cache, type erased, with a handle that dies
+// with the cache.
+type ErasedMap = (Weak<()>, Box<dyn Any>);
+
+thread_local! {
+    // This thread's front maps, by cache id.
+    static FRONTS: RefCell<HashMap<u64, ErasedMap>> = RefCell::new(HashMap::new());
+}
+
+/// Sizing of the per-thread front cache, see
+/// [`LocalCacheBuilder::thread_local_front`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThreadLocalFront {
+    capacity: usize,
+    ttl: Duration,
+}
+
+impl ThreadLocalFront {
+    /// Keeps up to `capacity` entries per thread, each for at most `ttl`.
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self { capacity: capacity.max(1), ttl }
+    }
+}
+
+struct FrontEntry<T> {
+    value: Arc<T>,
+    // The entry's expiry time or the end of its `ttl`, whichever is first.
+    until: u128,
+    // Its shard's generation when it was copied.
+    generation: u64,
+}
+
+type FrontMap<T> = HashMap<String, FrontEntry<T>>;
+
+// Runs a closure on the calling thread's map of a front; a fn pointer made
+// for a `T: 'static` by the builder, as `Any` needs.
+type WithMap<T> = fn(&Front<T>, &mut dyn FnMut(&mut FrontMap<T>));
+
+pub(crate) struct Front<T> {
+    id: u64,
+    config: ThreadLocalFront,
+    // One per shard, bumped by every change to it.
+    generations: Vec<Arc<AtomicU64>>,
+    alive: Arc<()>,
+    with_map: WithMap<T>,
+}
+
+impl<T> Front<T> {
+    fn attach_with<S>(shards: &[ShardLock<InnerLocalCache<T, S>>], config: ThreadLocalFront, with_map: WithMap<T>) -> Self {
+        let generations: Vec<_> = shards.iter().map(|_| Arc::new(AtomicU64::new(0))).collect();
+        for (shard, generation) in shards.iter().zip(&generations) {
+            shard.lock().front_generation = Some(generation.clone());
+        }
+        Self { id: FRONT_IDS.fetch_add(1, Ordering::Relaxed), config, generations, alive: Arc::new(()), with_map }
+    }
+
+    // A front of the same size for a copy of the cache, see `LocalCache::fork`.
+    pub(crate) fn fork<S>(&self, shards: &[ShardLock<InnerLocalCache<T, S>>]) -> Self {
+        Self::attach_with(shards, self.config, self.with_map)
+    }
+}
+
+impl<T: 'static> Front<T> {
+    pub(crate) fn attach<S>(shards: &Arc<[ShardLock<InnerLocalCache<T, S>>]>, config: ThreadLocalFront) -> Self {
+        Self::attach_with(shards, config, with_map::<T>)
+    }
+}
+
+fn with_map<T: 'static>(front: &Front<T>, f: &mut dyn FnMut(&mut FrontMap<T>)) {
+    let _ = FRONTS.try_with(|fronts| {
+        let mut fronts = fronts.borrow_mut();
+        if !fronts.contains_key(&front.id) {
+            // Forget the maps of the caches dropped since.
+            fronts.retain(|_, (alive, _)| alive.strong_count() > 0);
+            fronts.insert(front.id, (Arc::downgrade(&front.alive), Box::new(FrontMap::<T>::new())));
+        }
+        if let Some(map) = fronts.get_mut(&front.id).and_then(|(_, map)| map.downcast_mut()) {
+            f(map);
+        }
+    });
+}
+
+impl<T> Drop for Front<T> {
+    fn drop(&mut self) {
+        // Other threads let go of their maps when they next set one up.
+        let _ = FRONTS.try_with(|fronts| {
+            if let Ok(mut fronts) = fronts.try_borrow_mut() {
+                fronts.remove(&self.id);
+            }
+        });
+    }
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    // `lookup` through the calling thread's front map: a current copy from
+    // there, else the shard's answer, copying hits into the map.
+    pub(crate) fn front_lookup(&self, front: &Front<T>, key: &str) -> Lookup<T> {
+        let shard = self.shard_index(key);
+        let now = self.clock.now_ns();
+        let generation = front.generations[shard].load(Ordering::Acquire);
+        let mut found = None;
+        (front.with_map)(front, &mut |map| {
+            found = map.get(key).filter(|entry| entry.generation == generation && now <= entry.until).map(|entry| entry.value.clone());
+        });
+        if let Some(value) = found {
+            return Lookup::Hit(value);
+        }
+        // The generation is only bumped under the shard's lock, so the one
+        // read with it matches the answer.
+        let (lookup, exp, generation) = {
+            let mut local_cache = self.shards[shard].lock();
+            let lookup = local_cache.get(key);
+            let exp = local_cache.map.get(key).map(|entity| entity.exp);
+            (lookup, exp, front.generations[shard].load(Ordering::Acquire))
+        };
+        if let (Lookup::Hit(value), Some(exp)) = (&lookup, exp) {
+            let until = exp.min(now.saturating_add(ttl_ns(front.config.ttl)));
+            let capacity = front.config.capacity;
+            (front.with_map)(front, &mut |map| {
+                if map.len() >= capacity && !map.contains_key(key) {
+                    map.retain(|key, entry| {
+                        now <= entry.until && entry.generation == front.generations[self.shard_index(key)].load(Ordering::Relaxed)
+                    });
+                    if map.len() >= capacity {
+                        map.clear();
+                    }
+                }
+                map.insert(key.to_string(), FrontEntry { value: value.clone(), until, generation });
+            });
+        }
+        lookup
+    }
+}
+
+impl<T: 'static, S> LocalCacheBuilder<T, S> {
+    /// For keys so hot that even their shard's lock is contended: `get`
+    /// first looks in a small map of the calling thread's own, filled by its
+    /// hits. Every change to a shard bumps its generation, which retires
+    /// the copies of its entries in every thread, and copies are kept for
+    /// at most the configured TTL. A thread's map is cleared when it fills
+    /// up with current copies. Hits served from it don't count in
+    /// [`stats`](LocalCache::stats), nor update the entry's recency, hit
+    /// count or cold promotion, nor check its checksum. Values stay
+    /// referenced by other threads' maps after the cache is dropped until
+    /// those threads use a new cache with a front. Has no effect with
+    /// sliding expiration or [`time_to_idle`](Self::time_to_idle), which
+    /// need every read.
+    pub fn thread_local_front(mut self, config: ThreadLocalFront) -> Self {
+        self.thread_local_front = Some((config, Front::attach));
+        self
+    }
+}
+
+#[test]
+fn test_thread_local_front() {
+    use std::thread;
+
+    let now = Arc::new(AtomicU64::new(1));
+    let clock = now.clone();
+    let local_cache: Arc<LocalCache<usize>> = Arc::new(
+        LocalCache::builder()
+            .shards(2)
+            .thread_local_front(ThreadLocalFront::new(2, Duration::from_secs(10)))
+            .clock(move || Duration::from_secs(clock.load(Ordering::Relaxed)).as_nanos())
+            .build(),
+    );
+    local_cache.put(String::from("a"), Arc::new(1));
+    local_cache.put_with_ttl(String::from("b"), Arc::new(2), Duration::from_secs(5));
+    for _ in 0..3 {
+        assert_eq!(Some(Arc::new(1)), local_cache.get("a"));
+        assert_eq!(Some(Arc::new(2)), local_cache.get("b"));
+    }
+    // Only the first round reached the shards.
+    assert_eq!(2, local_cache.stats().hits);
+
+    // Another thread has a map of its own.
+    let other = local_cache.clone();
+    thread::spawn(move || assert_eq!(Some(Arc::new(1)), other.get("a"))).join().unwrap();
+    assert_eq!(3, local_cache.stats().hits);
+
+    local_cache.put(String::from("a"), Arc::new(10));
+    assert_eq!(Some(Arc::new(10)), local_cache.get("a"));
+    local_cache.remove("a");
+    assert_eq!(None, local_cache.get("a"));
+
+    // Copies don't outlive the entry, nor the front's TTL.
+    now.store(7, Ordering::Relaxed);
+    assert_eq!(None, local_cache.get("b"));
+    local_cache.put_with_ttl(String::from("c"), Arc::new(3), Duration::from_secs(60));
+    assert_eq!(Some(Arc::new(3)), local_cache.get("c"));
+    let hits = local_cache.stats().hits;
+    now.store(20, Ordering::Relaxed);
+    assert_eq!(Some(Arc::new(3)), local_cache.get("c"));
+    assert_eq!(hits + 1, local_cache.stats().hits);
+}
@@ -0,0 +1,216 @@
+//! Entry lifetimes from HTTP response headers, following RFC 9111.
+//!
+//! Only explicit lifetimes are used: a response without `max-age`,
+//! `s-maxage` or `Expires` is stale from the start, as no heuristic
+//! freshness is applied.
+
+use std::hash::BuildHasher;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{Key, LocalCache};
+
+/// How a response may be cached, see [`policy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HttpPolicy {
+    /// Freshness lifetime less the response's current age.
+    pub fresh_for: Duration,
+    /// `stale-while-revalidate`: how long past `fresh_for` the response may
+    /// still be served while it is refetched in the background.
+    pub stale_while_revalidate: Duration,
+    /// `stale-if-error`: how long past `fresh_for` it may still be served
+    /// when refetching fails.
+    pub stale_if_error: Duration,
+    /// `no-cache`, `must-revalidate` (or `proxy-revalidate` and `s-maxage`
+    /// in a shared cache): never served stale.
+    pub must_revalidate: bool,
+}
+
+/// What to do with a cached response, see [`HttpPolicy::staleness`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Staleness {
+    Fresh,
+    /// Serve it and refetch in the background.
+    Revalidate,
+    /// Refetch, and serve it if that fails.
+    ServeOnError,
+    /// Refetch before using it.
+    Stale,
+}
+
+impl HttpPolicy {
+    /// How long to keep the response: its fresh lifetime and the longer of
+    /// its stale allowances.
+    pub fn ttl(&self) -> Duration {
+        if self.must_revalidate {
+            return self.fresh_for;
+        }
+        self.fresh_for.saturating_add(self.stale_while_revalidate.max(self.stale_if_error))
+    }
+
+    /// The state of the response `stored_for` after it was cached.
+    pub fn staleness(&self, stored_for: Duration) -> Staleness {
+        if stored_for < self.fresh_for {
+            return Staleness::Fresh;
+        }
+        let stale_for = stored_for - self.fresh_for;
+        if self.must_revalidate {
+            Staleness::Stale
+        } else if stale_for < self.stale_while_revalidate {
+            Staleness::Revalidate
+        } else if stale_for < self.stale_if_error {
+            Staleness::ServeOnError
+        } else {
+            Staleness::Stale
+        }
+    }
+}
+
+/// The caching policy of a response received at `now`, from its headers
+/// (names compared case-insensitively, repeated `Cache-Control` headers
+/// combined). `shared` caches honour `s-maxage` and skip `private`
+/// responses. `None` if the response must not be stored.
+pub fn policy<'a, I>(headers: I, now: SystemTime, shared: bool) -> Option<HttpPolicy>
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let (mut max_age, mut s_maxage, mut expires, mut date, mut age) = (None, None, None, None, Duration::ZERO);
+    let mut stale_while_revalidate = Duration::ZERO;
+    let mut stale_if_error = Duration::ZERO;
+    let mut must_revalidate = false;
+    for (name, value) in headers {
+        if name.eq_ignore_ascii_case("cache-control") {
+            for directive in value.split(',') {
+                let (directive, argument) = directive.split_once('=').unwrap_or((directive, ""));
+                // Malformed delta-seconds make the response stale rather than fresh forever.
+                let seconds = Duration::from_secs(argument.trim().trim_matches('"').parse().unwrap_or(0));
+                match directive.trim().to_ascii_lowercase().as_str() {
+                    "no-store" => return None,
+                    "private" if shared => return None,
+                    "max-age" => max_age = Some(seconds),
+                    "s-maxage" if shared => {
+                        s_maxage = Some(seconds);
+                        must_revalidate = true;
+                    }
+                    "stale-while-revalidate" => stale_while_revalidate = seconds,
+                    "stale-if-error" => stale_if_error = seconds,
+                    "no-cache" => {
+                        max_age = Some(Duration::ZERO);
+                        must_revalidate = true;
+                    }
+                    "must-revalidate" => must_revalidate = true,
+                    "proxy-revalidate" if shared => must_revalidate = true,
+                    _ => {}
+                }
+            }
+        } else if name.eq_ignore_ascii_case("expires") {
+            // An invalid date means already expired.
+            expires = Some(parse_http_date(value).unwrap_or(UNIX_EPOCH));
+        } else if name.eq_ignore_ascii_case("date") {
+            date = parse_http_date(value);
+        } else if name.eq_ignore_ascii_case("age") {
+            age = Duration::from_secs(value.trim().parse().unwrap_or(0));
+        }
+    }
+    let date = date.unwrap_or(now);
+    let lifetime = match (s_maxage.or(max_age), expires) {
+        (Some(lifetime), _) => lifetime,
+        (None, Some(expires)) => expires.duration_since(date).unwrap_or_default(),
+        (None, None) => Duration::ZERO,
+    };
+    let age = age.max(now.duration_since(date).unwrap_or_default());
+    Some(HttpPolicy { fresh_for: lifetime.saturating_sub(age), stale_while_revalidate, stale_if_error, must_revalidate })
+}
+
+/// Parses an HTTP-date in any of the three formats recipients must accept:
+/// `Sun, 06 Nov 1994 08:49:37 GMT`, `Sunday, 06-Nov-94 08:49:37 GMT` and
+/// `Sun Nov  6 08:49:37 1994`.
+pub fn parse_http_date(date: &str) -> Option<SystemTime> {
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let fields: Vec<&str> = date.split([' ', ',', '-']).filter(|field| !field.is_empty()).collect();
+    let month = |field: &str| MONTHS.iter().position(|&month| month == field);
+    let (day, month, year, time) = match fields.as_slice() {
+        [_, month_name, day, time, year] => (*day, month(month_name)?, *year, *time),
+        [_, day, month_name, year, time, "GMT"] => (*day, month(month_name)?, *year, *time),
+        _ => return None,
+    };
+    let day: u64 = day.parse().ok()?;
+    let mut year: i64 = year.parse().ok()?;
+    if year < 100 {
+        year += if year < 70 { 2000 } else { 1900 };
+    }
+    let mut time = time.splitn(3, ':').map(|field| field.parse::<u64>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+    // Four-digit years only, which also keeps the arithmetic below in range.
+    if year > 9999 || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    let days = u64::try_from(days_from_civil(year, month as i64 + 1, day as i64)).ok()?;
+    let secs = days.checked_mul(86400)?.checked_add(hour * 3600 + minute * 60 + second)?;
+    UNIX_EPOCH.checked_add(Duration::from_secs(secs))
+}
+
+// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// Caches a response for its policy's [`ttl`](HttpPolicy::ttl). Does
+    /// nothing if that is zero. Returns whether it was stored.
+    pub fn put_response(&self, key: impl Into<Key>, value: impl Into<Arc<T>>, policy: &HttpPolicy) -> bool {
+        let value = value.into();
+        let ttl = policy.ttl();
+        if ttl.is_zero() {
+            return false;
+        }
+        self.put_with_ttl(key, value, ttl);
+        true
+    }
+}
+
+#[test]
+fn test_http_policy() {
+    let now = parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+    assert_eq!(Duration::from_secs(784111777), now.duration_since(UNIX_EPOCH).unwrap());
+    assert_eq!(Some(now), parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"));
+    assert_eq!(Some(now), parse_http_date("Sun Nov  6 08:49:37 1994"));
+    assert_eq!(None, parse_http_date("yesterday"));
+    assert_eq!(None, parse_http_date("Sun, 06 Nov 9223372036854775807 08:49:37 GMT"));
+
+    let of = |headers: &[(&str, &str)], shared| policy(headers.iter().copied(), now, shared);
+    let cache_control = |value| of(&[("Cache-Control", value)], true);
+    assert_eq!(None, cache_control("public, no-store"));
+    assert_eq!(None, cache_control("private, max-age=60"));
+    assert!(of(&[("cache-control", "private, max-age=60")], false).is_some());
+
+    let p = of(&[("Cache-Control", "max-age=60, s-maxage=600"), ("Age", "100")], true).unwrap();
+    assert_eq!((Duration::from_secs(500), true), (p.fresh_for, p.must_revalidate));
+    let p = of(&[("Cache-Control", "max-age=60, s-maxage=600"), ("Age", "100")], false).unwrap();
+    assert_eq!((Duration::ZERO, false), (p.fresh_for, p.must_revalidate));
+
+    let expires = [("Date", "Sun, 06 Nov 1994 08:48:37 GMT"), ("Expires", "Sun, 06 Nov 1994 09:48:37 GMT")];
+    assert_eq!(Duration::from_secs(3540), of(&expires, true).unwrap().fresh_for);
+    assert_eq!(Duration::ZERO, of(&[("Expires", "0")], true).unwrap().fresh_for);
+
+    let p = cache_control("max-age=10, stale-while-revalidate=5, stale-if-error=60").unwrap();
+    assert_eq!(Duration::from_secs(70), p.ttl());
+    let staleness = [0, 12, 30, 80].map(|secs| p.staleness(Duration::from_secs(secs)));
+    assert_eq!([Staleness::Fresh, Staleness::Revalidate, Staleness::ServeOnError, Staleness::Stale], staleness);
+    let p = cache_control("max-age=10, must-revalidate, stale-if-error=60").unwrap();
+    assert_eq!((Duration::from_secs(10), Staleness::Stale), (p.ttl(), p.staleness(Duration::from_secs(12))));
+    let p = cache_control("max-age=18446744073709551615, stale-if-error=1").unwrap();
+    assert_eq!(Duration::MAX, p.ttl());
+
+    let local_cache: LocalCache<&str> = LocalCache::new(4, 360);
+    assert!(local_cache.put_response(String::from("/a"), Arc::new("a"), &cache_control("max-age=60").unwrap()));
+    assert!(!local_cache.put_response(String::from("/b"), Arc::new("b"), &cache_control("no-cache").unwrap()));
+    let info = local_cache.get_entry_info("/a").unwrap();
+    assert!(info.expires_at <= info.inserted_at + Duration::from_secs(60));
+    assert_eq!(None, local_cache.get("/b"));
+}
@@ -0,0 +1,161 @@
+use std::hash::BuildHasher;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{CacheEntity, LocalCache};
+
+/// Metadata of a live entry, see [`LocalCache::get_entry_info`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryInfo {
+    pub inserted_at: SystemTime,
+    /// Some time in 2554 for entries that never expire.
+    pub expires_at: SystemTime,
+    /// `None` if the entry was never read.
+    pub last_access: Option<SystemTime>,
+    /// Reads since insertion.
+    pub hits: u64,
+    /// See [`LocalCache::put_with_source`].
+    pub source: &'static str,
+    pub tags: Vec<String>,
+}
+
+fn system_time(ns: u128) -> SystemTime {
+    UNIX_EPOCH + Duration::from_nanos(u64::try_from(ns).unwrap_or(u64::MAX))
+}
+
+impl<T> CacheEntity<T> {
+    fn info(&self) -> EntryInfo {
+        EntryInfo {
+            inserted_at: system_time(self.created),
+            expires_at: system_time(self.exp),
+            last_access: (self.last_access > 0).then(|| system_time(self.last_access)),
+            hits: self.accesses,
+            source: self.source,
+            tags: self.tags.to_vec(),
+        }
+    }
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// Metadata of the live entry for `key`. Doesn't count as an access.
+    pub fn get_entry_info(&self, key: &str) -> Option<EntryInfo> {
+        let local_cache = self.lock(key);
+        let entity = local_cache.map.get(key)?;
+        if local_cache.now() > entity.exp {
+            return None;
+        }
+        Some(entity.info())
+    }
+
+    /// The `n` live entries read most often since they were inserted, most
+    /// hits first, with their metadata. Negative entries count too.
+    pub fn top_n_by_hits(&self, n: usize) -> Vec<(String, EntryInfo)> {
+        let mut top = Vec::new();
+        for shard in self.shards.iter() {
+            let local_cache = shard.lock();
+            let now = local_cache.now();
+            let mut entities: Vec<_> =
+                local_cache.map.values().filter(|entity| now <= entity.exp).collect();
+            entities.sort_unstable_by(|a, b| b.accesses.cmp(&a.accesses).then_with(|| a.key.cmp(&b.key)));
+            top.extend(entities.into_iter().take(n).map(|entity| (entity.key.to_string(), entity.info())));
+        }
+        top.sort_unstable_by(|(a_key, a), (b_key, b)| b.hits.cmp(&a.hits).then_with(|| a_key.cmp(b_key)));
+        top.truncate(n);
+        top
+    }
+
+    /// The live entries with their metadata, hottest first as the LRU lists
+    /// have them, shard by shard: each shard evicts for capacity from the
+    /// end of its run, except that LFU and sampled eviction pick victims
+    /// within the main list by their own order. Negative entries are
+    /// included.
+    pub fn iter_lru(&self) -> impl Iterator<Item = (String, EntryInfo)> {
+        let mut entries = Vec::new();
+        for shard in self.shards.iter() {
+            let local_cache = shard.lock();
+            let now = local_cache.now();
+            for head in local_cache.lru_heads() {
+                let mut cur = head;
+                while let Some(id) = cur {
+                    let entity = &local_cache.map[id];
+                    if now <= entity.exp {
+                        entries.push((entity.key.to_string(), entity.info()));
+                    }
+                    cur = entity.lru_next;
+                }
+            }
+        }
+        entries.into_iter()
+    }
+
+    /// The live entries with their metadata, soonest to expire first across
+    /// all shards, as the expiry sweep will remove them. Entries that never
+    /// expire come last.
+    pub fn iter_by_expiry(&self) -> impl Iterator<Item = (String, EntryInfo)> {
+        let mut entries = Vec::new();
+        for shard in self.shards.iter() {
+            let local_cache = shard.lock();
+            let now = local_cache.now();
+            let live = local_cache.map.values().filter(|entity| now <= entity.exp);
+            entries.extend(live.map(|entity| (entity.exp, entity.key.to_string(), entity.info())));
+        }
+        entries.sort_unstable_by(|(a_exp, a_key, _), (b_exp, b_key, _)| a_exp.cmp(b_exp).then_with(|| a_key.cmp(b_key)));
+        entries.into_iter().map(|(_, key, info)| (key, info))
+    }
+}
+
+#[test]
+fn test_get_entry_info() {
+    use std::sync::Arc;
+
+    let local_cache: LocalCache<usize> = LocalCache::new(4, 360);
+    let before = SystemTime::now();
+    local_cache.put_tagged(String::from("x"), Arc::new(1), ["a"]);
+    let info = local_cache.get_entry_info("x").unwrap();
+    assert!(info.inserted_at >= before && info.expires_at > info.inserted_at + Duration::from_secs(359));
+    assert_eq!((None, 0, "put", vec![String::from("a")]), (info.last_access, info.hits, info.source, info.tags));
+
+    local_cache.get("x");
+    local_cache.get("x");
+    let info = local_cache.get_entry_info("x").unwrap();
+    assert_eq!(2, info.hits);
+    assert!(info.last_access.unwrap() >= info.inserted_at);
+    assert_eq!(None, local_cache.get_entry_info("y"));
+}
+
+#[test]
+fn test_top_n_by_hits() {
+    use std::sync::Arc;
+
+    let local_cache: LocalCache<usize> = LocalCache::builder().shards(4).build();
+    for i in 0..10 {
+        local_cache.put(i.to_string(), Arc::new(i));
+        for _ in 0..i % 5 {
+            local_cache.get(&i.to_string());
+        }
+    }
+    let top: Vec<_> = local_cache.top_n_by_hits(3).into_iter().map(|(key, info)| (key, info.hits)).collect();
+    assert_eq!(vec![(String::from("4"), 4), (String::from("9"), 4), (String::from("3"), 3)], top);
+    assert_eq!(10, local_cache.top_n_by_hits(20).len());
+    assert!(local_cache.top_n_by_hits(0).is_empty());
+}
+
+#[test]
+fn test_ordered_scans() {
+    use std::sync::Arc;
+
+    let local_cache: LocalCache<usize> = LocalCache::builder().shards(2).build();
+    for (i, ttl) in [30, 10, 20].into_iter().enumerate() {
+        local_cache.put_with_ttl(i.to_string(), Arc::new(i), Duration::from_secs(ttl));
+    }
+    local_cache.put_with_ttl(String::from("forever"), Arc::new(9), Duration::MAX);
+    let by_expiry: Vec<_> = local_cache.iter_by_expiry().map(|(key, _)| key).collect();
+    assert_eq!(vec!["1", "2", "0", "forever"], by_expiry);
+
+    let local_cache: LocalCache<usize> = LocalCache::new(4, 360);
+    for i in 0..3 {
+        local_cache.put(i.to_string(), Arc::new(i));
+    }
+    local_cache.get("0");
+    let lru: Vec<_> = local_cache.iter_lru().map(|(key, info)| (key, info.hits)).collect();
+    assert_eq!(vec![(String::from("0"), 1), (String::from("2"), 0), (String::from("1"), 0)], lru);
+}
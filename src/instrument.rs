@@ -0,0 +1,94 @@
+use crate::{InnerLocalCache, LocalCache, LocalCacheBuilder};
+
+impl<T, S> LocalCacheBuilder<T, S> {
+    /// Records keys in the `tracing` spans and events emitted for inserts,
+    /// evictions, loader runs and expiry sweeps. Keys are left out by
+    /// default, as collectors index every distinct field value.
+    pub fn trace_keys(mut self) -> Self {
+        self.trace_keys = true;
+        self
+    }
+}
+
+impl<T, S> InnerLocalCache<T, S> {
+    // The `key` field of spans and events, left empty unless keys are traced.
+    pub(crate) fn traced_key<'a>(&self, key: &'a str) -> Option<&'a str> {
+        self.trace_keys.then_some(key)
+    }
+}
+
+impl<T, S> LocalCache<T, S> {
+    pub(crate) fn traced_key<'a>(&self, key: &'a str) -> Option<&'a str> {
+        self.trace_keys.then_some(key)
+    }
+}
+
+#[test]
+fn test_tracing() {
+    use std::fmt::Debug;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    // Writes every span and event as `name key=value ...`.
+    #[derive(Clone, Default)]
+    struct Lines(Arc<Mutex<Vec<String>>>);
+
+    struct Fields(String);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            self.0 += &format!(" {}={:?}", field.name(), value);
+        }
+    }
+
+    impl Subscriber for Lines {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut fields = Fields(span.metadata().name().to_string());
+            span.record(&mut fields);
+            let mut lines = self.0.lock().unwrap();
+            lines.push(fields.0);
+            Id::from_u64(lines.len() as u64)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields(String::new());
+            event.record(&mut fields);
+            self.0.lock().unwrap().push(fields.0.trim_start().to_string());
+        }
+        fn enter(&self, _: &Id) {}
+        fn exit(&self, _: &Id) {}
+    }
+
+    let lines = Lines::default();
+    tracing::subscriber::with_default(lines.clone(), || {
+        let local_cache: LocalCache<usize> = LocalCache::builder().max_entries(1).trace_keys().build();
+        local_cache.get_or_insert_with(String::from("a"), || 1);
+        local_cache.put_with_ttl(String::from("b"), Arc::new(2), Duration::from_millis(5));
+        std::thread::sleep(Duration::from_millis(10));
+        local_cache.evict_expired();
+
+        let local_cache: LocalCache<usize> = LocalCache::new(1, 360);
+        local_cache.put(String::from("c"), Arc::new(3));
+    });
+    let lines = lines.0.lock().unwrap();
+    assert_eq!(
+        *lines,
+        [
+            "loader key=\"a\"",
+            "message=insert key=\"a\" ttl_ms=60000",
+            "message=evict key=\"a\" reason=Capacity source=\"loader\"",
+            "message=insert key=\"b\" ttl_ms=5",
+            "message=evict key=\"b\" reason=Expired source=\"put\"",
+            "message=expiry sweep removed=1",
+            "message=insert ttl_ms=360000",
+        ]
+    );
+}
@@ -0,0 +1,135 @@
+use alloc::sync::Arc;
+use core::hash::{BuildHasher, Hasher};
+use core::time::Duration;
+
+use crate::{Key, LocalCache, LocalCacheBuilder, Lookup};
+
+// The multiplier of rustc's FxHash.
+const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+/// [`BuildHasher`] of [`FxHasher`]s, for [`LocalCacheU64`] and other caches
+/// whose keys are short and not chosen by an attacker.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FxBuildHasher;
+
+impl BuildHasher for FxBuildHasher {
+    type Hasher = FxHasher;
+
+    fn build_hasher(&self) -> FxHasher {
+        FxHasher(0)
+    }
+}
+
+/// The hash rustc uses internally: a rotate, xor and multiply per word, a
+/// few times faster than the default on short keys, but without its
+/// protection against keys crafted to collide.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FxHasher(u64);
+
+impl FxHasher {
+    fn add(&mut self, word: u64) {
+        self.0 = (self.0.rotate_left(5) ^ word).wrapping_mul(SEED);
+    }
+}
+
+impl Hasher for FxHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.add(u64::from_le_bytes(word));
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.add(i as u64)
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.add(i)
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// A cache keyed by `u64` ids, which are never formatted or allocated:
+/// each becomes a fixed ten-byte [`Key`] held inline in its entry.
+///
+/// The wrapped cache sees those ten bytes as its keys, so prefix
+/// invalidation, dumps and iteration over it show them rather than the ids.
+pub struct LocalCacheU64<T, S = FxBuildHasher> {
+    local_cache: LocalCache<T, S>,
+}
+
+impl<T> LocalCacheU64<T> {
+    /// A cache of `max_numbers` entries expiring after `max_age_secs`, or
+    /// never for `u64::MAX`, like [`LocalCache::new`].
+    pub fn new(max_numbers: usize, max_age_secs: u64) -> Self {
+        Self::with_cache(Self::builder().max_entries(max_numbers).ttl(Duration::from_secs(max_age_secs)).build())
+    }
+
+    /// A builder that hashes and routes keys with [`FxBuildHasher`], for
+    /// [`with_cache`](Self::with_cache).
+    pub fn builder() -> LocalCacheBuilder<T, FxBuildHasher> {
+        // The map takes its buckets from the low bits of the same hash, so
+        // route by the high ones.
+        LocalCache::builder_with_hasher(FxBuildHasher)
+            .shard_router(|key: &str, shards: usize| (FxBuildHasher.hash_one(key) >> 32) as usize % shards)
+    }
+}
+
+impl<T, S: BuildHasher> LocalCacheU64<T, S> {
+    /// Wraps `local_cache`, which should be given no other keys.
+    pub fn with_cache(local_cache: LocalCache<T, S>) -> Self {
+        Self { local_cache }
+    }
+
+    /// The wrapped cache, for stats and anything not mirrored here.
+    pub fn cache(&self) -> &LocalCache<T, S> {
+        &self.local_cache
+    }
+
+    pub fn get(&self, id: u64) -> Option<Arc<T>> {
+        self.local_cache.get(&Key::from_id(id))
+    }
+
+    pub fn lookup(&self, id: u64) -> Lookup<T> {
+        self.local_cache.lookup(&Key::from_id(id))
+    }
+
+    pub fn put(&self, id: u64, value: impl Into<Arc<T>>) {
+        self.local_cache.put(Key::from_id(id), value)
+    }
+
+    pub fn put_with_ttl(&self, id: u64, value: impl Into<Arc<T>>, ttl: Duration) {
+        self.local_cache.put_with_ttl(Key::from_id(id), value, ttl)
+    }
+
+    pub fn remove(&self, id: u64) -> Option<Arc<T>> {
+        self.local_cache.remove(&Key::from_id(id))
+    }
+}
+
+#[test]
+fn test_local_cache_u64() {
+    let local_cache: LocalCacheU64<u64> = LocalCacheU64::with_cache(LocalCacheU64::builder().max_entries(4096).shards(4).build());
+    for id in (0..1000).chain([u64::MAX - 1, u64::MAX]) {
+        local_cache.put(id, id % 7);
+    }
+    assert_eq!(Some(Arc::new(3)), local_cache.get(3));
+    assert_eq!(Some(Arc::new((u64::MAX - 1) % 7)), local_cache.get(u64::MAX - 1));
+    assert_eq!(None, local_cache.get(1000));
+    assert_eq!(Some(Arc::new(u64::MAX % 7)), local_cache.remove(u64::MAX));
+    assert_eq!(None, local_cache.get(u64::MAX));
+    assert_eq!(1001, local_cache.cache().stats().entries);
+
+    // No key on the heap, and the ids spread over every shard.
+    for shard in local_cache.cache().shards.iter() {
+        let shard = shard.lock();
+        assert!(shard.map.len() > 150);
+        assert!(shard.map.values().all(|entity| entity.key.heap_size() == 0));
+    }
+    assert!(Key::from_id(255) < Key::from_id(256));
+}
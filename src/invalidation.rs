@@ -0,0 +1,186 @@
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::{lock, InnerLocalCache, LocalCache, LocalCacheBuilder, ShardLock, ShardRouter};
+
+type Shards<T, S> = Arc<[ShardLock<InnerLocalCache<T, S>>]>;
+
+// Ids telling the caches of one process apart on a bus.
+static NEXT_ORIGIN: AtomicU64 = AtomicU64::new(0);
+
+/// What another cache's write made stale.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Invalidation {
+    Key(String),
+    Tag(String),
+}
+
+/// Carries invalidations between caches, typically on other nodes, see
+/// [`LocalCacheBuilder::invalidation_bus`]. Each cache is known to the bus
+/// by an `origin` id.
+///
+/// `publish` is called under the shard lock on every write and removal, so
+/// it should hand the message off rather than wait on the network.
+pub trait InvalidationBus: Send + Sync {
+    /// Delivers `invalidation` to every subscriber but `origin`.
+    fn publish(&self, origin: u64, invalidation: &Invalidation);
+    /// Invalidations published by everyone but `origin`.
+    fn subscribe(&self, origin: u64) -> Receiver<Invalidation>;
+    /// Drops the sender behind `origin`'s receiver.
+    fn unsubscribe(&self, origin: u64);
+}
+
+/// [`InvalidationBus`] between caches of the same process.
+#[derive(Default)]
+pub struct ChannelBus {
+    subscribers: Mutex<Vec<(u64, Sender<Invalidation>)>>,
+}
+
+impl InvalidationBus for ChannelBus {
+    fn publish(&self, origin: u64, invalidation: &Invalidation) {
+        for (id, sender) in lock(&self.subscribers).iter() {
+            if *id != origin {
+                let _ = sender.send(invalidation.clone());
+            }
+        }
+    }
+
+    fn subscribe(&self, origin: u64) -> Receiver<Invalidation> {
+        let (sender, receiver) = mpsc::channel();
+        lock(&self.subscribers).push((origin, sender));
+        receiver
+    }
+
+    fn unsubscribe(&self, origin: u64) {
+        lock(&self.subscribers).retain(|(id, _)| *id != origin);
+    }
+}
+
+impl<T, S> InnerLocalCache<T, S> {
+    pub(crate) fn publish(&self, invalidation: Invalidation) {
+        if let Some((origin, bus)) = &self.bus {
+            bus.publish(*origin, &invalidation);
+        }
+    }
+}
+
+// The thread applying remote invalidations. Dropping it unsubscribes and
+// waits for it to finish the ones already received.
+pub(crate) struct InvalidationWorker<T, S> {
+    origin: u64,
+    bus: Arc<dyn InvalidationBus>,
+    shards: Shards<T, S>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl<T: Send + Sync + 'static, S: BuildHasher + Send + 'static> InvalidationWorker<T, S> {
+    pub(crate) fn spawn(shards: &Shards<T, S>, router: Arc<dyn ShardRouter>, bus: Arc<dyn InvalidationBus>) -> Self {
+        let origin = NEXT_ORIGIN.fetch_add(1, Ordering::Relaxed);
+        let receiver = bus.subscribe(origin);
+        for local_cache in shards.iter() {
+            local_cache.lock().bus = Some((origin, bus.clone()));
+        }
+        let worker_shards = shards.clone();
+        let worker = thread::Builder::new()
+            .name("local-cache-invalidation".to_string())
+            .spawn(move || apply(&worker_shards, &*router, receiver))
+            .expect("failed to spawn invalidation thread");
+        Self { origin, bus, shards: shards.clone(), worker: Some(worker) }
+    }
+}
+
+impl<T, S> InvalidationWorker<T, S> {
+    fn publish(&self, invalidation: Invalidation) {
+        self.bus.publish(self.origin, &invalidation);
+    }
+}
+
+impl<T, S> Drop for InvalidationWorker<T, S> {
+    fn drop(&mut self) {
+        for shard in self.shards.iter() {
+            shard.lock().bus = None;
+        }
+        self.bus.unsubscribe(self.origin);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+// Remote writes already reached the backing store, so only memory and the
+// spill store are cleared.
+fn apply<T, S: BuildHasher>(shards: &[ShardLock<InnerLocalCache<T, S>>], router: &dyn ShardRouter, receiver: Receiver<Invalidation>) {
+    for invalidation in receiver {
+        match invalidation {
+            Invalidation::Key(key) => {
+                let index = if shards.len() == 1 { 0 } else { router.shard(&key, shards.len()) % shards.len() };
+                let mut local_cache = shards[index].lock();
+                if let Some(store) = &local_cache.store {
+                    store.remove(&key);
+                }
+                local_cache.remove(&key);
+            }
+            Invalidation::Tag(tag) => {
+                for shard in shards {
+                    shard.lock().remove_tagged(&tag);
+                }
+            }
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static, S: BuildHasher + Send + 'static> LocalCacheBuilder<T, S> {
+    /// Publishes the keys this cache writes or removes, and the tags it
+    /// invalidates, to `bus`, and drops the keys and tags published there by
+    /// other caches on a background thread.
+    pub fn invalidation_bus(mut self, bus: Arc<dyn InvalidationBus>) -> Self {
+        self.invalidation_bus = Some((bus, InvalidationWorker::spawn));
+        self
+    }
+}
+
+impl<T, S> LocalCache<T, S> {
+    pub(crate) fn publish(&self, invalidation: Invalidation) {
+        if let Some(worker) = &self.invalidation {
+            worker.publish(invalidation);
+        }
+    }
+}
+
+#[test]
+fn test_invalidation_bus() {
+    use std::time::{Duration, Instant};
+
+    use crate::testing::Db;
+
+    let (db, bus) = (Db::default(), Arc::new(ChannelBus::default()));
+    db.0.lock().unwrap().extend([(String::from("a"), 0), (String::from("b"), 0)]);
+    let nodes: Vec<LocalCache<usize>> =
+        (0..3).map(|_| LocalCache::builder().shards(2).backing_store(db.clone()).invalidation_bus(bus.clone()).build()).collect();
+    let cached = |node: &LocalCache<usize>, key: &str| node.shards.iter().any(|shard| shard.lock().map.get(key).is_some());
+    let wait_until_dropped = |node: &LocalCache<usize>, key: &str| {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while cached(node, key) && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(1));
+        }
+        !cached(node, key)
+    };
+    // Loads don't publish anything.
+    assert!(nodes.iter().all(|node| node.get("a") == Some(Arc::new(0))));
+
+    nodes[0].put(String::from("a"), Arc::new(1));
+    assert!(wait_until_dropped(&nodes[1], "a"));
+    assert!(wait_until_dropped(&nodes[2], "a"));
+    assert!(cached(&nodes[0], "a"));
+    assert_eq!(Some(Arc::new(1)), nodes[1].get("a"));
+
+    nodes[1].put_tagged(String::from("b"), Arc::new(1), ["t"]);
+    assert_eq!(0, nodes[2].invalidate_tag("t"));
+    assert!(wait_until_dropped(&nodes[1], "b"));
+
+    drop(nodes);
+    assert!(bus.subscribers.lock().unwrap().is_empty());
+}
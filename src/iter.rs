@@ -0,0 +1,155 @@
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use alloc::vec;
+use core::hash::BuildHasher;
+
+use crate::{CacheEntity, InnerLocalCache, LocalCache, Slot};
+
+impl<T, S: BuildHasher> InnerLocalCache<T, S> {
+    // Empties the shard, returning its live entries least recently used first.
+    fn drain(&mut self) -> Vec<(String, Arc<T>)> {
+        let mut keys = Vec::with_capacity(self.map.len());
+        for tail in self.lru_tails() {
+            let mut cur = tail;
+            while let Some(e) = cur {
+                let b = &self.map[e];
+                keys.push(b.key.to_string());
+                cur = b.lru_prev;
+            }
+        }
+        let now = self.now();
+        let mut entries = Vec::with_capacity(keys.len());
+        for key in keys {
+            let Some(old) = self.remove(&key) else {
+                continue;
+            };
+            if now > old.exp {
+                continue;
+            }
+            let value = match old.value {
+                Slot::Hot(value) => value,
+                Slot::Cold(bytes) => match self.cold.as_ref().and_then(|cold| cold.codec.decode(&bytes)) {
+                    Some(value) => Arc::new(value),
+                    None => continue,
+                },
+                Slot::Weak(value) => match value.upgrade() {
+                    Some(value) => value,
+                    None => continue,
+                },
+                Slot::Negative => continue,
+            };
+            entries.push((key, value));
+        }
+        entries
+    }
+
+    fn keys(&self) -> Vec<String> {
+        let now = self.now();
+        let live = |entity: &&CacheEntity<T>| {
+            now <= entity.exp
+                && match &entity.value {
+                    Slot::Hot(_) | Slot::Cold(_) => true,
+                    Slot::Weak(value) => value.strong_count() > 0,
+                    Slot::Negative => false,
+                }
+        };
+        self.map.values().filter(live).map(|entity| entity.key.to_string()).collect()
+    }
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// Empties the cache, returning its live entries least recently used
+    /// first, shard by shard. Waits for queued inserts first; the spill and
+    /// backing stores are left alone.
+    pub fn drain(&self) -> impl Iterator<Item = (String, Arc<T>)> {
+        #[cfg(feature = "std")]
+        self.wait_for_inserts();
+        let mut entries = Vec::new();
+        for shard in self.shards.iter() {
+            entries.extend(shard.lock().drain());
+        }
+        entries.into_iter()
+    }
+
+    /// The keys of all live entries, shard by shard in no particular order.
+    /// Negative entries are left out. Each shard is locked only while its
+    /// keys are copied.
+    pub fn keys(&self) -> Vec<String> {
+        self.shards.iter().flat_map(|shard| shard.lock().keys()).collect()
+    }
+
+    /// The values of all live entries, like [`keys`](Self::keys). Cold
+    /// entries are decoded; none of this counts as an access.
+    pub fn values(&self) -> Vec<Arc<T>> {
+        let mut values = Vec::new();
+        for shard in self.shards.iter() {
+            let local_cache = shard.lock();
+            values.extend(local_cache.map.keys().filter_map(|key| local_cache.peek(key)));
+        }
+        values
+    }
+}
+
+/// Builds a cache with the default settings and inserts every item, so
+/// only the last `max_entries` of them are kept.
+impl<K: Into<String>, T, S: BuildHasher + Clone + Default> FromIterator<(K, T)> for LocalCache<T, S> {
+    fn from_iter<I: IntoIterator<Item = (K, T)>>(iter: I) -> Self {
+        let local_cache = LocalCache::builder_with_hasher(S::default()).build();
+        local_cache.put_many(iter.into_iter().map(|(key, value)| (key.into(), Arc::new(value))));
+        local_cache
+    }
+}
+
+/// Yields the live entries least recently used first, shard by shard,
+/// after applying any queued inserts. Negative entries are left out.
+impl<T, S: BuildHasher> IntoIterator for LocalCache<T, S> {
+    type Item = (String, Arc<T>);
+    type IntoIter = vec::IntoIter<(String, Arc<T>)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.drain().collect::<Vec<_>>().into_iter()
+    }
+}
+
+#[test]
+fn test_iterators() {
+    let local_cache: LocalCache<usize> = [("a", 0), ("b", 1), ("c", 2)].into_iter().collect();
+    local_cache.get("a");
+    local_cache.put_negative(String::from("n"));
+    let entries: Vec<_> = local_cache.into_iter().map(|(key, value)| (key, *value)).collect();
+    assert_eq!(vec![(String::from("b"), 1), (String::from("c"), 2), (String::from("a"), 0)], entries);
+}
+
+#[test]
+fn test_drain() {
+    let local_cache: LocalCache<usize> = LocalCache::builder().shards(4).build();
+    for i in 0..10 {
+        local_cache.put(i.to_string(), Arc::new(i));
+    }
+    let mut drained: Vec<_> = local_cache.drain().map(|(_, value)| *value).collect();
+    drained.sort();
+    assert_eq!((0..10).collect::<Vec<_>>(), drained);
+    assert_eq!(0, local_cache.stats().entries);
+    assert_eq!(None, local_cache.get("1"));
+}
+
+#[test]
+fn test_keys_values() {
+    use std::time::Duration;
+
+    let local_cache: LocalCache<usize> = LocalCache::builder().shards(4).build();
+    for i in 0..5 {
+        local_cache.put(i.to_string(), Arc::new(i));
+    }
+    local_cache.put_negative(String::from("n"));
+    local_cache.put_with_ttl(String::from("gone"), Arc::new(9), Duration::from_millis(1));
+    std::thread::sleep(Duration::from_millis(5));
+    let mut keys = local_cache.keys();
+    keys.sort();
+    assert_eq!(vec!["0", "1", "2", "3", "4"], keys);
+    let mut values: Vec<_> = local_cache.values().into_iter().map(|value| *value).collect();
+    values.sort();
+    assert_eq!(vec![0, 1, 2, 3, 4], values);
+    assert_eq!(0, local_cache.stats().hits);
+}
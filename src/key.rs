@@ -0,0 +1,151 @@
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::hash::{Hash, Hasher};
+use core::ops::Deref;
+use core::{fmt, mem};
+
+// The longest key kept inline: what fits beside the length in 24 bytes.
+const INLINE: usize = 22;
+
+/// A cache key as entries hold it: up to 22 bytes inline, with no heap
+/// allocation, and longer keys in an `Arc<str>` shared with the cache's
+/// indexes and with whoever handed it in. Converts from `&str`, `String`
+/// and `Arc<str>`, and derefs to `str`.
+#[derive(Clone)]
+pub struct Key(Repr);
+
+#[derive(Clone)]
+enum Repr {
+    Inline { len: u8, bytes: [u8; INLINE] },
+    Shared(Arc<str>),
+}
+
+impl Key {
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            // Only ever filled from a `str`, up to `len`.
+            Repr::Inline { len, bytes } => unsafe { core::str::from_utf8_unchecked(&bytes[..*len as usize]) },
+            Repr::Shared(key) => key,
+        }
+    }
+
+    // Bytes on the heap for the key, counting the Arc's two counts.
+    pub(crate) fn heap_size(&self) -> usize {
+        match &self.0 {
+            Repr::Inline { .. } => 0,
+            Repr::Shared(key) => 2 * mem::size_of::<usize>() + key.len(),
+        }
+    }
+
+    // `id` in ten base-128 digits, most significant first so that keys sort
+    // like the ids; every digit is an ASCII byte.
+    pub(crate) fn from_id(id: u64) -> Self {
+        let mut bytes = [0; INLINE];
+        for (i, digit) in bytes[..10].iter_mut().enumerate() {
+            *digit = (id >> (7 * (9 - i)) & 0x7f) as u8;
+        }
+        Self(Repr::Inline { len: 10, bytes })
+    }
+
+    fn inline(key: &str) -> Option<Self> {
+        let mut bytes = [0; INLINE];
+        bytes.get_mut(..key.len())?.copy_from_slice(key.as_bytes());
+        Some(Self(Repr::Inline { len: key.len() as u8, bytes }))
+    }
+}
+
+impl From<&str> for Key {
+    fn from(key: &str) -> Self {
+        Self::inline(key).unwrap_or_else(|| Self(Repr::Shared(key.into())))
+    }
+}
+
+impl From<&String> for Key {
+    fn from(key: &String) -> Self {
+        Self::from(key.as_str())
+    }
+}
+
+impl From<String> for Key {
+    fn from(key: String) -> Self {
+        Self::inline(&key).unwrap_or_else(|| Self(Repr::Shared(key.into())))
+    }
+}
+
+impl From<Arc<str>> for Key {
+    fn from(key: Arc<str>) -> Self {
+        Self::inline(&key).unwrap_or(Self(Repr::Shared(key)))
+    }
+}
+
+impl Deref for Key {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Borrow<str> for Key {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Key {}
+
+impl PartialOrd for Key {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Key {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+// As `str` hashes, for lookups through `Borrow<str>`.
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl fmt::Debug for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[test]
+fn test_key() {
+    assert_eq!(24, mem::size_of::<Key>());
+    let short = Key::from("user:42");
+    assert!(matches!(short.0, Repr::Inline { len: 7, .. }));
+    assert_eq!((0, "user:42"), (short.heap_size(), &*short));
+    let max = Key::from("a".repeat(INLINE));
+    assert_eq!(0, max.heap_size());
+
+    let long: Arc<str> = Arc::from("https://example.com/a/rather/long/path");
+    let key = Key::from(long.clone());
+    assert!(matches!(&key.0, Repr::Shared(shared) if Arc::ptr_eq(shared, &long)));
+    assert_eq!(16 + long.len(), key.heap_size());
+    assert_eq!(Key::from(long.to_string()), key);
+    assert!(key < short);
+}
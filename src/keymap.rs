@@ -0,0 +1,170 @@
+use core::hash::BuildHasher;
+use core::ops::{Index, IndexMut};
+
+use hashbrown::HashTable;
+
+use crate::slab::{NodeId, Slab};
+use crate::{CacheEntity, CacheError, HashMap};
+
+// The shard's nodes and their index by key. Nodes are filed by the key they
+// hold, so each key is stored once, in its node, and looked up by `&str`
+// without allocating.
+pub(crate) struct KeyMap<T, S> {
+    nodes: Slab<CacheEntity<T>>,
+    table: HashTable<NodeId>,
+    hasher: S,
+}
+
+impl<T, S> KeyMap<T, S> {
+    pub(crate) fn with_hasher(hasher: S) -> Self {
+        Self { nodes: Slab::new(), table: HashTable::new(), hasher }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.table.capacity()
+    }
+
+    // Node slots allocated, filled or not.
+    pub(crate) fn node_capacity(&self) -> usize {
+        self.nodes.capacity()
+    }
+
+    // Like indexing, for callers that mustn't panic on a stale id.
+    pub(crate) fn node(&self, id: NodeId) -> Option<&CacheEntity<T>> {
+        self.nodes.get(id)
+    }
+
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &str> {
+        self.values().map(|entity| &*entity.key)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (NodeId, &CacheEntity<T>)> {
+        self.nodes.iter()
+    }
+
+    pub(crate) fn values(&self) -> impl Iterator<Item = &CacheEntity<T>> {
+        self.iter().map(|(_, entity)| entity)
+    }
+
+    pub(crate) fn values_mut(&mut self) -> impl Iterator<Item = &mut CacheEntity<T>> {
+        self.nodes.values_mut()
+    }
+
+    // The same map over copies of the nodes, under the same ids.
+    pub(crate) fn fork(&self, copy: impl Fn(&CacheEntity<T>) -> CacheEntity<T>) -> Self
+    where
+        S: Clone,
+    {
+        Self { nodes: self.nodes.fork(copy), table: self.table.clone(), hasher: self.hasher.clone() }
+    }
+}
+
+impl<T, S: BuildHasher> KeyMap<T, S> {
+    pub(crate) fn find(&self, key: &str) -> Option<NodeId> {
+        let nodes = &self.nodes;
+        self.table.find(self.hasher.hash_one(key), |&id| *nodes[id].key == *key).copied()
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<&CacheEntity<T>> {
+        self.find(key).map(|id| &self.nodes[id])
+    }
+
+    // Files `entity` under its key, which must not be in the map yet.
+    pub(crate) fn try_insert(&mut self, entity: CacheEntity<T>) -> Result<NodeId, CacheError> {
+        self.try_reserve(1)?;
+        let hash = self.hasher.hash_one(&*entity.key);
+        let id = self.nodes.try_insert(entity)?;
+        let (nodes, hasher) = (&self.nodes, &self.hasher);
+        self.table.insert_unique(hash, id, |&id| hasher.hash_one(&*nodes[id].key));
+        Ok(id)
+    }
+
+    pub(crate) fn remove(&mut self, id: NodeId) -> CacheEntity<T> {
+        let hash = self.hasher.hash_one(&*self.nodes[id].key);
+        if let Ok(entry) = self.table.find_entry(hash, |&e| e == id) {
+            entry.remove();
+        }
+        self.nodes.remove(id)
+    }
+
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        let (nodes, hasher) = (&self.nodes, &self.hasher);
+        self.table.reserve(additional, |&id| hasher.hash_one(&*nodes[id].key));
+        self.nodes.reserve(additional);
+    }
+
+    pub(crate) fn try_reserve(&mut self, additional: usize) -> Result<(), CacheError> {
+        let (nodes, hasher) = (&self.nodes, &self.hasher);
+        self.table.try_reserve(additional, |&id| hasher.hash_one(&*nodes[id].key)).map_err(|_| CacheError::AllocFailed)?;
+        self.nodes.try_reserve(additional)
+    }
+
+    // Also moves nodes into the slots of removed ones, see `Slab::compact`,
+    // returning the ids that changed.
+    pub(crate) fn shrink_to_fit(&mut self) -> HashMap<NodeId, NodeId> {
+        let moved = self.nodes.compact();
+        for id in self.table.iter_mut() {
+            *id = moved.get(id).copied().unwrap_or(*id);
+        }
+        let (nodes, hasher) = (&self.nodes, &self.hasher);
+        self.table.shrink_to_fit(|&id| hasher.hash_one(&*nodes[id].key));
+        moved
+    }
+}
+
+impl<T, S> Index<NodeId> for KeyMap<T, S> {
+    type Output = CacheEntity<T>;
+
+    fn index(&self, id: NodeId) -> &CacheEntity<T> {
+        &self.nodes[id]
+    }
+}
+
+impl<T, S> IndexMut<NodeId> for KeyMap<T, S> {
+    fn index_mut(&mut self, id: NodeId) -> &mut CacheEntity<T> {
+        &mut self.nodes[id]
+    }
+}
+
+#[test]
+fn test_key_map() {
+    use std::sync::Arc;
+
+    use crate::{LocalCache, Slot};
+
+    let local_cache: LocalCache<usize> = LocalCache::new(1024, 360);
+    for i in 0..1000 {
+        local_cache.put(i.to_string(), Arc::new(i));
+    }
+    local_cache.put(String::from("7"), Arc::new(70));
+    let mut local_cache = local_cache.shards[0].lock();
+    assert_eq!(1000, local_cache.map.len());
+    let entity = local_cache.map.get("7").unwrap();
+    assert!(matches!(&entity.value, Slot::Hot(value) if **value == 70));
+    local_cache.shrink_to_fit();
+    assert!((0..1000).all(|i| local_cache.map.get(&i.to_string()).is_some()));
+    assert!(local_cache.map.get("1000").is_none());
+    assert!(local_cache.remove(&String::from("7")).is_some());
+    assert!(local_cache.map.get("7").is_none());
+    assert_eq!(999, local_cache.map.keys().count());
+}
+
+#[test]
+fn test_shared_keys() {
+    use std::sync::Arc;
+
+    use crate::LocalCache;
+
+    let local_cache: LocalCache<usize> = LocalCache::builder().prefix_index().build();
+    let key = "https://example.com/a/rather/long/path?with=query";
+    local_cache.put_tagged(key.to_string(), Arc::new(0), ["page"]);
+    let local_cache = local_cache.shards[0].lock();
+    let node_key = &local_cache.map.get(key).unwrap().key;
+    // One copy, held by the node and both indexes.
+    assert_eq!(node_key.as_ptr(), local_cache.prefix_index.as_ref().unwrap().get(key).unwrap().as_ptr());
+    assert_eq!(node_key.as_ptr(), local_cache.tags["page"].get(key).unwrap().as_ptr());
+}
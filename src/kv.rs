@@ -0,0 +1,246 @@
+use std::future::{self, Future};
+use std::pin::Pin;
+
+use crate::store::{decode_spilled, encode_spilled};
+use crate::{Spilled, Store, ValueCodec};
+
+/// The few calls a [`KvStore`] makes of an embedded key-value database.
+///
+/// Implemented for `sled::Tree` and `sled::Db` with the `sled` feature, and
+/// for `rocksdb::DB` with the `rocksdb` feature; their errors are dropped,
+/// as the spill tier is best-effort. Calls are made under the shard lock,
+/// like those to any [`Store`], except for `get_async`.
+pub trait KvBackend: Send + Sync {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>>;
+    fn put(&self, key: &[u8], value: &[u8]);
+    fn delete(&self, key: &[u8]);
+    /// Backs [`Store::load_async`]. Defaults to `get`.
+    fn get_async<'a>(&'a self, key: &'a [u8]) -> Pin<Box<dyn Future<Output = Option<Vec<u8>>> + Send + 'a>> {
+        Box::pin(future::ready(self.get(key)))
+    }
+}
+
+/// [`Store`] keeping entries in a [`KvBackend`], under their key with
+/// `prefix` in front so that one database can serve several caches, in the
+/// same form as a [`FileStore`](crate::FileStore) file.
+pub struct KvStore<B, C> {
+    backend: B,
+    codec: C,
+    prefix: Vec<u8>,
+}
+
+impl<B, C> KvStore<B, C> {
+    pub fn new(backend: B, codec: C, prefix: impl Into<Vec<u8>>) -> Self {
+        Self { backend, codec, prefix: prefix.into() }
+    }
+
+    /// The database, e.g. to flush it.
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    fn key(&self, key: &str) -> Vec<u8> {
+        [&self.prefix, key.as_bytes()].concat()
+    }
+}
+
+impl<T, B: KvBackend, C: ValueCodec<T>> Store<T> for KvStore<B, C> {
+    fn load(&self, key: &str) -> Option<Spilled<T>> {
+        decode_spilled(&self.codec, key, &self.backend.get(&self.key(key))?)
+    }
+
+    fn save(&self, key: &str, entry: Spilled<&T>) {
+        match encode_spilled(&self.codec, key, entry) {
+            Some(bytes) => self.backend.put(&self.key(key), &bytes),
+            None => self.backend.delete(&self.key(key)),
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        self.backend.delete(&self.key(key));
+    }
+
+    fn load_async<'a>(&'a self, key: &'a str) -> Pin<Box<dyn Future<Output = Option<Spilled<T>>> + Send + 'a>>
+    where
+        T: Send + 'a,
+    {
+        Box::pin(async move {
+            let bytes = self.backend.get_async(&self.key(key)).await?;
+            decode_spilled(&self.codec, key, &bytes)
+        })
+    }
+}
+
+#[cfg(feature = "sled")]
+impl KvBackend for sled::Tree {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        sled::Tree::get(self, key).ok()?.map(|value| value.to_vec())
+    }
+    fn put(&self, key: &[u8], value: &[u8]) {
+        let _ = self.insert(key, value);
+    }
+    fn delete(&self, key: &[u8]) {
+        let _ = self.remove(key);
+    }
+}
+
+// The database's default tree.
+#[cfg(feature = "sled")]
+impl KvBackend for sled::Db {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        KvBackend::get(&**self, key)
+    }
+    fn put(&self, key: &[u8], value: &[u8]) {
+        KvBackend::put(&**self, key, value)
+    }
+    fn delete(&self, key: &[u8]) {
+        KvBackend::delete(&**self, key)
+    }
+}
+
+#[cfg(feature = "rocksdb")]
+impl KvBackend for rocksdb::DB {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        rocksdb::DB::get(self, key).ok()?
+    }
+    fn put(&self, key: &[u8], value: &[u8]) {
+        let _ = rocksdb::DB::put(self, key, value);
+    }
+    fn delete(&self, key: &[u8]) {
+        let _ = rocksdb::DB::delete(self, key);
+    }
+}
+
+#[test]
+fn test_kv_store() {
+    use std::collections::BTreeMap;
+    use std::sync::{Arc, Mutex};
+
+    use crate::{lock, LocalCache};
+
+    #[derive(Clone, Default)]
+    struct Tree(Arc<Mutex<BTreeMap<Vec<u8>, Vec<u8>>>>);
+    impl KvBackend for Tree {
+        fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+            lock(&self.0).get(key).cloned()
+        }
+        fn put(&self, key: &[u8], value: &[u8]) {
+            lock(&self.0).insert(key.to_vec(), value.to_vec());
+        }
+        fn delete(&self, key: &[u8]) {
+            lock(&self.0).remove(key);
+        }
+    }
+    struct Le;
+    impl ValueCodec<u64> for Le {
+        fn encode(&self, value: &u64) -> Option<Vec<u8>> {
+            Some(value.to_le_bytes().to_vec())
+        }
+        fn decode(&self, bytes: &[u8]) -> Option<u64> {
+            Some(u64::from_le_bytes(bytes.try_into().ok()?))
+        }
+    }
+
+    let tree = Tree::default();
+    let local_cache: LocalCache<u64> = LocalCache::builder().max_entries(1).store(KvStore::new(tree.clone(), Le, "a/")).build();
+    let other: LocalCache<u64> = LocalCache::builder().max_entries(1).store(KvStore::new(tree.clone(), Le, "b/")).build();
+    local_cache.put(String::from("x"), 1);
+    local_cache.put(String::from("y"), 2);
+    other.put(String::from("x"), 10);
+    other.put(String::from("y"), 20);
+    let keys: Vec<_> = lock(&tree.0).keys().cloned().collect();
+    assert_eq!(vec![b"a/x".to_vec(), b"b/x".to_vec()], keys);
+
+    assert_eq!(Some(Arc::new(1)), local_cache.get("x"));
+    assert_eq!(Some(Arc::new(10)), other.get("x"));
+    assert_eq!(Some(Arc::new(2)), local_cache.get("y"));
+}
+
+#[test]
+fn test_load_async() {
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Waker};
+    use std::time::{Duration, SystemTime};
+
+    use crate::testing::Utf8;
+    use crate::LocalCache;
+
+    // Has every key spilled, and takes two polls to read it, like a read
+    // off a connection pool.
+    struct Slow;
+    impl KvBackend for Slow {
+        fn get(&self, _: &[u8]) -> Option<Vec<u8>> {
+            unreachable!()
+        }
+        fn put(&self, _: &[u8], _: &[u8]) {}
+        fn delete(&self, _: &[u8]) {}
+        fn get_async<'a>(&'a self, key: &'a [u8]) -> Pin<Box<dyn Future<Output = Option<Vec<u8>>> + Send + 'a>> {
+            let mut polled = false;
+            Box::pin(future::poll_fn(move |cx| {
+                if !polled {
+                    polled = true;
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+                let value = String::from("abc");
+                let entry = Spilled { value: &value, expires_at: SystemTime::now() + Duration::from_secs(60), checksum: None };
+                Poll::Ready(encode_spilled(&Utf8, std::str::from_utf8(key).unwrap(), entry))
+            }))
+        }
+    }
+    fn assert_send<F: Send>(future: F) -> F {
+        future
+    }
+
+    let local_cache: LocalCache<String> = LocalCache::builder().shards(1).store(KvStore::new(Slow, Utf8, "")).build();
+    let mut future = std::pin::pin!(assert_send(local_cache.get_async("x")));
+    let mut cx = Context::from_waker(Waker::noop());
+    assert!(future.as_mut().poll(&mut cx).is_pending());
+    // The shard isn't locked while the store is read.
+    local_cache.put("y", String::from("123"));
+    assert_eq!(Poll::Ready(Some(Arc::new(String::from("abc")))), future.as_mut().poll(&mut cx));
+    assert_eq!(Some(Arc::new(String::from("abc"))), local_cache.get("x"));
+}
+
+#[cfg(feature = "sled")]
+#[test]
+fn test_sled() {
+    use std::sync::Arc;
+
+    use crate::testing::Utf8;
+    use crate::LocalCache;
+
+    let db = sled::Config::new().temporary(true).open().unwrap();
+    let local_cache: LocalCache<String> = LocalCache::builder().max_entries(1).store(KvStore::new(db.clone(), Utf8, "c/")).build();
+    local_cache.put("x", String::from("abc"));
+    local_cache.put("y", String::from("123"));
+    assert!(db.contains_key("c/x").unwrap());
+    assert_eq!(Some(Arc::new(String::from("abc"))), local_cache.get("x"));
+    assert!(!db.contains_key("c/x").unwrap());
+    assert!(db.contains_key("c/y").unwrap());
+
+    let tree = db.open_tree("other").unwrap();
+    KvBackend::put(&tree, b"k", b"v");
+    assert_eq!(Some(b"v".to_vec()), KvBackend::get(&tree, b"k"));
+    KvBackend::delete(&tree, b"k");
+    assert_eq!(None, KvBackend::get(&tree, b"k"));
+}
+
+#[cfg(feature = "rocksdb")]
+#[test]
+fn test_rocksdb() {
+    use std::sync::Arc;
+
+    use crate::testing::Utf8;
+    use crate::LocalCache;
+
+    let path = std::env::temp_dir().join(format!("local-cache-rocksdb-{}", std::process::id()));
+    let db = rocksdb::DB::open_default(&path).unwrap();
+    let local_cache: LocalCache<String> = LocalCache::builder().max_entries(1).store(KvStore::new(db, Utf8, "c/")).build();
+    local_cache.put("x", String::from("abc"));
+    local_cache.put("y", String::from("123"));
+    assert_eq!(Some(Arc::new(String::from("abc"))), local_cache.get("x"));
+    assert_eq!(Some(Arc::new(String::from("123"))), local_cache.get("y"));
+    drop(local_cache);
+    let _ = rocksdb::DB::destroy(&rocksdb::Options::default(), &path);
+}
@@ -0,0 +1,183 @@
+use std::future::Future;
+use std::hash::BuildHasher;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use tower::{Layer, Service};
+
+use crate::{DefaultHashBuilder, LocalCache};
+
+/// Where a request's response is cached, see [`CacheLayer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey {
+    key: String,
+    ttl: Option<Duration>,
+}
+
+impl CacheKey {
+    pub fn new(key: impl Into<String>) -> Self {
+        Self { key: key.into(), ttl: None }
+    }
+
+    /// Caches the response for `ttl` instead of the cache's default TTL.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+}
+
+/// Serves responses of the wrapped service from `cache`, under the
+/// [`CacheKey`] that `key` gives each request. Requests it gives `None`
+/// bypass the cache, and responses are only cached when the inner service
+/// succeeds.
+pub struct CacheLayer<K, T, S = DefaultHashBuilder> {
+    cache: Arc<LocalCache<T, S>>,
+    key: K,
+}
+
+impl<K, T, S> CacheLayer<K, T, S> {
+    pub fn new(cache: Arc<LocalCache<T, S>>, key: K) -> Self {
+        Self { cache, key }
+    }
+}
+
+impl<K: Clone, T, S> Clone for CacheLayer<K, T, S> {
+    fn clone(&self) -> Self {
+        Self { cache: self.cache.clone(), key: self.key.clone() }
+    }
+}
+
+impl<I, K: Clone, T, S> Layer<I> for CacheLayer<K, T, S> {
+    type Service = CacheService<I, K, T, S>;
+
+    fn layer(&self, inner: I) -> Self::Service {
+        CacheService { inner, cache: self.cache.clone(), key: self.key.clone() }
+    }
+}
+
+/// The service built by [`CacheLayer`].
+pub struct CacheService<I, K, T, S = DefaultHashBuilder> {
+    inner: I,
+    cache: Arc<LocalCache<T, S>>,
+    key: K,
+}
+
+impl<I: Clone, K: Clone, T, S> Clone for CacheService<I, K, T, S> {
+    fn clone(&self) -> Self {
+        Self { inner: self.inner.clone(), cache: self.cache.clone(), key: self.key.clone() }
+    }
+}
+
+impl<I, K, T, S, R> Service<R> for CacheService<I, K, T, S>
+where
+    I: Service<R, Response = T>,
+    K: Fn(&R) -> Option<CacheKey>,
+    T: Clone,
+    S: BuildHasher,
+{
+    type Response = T;
+    type Error = I::Error;
+    type Future = ResponseFuture<I::Future, T, S>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: R) -> Self::Future {
+        let key = (self.key)(&request);
+        if let Some(response) = key.as_ref().and_then(|key| self.cache.get(&key.key)) {
+            return ResponseFuture { state: State::Hit(Some((*response).clone())) };
+        }
+        let future = Box::pin(self.inner.call(request));
+        ResponseFuture { state: State::Miss { future, cache: self.cache.clone(), key } }
+    }
+}
+
+/// The response of a [`CacheService`], cached once the inner service's
+/// future completes successfully.
+pub struct ResponseFuture<F, T, S = DefaultHashBuilder> {
+    state: State<F, T, S>,
+}
+
+enum State<F, T, S> {
+    Hit(Option<T>),
+    Miss { future: Pin<Box<F>>, cache: Arc<LocalCache<T, S>>, key: Option<CacheKey> },
+}
+
+// The inner future is boxed, so nothing here needs to stay put.
+impl<F, T, S> Unpin for ResponseFuture<F, T, S> {}
+
+impl<F, T, S, E> Future for ResponseFuture<F, T, S>
+where
+    F: Future<Output = Result<T, E>>,
+    T: Clone,
+    S: BuildHasher,
+{
+    type Output = Result<T, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match &mut self.get_mut().state {
+            State::Hit(response) => Poll::Ready(Ok(response.take().expect("polled after completion"))),
+            State::Miss { future, cache, key } => {
+                let response = std::task::ready!(future.as_mut().poll(cx))?;
+                if let Some(CacheKey { key, ttl }) = key.take() {
+                    let value = Arc::new(response.clone());
+                    match ttl {
+                        Some(ttl) => cache.put_with_ttl(key, value, ttl),
+                        None => cache.put(key, value),
+                    }
+                }
+                Poll::Ready(Ok(response))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_cache_layer() {
+    use std::future::{ready, Ready};
+    use std::task::Waker;
+
+    // Answers with the request and counts the calls.
+    #[derive(Clone, Default)]
+    struct Echo(Arc<std::sync::atomic::AtomicUsize>);
+    impl Service<&'static str> for Echo {
+        type Response = String;
+        type Error = &'static str;
+        type Future = Ready<Result<String, &'static str>>;
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+        fn call(&mut self, request: &'static str) -> Self::Future {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            ready(if request == "fail" { Err("failed") } else { Ok(request.to_uppercase()) })
+        }
+    }
+
+    let cache: Arc<LocalCache<String>> = Arc::new(LocalCache::new(4, 360));
+    let key = |request: &&'static str| match *request {
+        "nocache" => None,
+        "short" => Some(CacheKey::new(*request).ttl(Duration::from_millis(5))),
+        _ => Some(CacheKey::new(*request)),
+    };
+    let echo = Echo::default();
+    let mut service = CacheLayer::new(cache.clone(), key).layer(echo.clone());
+    let mut call = |request| {
+        let mut future = service.call(request);
+        match Pin::new(&mut future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(response) => response,
+            Poll::Pending => unreachable!(),
+        }
+    };
+    let calls = || echo.0.load(std::sync::atomic::Ordering::Relaxed);
+    for request in ["a", "a", "nocache", "nocache", "fail", "fail", "short"] {
+        call(request).ok();
+    }
+    assert_eq!(Ok(String::from("A")), call("a"));
+    assert_eq!(6, calls());
+    assert_eq!(Some(Arc::new(String::from("A"))), cache.get("a"));
+    std::thread::sleep(Duration::from_millis(10));
+    assert_eq!(None, cache.get("short"));
+}
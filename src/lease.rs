@@ -0,0 +1,103 @@
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use core::hash::BuildHasher;
+use core::sync::atomic::Ordering;
+use core::time::Duration;
+
+use crate::LocalCache;
+
+pub(crate) struct Lease {
+    id: u64,
+    exp: u128,
+}
+
+/// Exclusive right to populate a key, handed out by [`LocalCache::acquire_lease`].
+///
+/// The lease ends when it is fulfilled, released, or its TTL runs out,
+/// whichever comes first.
+#[derive(Debug, PartialEq, Eq)]
+pub struct LeaseToken {
+    key: String,
+    id: u64,
+}
+
+impl LeaseToken {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+    /// Unique per cache; lets other parties tell lease holders apart.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// Returns a token if nobody else currently holds a lease on `key`.
+    pub fn acquire_lease(&self, key: &str, ttl: Duration) -> Option<LeaseToken> {
+        let mut local_cache = self.lock(key);
+        let now = local_cache.now();
+        if local_cache.leases.get(key).is_some_and(|lease| lease.exp > now) {
+            return None;
+        }
+        // Abandoned leases are only replaced on acquire; prune them before
+        // they outnumber the entries themselves.
+        if local_cache.leases.len() > local_cache.max_numbers {
+            local_cache.leases.retain(|_, lease| lease.exp > now);
+        }
+        let id = self.lease_ids.fetch_add(1, Ordering::Relaxed) + 1;
+        local_cache.leases.insert(key.to_string(), Lease { id, exp: now + ttl.as_nanos() });
+        Some(LeaseToken { key: key.to_string(), id })
+    }
+
+    /// Id of the live lease on `key`, if any.
+    pub fn lease_holder(&self, key: &str) -> Option<u64> {
+        let local_cache = self.lock(key);
+        let now = local_cache.now();
+        local_cache.leases.get(key).filter(|lease| lease.exp > now).map(|lease| lease.id)
+    }
+
+    /// Stores `value` and ends the lease, unless the lease has already been
+    /// lost (expired and possibly re-acquired by someone else).
+    /// Returns whether the value was stored.
+    pub fn fulfill_lease(&self, token: LeaseToken, value: impl Into<Arc<T>>) -> bool {
+        let value = value.into();
+        let mut local_cache = self.lock(&token.key);
+        let now = local_cache.now();
+        match local_cache.leases.get(&token.key) {
+            Some(lease) if lease.id == token.id && lease.exp > now => {}
+            _ => return false,
+        }
+        local_cache.leases.remove(&token.key);
+        local_cache.insert(token.key.into(), Some(value), None, "lease");
+        true
+    }
+
+    /// Gives up the lease without populating the key.
+    pub fn release_lease(&self, token: LeaseToken) {
+        let mut local_cache = self.lock(&token.key);
+        if local_cache.leases.get(&token.key).is_some_and(|lease| lease.id == token.id) {
+            local_cache.leases.remove(&token.key);
+        }
+    }
+}
+
+#[test]
+fn test_lease() {
+    let local_cache: LocalCache<String> = LocalCache::new(4, 360);
+    let key = "x".to_string();
+
+    let token = local_cache.acquire_lease(&key, Duration::from_secs(10)).unwrap();
+    assert_eq!(None, local_cache.acquire_lease(&key, Duration::from_secs(10)));
+    assert_eq!(Some(token.id()), local_cache.lease_holder(&key));
+    assert!(local_cache.fulfill_lease(token, Arc::new(String::from("abc"))));
+    assert_eq!(None, local_cache.lease_holder(&key));
+    assert_eq!(Some(Arc::new(String::from("abc"))), local_cache.get(&key));
+
+    let stale = local_cache.acquire_lease(&key, Duration::ZERO).unwrap();
+    std::thread::sleep(Duration::from_millis(1));
+    let fresh = local_cache.acquire_lease(&key, Duration::from_secs(10)).unwrap();
+    assert!(!local_cache.fulfill_lease(stale, Arc::new(String::from("old"))));
+    assert_eq!(Some(Arc::new(String::from("abc"))), local_cache.get(&key));
+    local_cache.release_lease(fresh);
+    assert_eq!(None, local_cache.lease_holder(&key));
+}
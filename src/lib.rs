@@ -1,199 +1,1720 @@
-use std::collections::{HashMap};
-use std::ptr::NonNull;
-use std::sync::{Arc, Mutex};
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::alloc::{handle_alloc_error, Layout};
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::{String, ToString};
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::hash::BuildHasher;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "std")]
+use std::fs::File;
+#[cfg(feature = "std")]
+use std::io::{self, Write};
+#[cfg(feature = "std")]
+use std::path::Path;
+#[cfg(feature = "std")]
+use std::sync::mpsc::{Sender, SyncSender};
+#[cfg(feature = "std")]
+use std::sync::{Mutex, MutexGuard, PoisonError};
+#[cfg(feature = "std")]
+use std::time::UNIX_EPOCH;
+
+#[cfg(not(feature = "std"))]
+use hashbrown::{HashMap, HashSet};
+use lock::{ShardGuard, ShardLock};
+use slab::NodeId;
+
+mod batch;
+#[cfg(feature = "chrono")]
+pub mod calendar;
+mod checksum;
+mod clock;
+mod cold;
+#[cfg(feature = "compression")]
+mod compression;
+mod conditional;
+#[cfg(feature = "std")]
+mod deadline;
+mod debug;
+mod entry;
+mod error;
+#[cfg(feature = "std")]
+mod events;
+#[cfg(feature = "metrics")]
+mod exporter;
+#[cfg(feature = "std")]
+mod expire;
+mod export;
+mod fork;
+#[cfg(feature = "std")]
+mod front;
+#[cfg(feature = "std")]
+pub mod http;
+#[cfg(feature = "std")]
+mod info;
+#[cfg(feature = "tracing")]
+mod instrument;
+mod int_key;
+#[cfg(feature = "std")]
+mod invalidation;
+mod iter;
+mod key;
+mod keymap;
+#[cfg(feature = "std")]
+mod kv;
+#[cfg(feature = "tower")]
+mod layer;
+mod lease;
+mod loader;
+mod lock;
+#[cfg(feature = "std")]
+mod maintenance;
+#[cfg(feature = "std")]
+mod manager;
+mod memory;
+mod namespace;
+mod or_insert;
+mod pin;
+#[cfg(all(unix, feature = "mmap"))]
+mod persistent;
+mod policy;
+mod prefix;
+mod promotion;
+mod queue;
+#[cfg(feature = "read-mostly")]
+mod read_mostly;
+#[cfg(feature = "redis")]
+mod redis;
+#[cfg(feature = "std")]
+mod repair;
+mod report;
+mod router;
+mod settings;
+mod slab;
+#[cfg(feature = "serde")]
+mod snapshot;
+mod stats;
+mod store;
+mod tags;
+#[cfg(test)]
+mod testing;
+mod typed;
+mod warm;
+mod weak;
+mod wheel;
+#[cfg(feature = "std")]
+mod write_behind;
+
+pub use clock::Clock;
+#[cfg(feature = "std")]
+pub use clock::SystemClock;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub use clock::JsClock;
+pub use cold::{ColdStorage, ValueCodec};
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use error::CacheError;
+#[cfg(feature = "std")]
+pub use events::CacheEvent;
+#[cfg(feature = "std")]
+pub use front::ThreadLocalFront;
+#[cfg(feature = "std")]
+pub use info::EntryInfo;
+pub use int_key::{FxBuildHasher, FxHasher, LocalCacheU64};
+#[cfg(feature = "std")]
+pub use invalidation::{ChannelBus, Invalidation, InvalidationBus};
+pub use key::Key;
+#[cfg(feature = "std")]
+pub use kv::{KvBackend, KvStore};
+pub use lease::LeaseToken;
+pub use loader::{AsyncCacheLoader, CacheLoader};
+pub use lock::{RawLock, SpinLock};
+#[cfg(feature = "std")]
+pub use maintenance::MaintenanceReport;
+#[cfg(feature = "std")]
+pub use manager::{CacheManager, ManagedCache};
+pub use namespace::Namespace;
+#[cfg(all(unix, feature = "mmap"))]
+pub use persistent::PersistentLocalCache;
+pub use policy::{Admission, EvictionPolicy, Priority};
+#[cfg(feature = "redis")]
+pub use redis::RedisStore;
+#[cfg(feature = "std")]
+pub use repair::ReadRepair;
+pub use report::{Evicted, EvictionReason};
+pub use router::{HashRouter, ShardRouter};
+#[cfg(feature = "std")]
+pub use stats::Forecast;
+pub use stats::{CacheStats, ShardStats, WindowStats};
+pub use store::BackingStore;
+#[cfg(feature = "std")]
+pub use store::{FileStore, Spilled, Store};
+pub use typed::{AnyValue, TypedLocalCache};
+#[cfg(feature = "std")]
+pub use write_behind::WriteBehind;
+#[cfg(feature = "serde")]
+pub use cold::JsonCodec;
+#[cfg(feature = "compression")]
+pub use compression::{CompressedBytes, CompressedCache};
+#[cfg(feature = "tower")]
+pub use layer::{CacheKey, CacheLayer, CacheService, ResponseFuture};
+
+const DEFAULT_MAX_NUMBERS: usize = 1024;
+const DEFAULT_MAX_AGE_SECS: u64 = 60;
+const DEFAULT_SHARDS: usize = 1;
+// Source of entries inserted with a plain `put`, see `LocalCache::put_with_source`.
+const DEFAULT_SOURCE: &str = "put";
+// Expiry, and TTL, of entries that never expire. They are left out of the
+// timer wheel.
+const NEVER: u128 = u128::MAX;
+
+/// Hashes keys unless [`LocalCache::builder_with_hasher`] picks another
+/// hasher: `ahash::RandomState` with the `ahash` feature, the std one
+/// otherwise, and hashbrown's without `std`.
+#[cfg(feature = "ahash")]
+pub type DefaultHashBuilder = ahash::RandomState;
+#[cfg(all(feature = "std", not(feature = "ahash")))]
+pub type DefaultHashBuilder = std::collections::hash_map::RandomState;
+#[cfg(not(feature = "std"))]
+pub type DefaultHashBuilder = hashbrown::DefaultHashBuilder;
+
+enum Slot<T> {
+    Hot(Arc<T>),
+    // Encoded by the cold storage codec; see `ColdStorage`.
+    Cold(Box<[u8]>),
+    // The key is known to be absent upstream.
+    Negative,
+    // Held without keeping the value alive, see `LocalCacheBuilder::weak_values`.
+    Weak(Weak<T>),
+}
+
+// Not derived, which would require `T: Clone`.
+impl<T> Clone for Slot<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Slot::Hot(value) => Slot::Hot(value.clone()),
+            Slot::Cold(bytes) => Slot::Cold(bytes.clone()),
+            Slot::Negative => Slot::Negative,
+            Slot::Weak(value) => Slot::Weak(value.clone()),
+        }
+    }
+}
+
+// Which LRU list a hot entry is on. Cold entries are always on the cold list.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Segment {
+    Main,
+    // TinyLFU admission window.
+    Window,
+    // SLRU entries hit again since entering the main list.
+    Protected,
+    // Never chosen for capacity eviction, see `LocalCache::pin`.
+    Pinned,
+    // Evicted before, respectively after, everything else.
+    Low,
+    High,
+}
 
 #[derive(Clone)]
 struct CacheEntity<T> {
-    key: String,
-    value: Arc<T>,
+    // Inline when short, else shared with the prefix and tag indexes.
+    key: Key,
+    value: Slot<T>,
     exp: u128,
-    lru_prev: Option<NonNull<Self>>,
-    lru_next: Option<NonNull<Self>>,
-    exp_prev: Option<NonNull<Self>>,
-    exp_next: Option<NonNull<Self>>,
+    // Expiry by TTL alone; `exp` is earlier when an idle timeout applies.
+    deadline: u128,
+    ttl_ns: u128,
+    // Which code path inserted the entry.
+    source: &'static str,
+    // See `LocalCache::put_tagged`.
+    tags: Box<[String]>,
+    created: u128,
+    // 0 until the first hit.
+    last_access: u128,
+    accesses: u64,
+    // Since insertion or moving between hot and cold, for the policies.
+    hits: u32,
+    // Of the value's bytes; only set when checksums are enabled.
+    checksum: u32,
+    segment: Segment,
+    // Bytes counted against `max_bytes`, 0 without one.
+    size: usize,
+    // Key in `InnerLocalCache::lfu` while on the main list under LFU.
+    lfu_key: (u32, u64),
+    // Position in `InnerLocalCache::pool` while on the main list under
+    // `EvictionPolicy::Sampled`.
+    pool_index: usize,
+    lru_prev: Link,
+    lru_next: Link,
+    // Neighbours in its timer wheel slot, see `wheel::TimerWheel`.
+    exp_prev: Link,
+    exp_next: Link,
+    wheel_slot: usize,
+}
+
+// A neighbour in a list, or a list's end, by its id in the shard's map.
+type Link = Option<NodeId>;
+
+// Takes a lock even if a thread panicked while holding it. User code run
+// under a shard's lock (hooks, codecs, stores, the hasher) is only ever
+// called between changes to the lists, never halfway through one, so a
+// poisoned shard is still consistent.
+#[cfg(feature = "std")]
+fn lock<X>(mutex: &Mutex<X>) -> MutexGuard<'_, X> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// A TTL in ns. `Duration::MAX`, like any TTL of `u64::MAX` seconds, never
+// expires.
+fn ttl_ns(ttl: Duration) -> u128 {
+    if ttl >= Duration::from_secs(u64::MAX) {
+        NEVER
+    } else {
+        ttl.as_nanos()
+    }
+}
+
+// Time left until `exp`, `Duration::MAX` if never.
+fn remaining(exp: u128, now: u128) -> Duration {
+    if exp == NEVER {
+        return Duration::MAX;
+    }
+    Duration::from_nanos(u64::try_from(exp.saturating_sub(now)).unwrap_or(u64::MAX))
+}
+
+// Shard `shard`'s part of a capacity of `total`: split evenly between
+// `shards`, or in proportion to `weights`, rounding so that the parts add up
+// to `total`.
+fn share(total: usize, shards: usize, weights: Option<&[usize]>, shard: usize) -> usize {
+    let Some(weights) = weights else {
+        return total / shards + usize::from(shard < total % shards);
+    };
+    let sum: u128 = weights.iter().map(|&weight| weight as u128).sum();
+    let before: u128 = weights[..shard].iter().map(|&weight| weight as u128).sum();
+    let upto = |weight: u128| (total as u128 * weight / sum.max(1)) as usize;
+    upto(before + weights[shard] as u128) - upto(before)
 }
 
-pub struct LocalCache<T>(Mutex<InnerLocalCache<T>>);
+pub struct LocalCache<T, S = DefaultHashBuilder> {
+    shards: Arc<[ShardLock<InnerLocalCache<T, S>>]>,
+    // The capacity as configured, which the shards split between them.
+    max_numbers: AtomicUsize,
+    // See `LocalCacheBuilder::shard_weights`.
+    shard_weights: Option<Vec<usize>>,
+    router: Arc<dyn ShardRouter>,
+    clock: Arc<dyn Clock>,
+    lease_ids: AtomicU64,
+    // Clock time the cache was built at, in ns since the epoch.
+    created: u128,
+    #[cfg(feature = "std")]
+    queues: Option<queue::InsertQueues<T>>,
+    // Only held to stop the repair thread when the cache is dropped.
+    #[cfg(feature = "std")]
+    _repair: Option<repair::RepairWorker<T, S>>,
+    #[cfg(feature = "std")]
+    invalidation: Option<invalidation::InvalidationWorker<T, S>>,
+    #[cfg(feature = "std")]
+    write_behind: Option<write_behind::WriteBehindWorker<T, S>>,
+    loader: Option<Arc<dyn CacheLoader<String, T>>>,
+    async_loader: Option<Arc<dyn AsyncCacheLoader<String, T>>>,
+    // See `LocalCacheBuilder::thread_local_front`.
+    #[cfg(feature = "std")]
+    front: Option<front::Front<T>>,
+    // One per shard, see `LocalCacheBuilder::read_mostly`.
+    #[cfg(feature = "read-mostly")]
+    snapshots: Option<Vec<Arc<read_mostly::ReadSnapshot<T>>>>,
+    #[cfg(feature = "metrics")]
+    metrics_name: Option<String>,
+    #[cfg(feature = "tracing")]
+    trace_keys: bool,
+}
 
-struct InnerLocalCache<T> {
+struct InnerLocalCache<T, S = DefaultHashBuilder> {
     max_numbers: usize,
     max_age_ns: u128,
-    lru_head: Option<NonNull<CacheEntity<T>>>,
-    lru_tail: Option<NonNull<CacheEntity<T>>>,
-    exp_head: Option<NonNull<CacheEntity<T>>>,
-    exp_tail: Option<NonNull<CacheEntity<T>>>,
-    map: HashMap<String, NonNull<CacheEntity<T>>>,
+    negative_ttl_ns: u128,
+    // A negative TTL was set, so failed loads are cached as negative entries.
+    negative_caching: bool,
+    idle_ns: Option<u128>,
+    sliding: bool,
+    // Expired entries each lookup sweeps, see `LocalCacheBuilder::sweep_on_read`.
+    sweep_on_read: usize,
+    clock: Arc<dyn Clock>,
+    cold: Option<ColdStorage<T>>,
+    cold_len: usize,
+    #[cfg(feature = "std")]
+    store: Option<Arc<dyn Store<T>>>,
+    backing: Option<Arc<dyn BackingStore<String, T>>>,
+    checksum: Option<fn(&T) -> u32>,
+    value_size: Option<fn(&T) -> usize>,
+    // See `LocalCacheBuilder::max_bytes`; `bytes` sums the entries' sizes.
+    max_bytes: Option<usize>,
+    bytes: usize,
+    weak_values: bool,
+    policy: EvictionPolicy,
+    // Main list entries by (hits, last access tick), under LFU.
+    lfu: BTreeMap<(u32, u64), NodeId>,
+    tick: u64,
+    pool: policy::SamplePool,
+    sketch: Option<policy::FrequencySketch>,
+    window_len: usize,
+    window_max: usize,
+    protected_len: usize,
+    protected_max: usize,
+    lru_head: Link,
+    lru_tail: Link,
+    window_head: Link,
+    window_tail: Link,
+    protected_head: Link,
+    protected_tail: Link,
+    pinned_head: Link,
+    pinned_tail: Link,
+    low_head: Link,
+    low_tail: Link,
+    high_head: Link,
+    high_tail: Link,
+    pinned_len: usize,
+    pinned_count: bool,
+    cold_head: Link,
+    cold_tail: Link,
+    wheel: wheel::TimerWheel,
+    map: keymap::KeyMap<T, S>,
+    // The map's keys in order, see `LocalCacheBuilder::prefix_index`.
+    prefix_index: Option<BTreeSet<Key>>,
+    // Tag to the keys carrying it.
+    tags: HashMap<String, HashSet<Key>>,
+    leases: HashMap<String, lease::Lease>,
+    counters: stats::Counters,
+    #[cfg(feature = "std")]
+    repair: Option<repair::Sampler<T>>,
+    // Collects evictions while set, see `LocalCache::put_with_report`.
+    evicted: Option<Vec<Evicted<T>>>,
+    // See `LocalCache::subscribe`.
+    #[cfg(feature = "std")]
+    subscribers: Vec<(SyncSender<CacheEvent<T>>, Option<events::KeyFilter>)>,
+    // This cache's id on the bus and the bus, see `LocalCacheBuilder::invalidation_bus`.
+    #[cfg(feature = "std")]
+    bus: Option<(u64, Arc<dyn InvalidationBus>)>,
+    // Feeds the thread running `LocalCacheBuilder::on_expire`.
+    #[cfg(feature = "std")]
+    on_expire: Option<Sender<(String, Arc<T>)>>,
+    // Feeds the thread set up by `LocalCacheBuilder::write_behind`.
+    #[cfg(feature = "std")]
+    write_behind: Option<Sender<write_behind::Write<T>>>,
+    // Bumped on every change, see `LocalCacheBuilder::thread_local_front`.
+    front_generation: Option<Arc<AtomicU64>>,
+    // Hits not yet made, see `LocalCacheBuilder::promotion_buffer`.
+    promotions: Option<Arc<promotion::HitRing>>,
+    // The shard's copy for lock-free reads and the locked reads since it
+    // went stale, see `LocalCacheBuilder::read_mostly`.
+    #[cfg(feature = "read-mostly")]
+    snapshot: Option<Arc<read_mostly::ReadSnapshot<T>>>,
+    #[cfg(feature = "read-mostly")]
+    stale_reads: usize,
+    #[cfg(feature = "tracing")]
+    trace_keys: bool,
 }
 
-impl<T> InnerLocalCache<T> {
-    fn new(max_numbers: usize, max_age_ns: u128) -> Self {
+impl<T, S: BuildHasher> InnerLocalCache<T, S> {
+    // Capacities are split between shards, see `LocalCacheBuilder::share`.
+    fn new(builder: &LocalCacheBuilder<T, S>, shard: usize) -> Self
+    where
+        S: Clone,
+    {
+        let negative_ttl = builder.negative_ttl.unwrap_or(builder.max_age);
+        let max_numbers = builder.share(builder.max_numbers, shard);
         Self {
             max_numbers,
-            max_age_ns,
+            max_age_ns: ttl_ns(builder.max_age),
+            negative_ttl_ns: ttl_ns(negative_ttl),
+            negative_caching: builder.negative_ttl.is_some(),
+            idle_ns: builder.time_to_idle.map(|idle| idle.as_nanos()),
+            sliding: builder.sliding,
+            sweep_on_read: builder.sweep_on_read,
+            clock: builder.clock.clone(),
+            cold: builder.cold.as_ref().map(|cold| ColdStorage {
+                max_entries: builder.share(cold.max_entries, shard),
+                ..cold.clone()
+            }),
+            cold_len: 0,
+            #[cfg(feature = "std")]
+            store: builder.store.clone(),
+            backing: builder.backing.clone(),
+            checksum: builder.checksum,
+            value_size: builder.value_size,
+            max_bytes: builder.max_bytes.map(|max_bytes| builder.share(max_bytes, shard)),
+            bytes: 0,
+            weak_values: builder.weak_values,
+            policy: builder.policy,
+            lfu: BTreeMap::new(),
+            tick: 0,
+            pool: policy::SamplePool::new(),
+            sketch: match builder.admission {
+                Admission::Always => None,
+                Admission::TinyLfu => Some(policy::FrequencySketch::new(max_numbers)),
+            },
+            window_len: 0,
+            window_max: (max_numbers / 100).max(1),
+            protected_len: 0,
+            protected_max: match builder.policy {
+                EvictionPolicy::Slru { protected_percent } => max_numbers * protected_percent.min(100) as usize / 100,
+                _ => 0,
+            },
             lru_head: None,
             lru_tail: None,
-            exp_head: None,
-            exp_tail: None,
-            map: Default::default(),
+            window_head: None,
+            window_tail: None,
+            protected_head: None,
+            protected_tail: None,
+            pinned_head: None,
+            pinned_tail: None,
+            low_head: None,
+            low_tail: None,
+            high_head: None,
+            high_tail: None,
+            pinned_len: 0,
+            pinned_count: builder.pinned_count,
+            cold_head: None,
+            cold_tail: None,
+            wheel: wheel::TimerWheel::new(builder.clock.now_ns()),
+            map: keymap::KeyMap::with_hasher(builder.hasher.clone()),
+            prefix_index: builder.prefix_index.then(BTreeSet::new),
+            tags: Default::default(),
+            leases: Default::default(),
+            counters: Default::default(),
+            #[cfg(feature = "std")]
+            repair: None,
+            evicted: None,
+            #[cfg(feature = "std")]
+            subscribers: Vec::new(),
+            #[cfg(feature = "std")]
+            bus: None,
+            #[cfg(feature = "std")]
+            on_expire: None,
+            #[cfg(feature = "std")]
+            write_behind: None,
+            front_generation: None,
+            promotions: builder.promotion_buffer.map(|capacity| Arc::new(promotion::HitRing::new(capacity))),
+            #[cfg(feature = "read-mostly")]
+            snapshot: None,
+            #[cfg(feature = "read-mostly")]
+            stale_reads: 0,
+            #[cfg(feature = "tracing")]
+            trace_keys: builder.trace_keys,
         }
     }
-    unsafe fn get(&mut self, key: &String) -> Option<Arc<T>> {
-        let value = self.map.get(key);
-        if value.is_none() {
-            return None;
+
+    fn now(&self) -> u128 {
+        self.clock.now_ns()
+    }
+
+    // Cold entries are kept on their own LRU list and don't count against
+    // `max_numbers`; neither do pinned ones unless configured to.
+    fn hot_len(&self) -> usize {
+        let pinned = if self.pinned_count { 0 } else { self.pinned_len };
+        self.map.len() - self.cold_len - pinned
+    }
+
+    // LRU list tails, coldest list first.
+    fn lru_tails(&self) -> [Link; 7] {
+        [
+            self.cold_tail,
+            self.low_tail,
+            self.lru_tail,
+            self.protected_tail,
+            self.window_tail,
+            self.high_tail,
+            self.pinned_tail,
+        ]
+    }
+
+    // LRU list heads, hottest list first.
+    #[cfg(feature = "std")]
+    fn lru_heads(&self) -> [Link; 7] {
+        [
+            self.pinned_head,
+            self.high_head,
+            self.window_head,
+            self.protected_head,
+            self.lru_head,
+            self.low_head,
+            self.cold_head,
+        ]
+    }
+
+    fn lru_list(&mut self, id: NodeId) -> (&mut Link, &mut Link) {
+        let entity = &self.map[id];
+        match (matches!(entity.value, Slot::Cold(_)), entity.segment) {
+            (true, _) => (&mut self.cold_head, &mut self.cold_tail),
+            (_, Segment::Window) => (&mut self.window_head, &mut self.window_tail),
+            (_, Segment::Main) => (&mut self.lru_head, &mut self.lru_tail),
+            (_, Segment::Protected) => (&mut self.protected_head, &mut self.protected_tail),
+            (_, Segment::Pinned) => (&mut self.pinned_head, &mut self.pinned_tail),
+            (_, Segment::Low) => (&mut self.low_head, &mut self.low_tail),
+            (_, Segment::High) => (&mut self.high_head, &mut self.high_tail),
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Lookup<T> {
+        let lookup = self.find(key);
+        self.record_get(key, &lookup);
+        lookup
+    }
+
+    // The bookkeeping `get` does for each lookup.
+    #[cfg_attr(not(feature = "std"), allow(unused_variables))]
+    fn record_get(&mut self, key: &str, lookup: &Lookup<T>) {
+        let now = self.now();
+        if self.sweep_on_read > 0 {
+            self.evict_expired(now, self.sweep_on_read);
+        }
+        self.counters.record(lookup, now);
+        #[cfg(feature = "std")]
+        self.sample_hit(key, lookup);
+        #[cfg(feature = "read-mostly")]
+        self.refresh_snapshot();
+    }
+
+    // Called on every change to the shard's entries or their expiry times.
+    fn changed(&self) {
+        if let Some(generation) = &self.front_generation {
+            generation.fetch_add(1, Ordering::Release);
+        }
+        #[cfg(feature = "read-mostly")]
+        if let Some(snapshot) = &self.snapshot {
+            snapshot.mark_stale();
         }
-        let mut non_null = value.unwrap().clone();
-        let entity = non_null.as_mut();
-        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    }
+
+    fn find(&mut self, key: &str) -> Lookup<T> {
+        match self.find_resident(key) {
+            Some(lookup) => lookup,
+            None => self.load_missing(key),
+        }
+    }
+
+    // Like `find`, but `None` where `find` goes on to `load_missing`.
+    fn find_resident(&mut self, key: &str) -> Option<Lookup<T>> {
+        if let Some(sketch) = &mut self.sketch {
+            sketch.increment(key);
+        }
+        let id = self.map.find(key)?;
+        let now = self.now();
+        let entity = &mut self.map[id];
         if now > entity.exp {
+            self.expire(key);
             return None;
         }
-        self.remove_lru(non_null.clone());
-        if self.lru_head.is_some() {
-            let mut old_lru_head = self.lru_head.unwrap();
-            old_lru_head.as_mut().lru_prev = Some(non_null.clone());
-            entity.lru_next = self.lru_head.clone();
+        entity.hits = entity.hits.saturating_add(1);
+        entity.accesses += 1;
+        entity.last_access = now;
+        if self.sliding {
+            entity.deadline = now.saturating_add(entity.ttl_ns);
+        }
+        if self.sliding || self.idle_ns.is_some() {
+            let exp = self.idle_ns.map_or(entity.deadline, |idle_ns| entity.deadline.min(now + idle_ns));
+            self.set_exp(id, exp);
+        }
+        let entity = &self.map[id];
+        let value = match &entity.value {
+            Slot::Hot(value) => {
+                if !self.verify(value, entity.checksum) {
+                    self.remove(key);
+                    return Some(Lookup::Corrupted);
+                }
+                value.clone()
+            }
+            Slot::Negative => {
+                self.touch_buffered(id);
+                return Some(Lookup::Negative);
+            }
+            Slot::Weak(value) => {
+                let Some(value) = value.upgrade() else {
+                    self.remove(key);
+                    return None;
+                };
+                if !self.verify(&value, entity.checksum) {
+                    self.remove(key);
+                    return Some(Lookup::Corrupted);
+                }
+                value
+            }
+            Slot::Cold(bytes) => {
+                let cold = self.cold.as_ref().unwrap();
+                let Some(value) = cold.codec.decode(bytes) else {
+                    self.remove(key);
+                    return Some(Lookup::Miss);
+                };
+                if !self.verify(&value, entity.checksum) {
+                    self.remove(key);
+                    return Some(Lookup::Corrupted);
+                }
+                let cold = self.cold.as_ref().unwrap();
+                let value = Arc::new(value);
+                if entity.hits >= cold.promote_hits {
+                    self.promote(id, value.clone());
+                    return Some(Lookup::Hit(value));
+                }
+                value
+            }
+        };
+        self.touch_buffered(id);
+        Some(Lookup::Hit(value))
+    }
+
+    // Records a hit for the eviction policy.
+    fn touch(&mut self, id: NodeId) {
+        if matches!(self.policy, EvictionPolicy::Fifo | EvictionPolicy::Sampled { .. }) {
+            return;
+        }
+        if self.in_lfu(&self.map[id]) {
+            self.lfu_unlink(id);
+            self.lfu_link(id);
+            return;
+        }
+        self.remove_lru(id);
+        let entity = &self.map[id];
+        if matches!(self.policy, EvictionPolicy::Slru { .. })
+            && entity.segment == Segment::Main
+            && !matches!(entity.value, Slot::Cold(_))
+        {
+            self.protect(id);
+            return;
+        }
+        self.push_lru_front(id);
+    }
+
+    // Moves a hot entry to the front of another segment's list.
+    fn resegment(&mut self, id: NodeId, segment: Segment) {
+        self.remove_lru(id);
+        if let Some(len) = self.segment_len(self.map[id].segment) {
+            *len -= 1;
+        }
+        self.map[id].segment = segment;
+        if let Some(len) = self.segment_len(segment) {
+            *len += 1;
         }
-        if self.lru_tail.is_none() {
-            self.lru_tail = Some(non_null.clone());
+        self.push_lru_front(id);
+    }
+
+    fn segment_len(&mut self, segment: Segment) -> Option<&mut usize> {
+        match segment {
+            Segment::Window => Some(&mut self.window_len),
+            Segment::Protected => Some(&mut self.protected_len),
+            Segment::Pinned => Some(&mut self.pinned_len),
+            Segment::Main | Segment::Low | Segment::High => None,
+        }
+    }
+
+    // Moves a re-accessed probation entry to the protected segment, pushing
+    // the protected segment's LRU entries back to probation when it is full.
+    fn protect(&mut self, id: NodeId) {
+        self.map[id].segment = Segment::Protected;
+        self.protected_len += 1;
+        self.push_lru_front(id);
+        while self.protected_len > self.protected_max {
+            let tail = self.protected_tail.unwrap();
+            self.remove_lru(tail);
+            self.map[tail].segment = Segment::Main;
+            self.protected_len -= 1;
+            self.push_lru_front(tail);
+        }
+    }
+
+    // The entry to drop next for capacity: low priority entries first, then
+    // whatever the eviction policy picks from the main list, then high
+    // priority entries.
+    fn victim(&self) -> Link {
+        if self.low_tail.is_some() {
+            return self.low_tail;
         }
-        self.lru_head = Some(non_null);
+        let victim = match self.policy {
+            EvictionPolicy::Lru | EvictionPolicy::Fifo => self.lru_tail,
+            EvictionPolicy::Lfu => self.lfu.first_key_value().map(|(_, &victim)| victim),
+            EvictionPolicy::Slru { .. } => self.lru_tail.or(self.protected_tail),
+            EvictionPolicy::Sampled { samples } => self.pool.oldest(&self.map, samples),
+        };
+        victim.or(self.high_tail)
+    }
 
-        Some(entity.value.clone())
+    fn in_lfu(&self, entity: &CacheEntity<T>) -> bool {
+        self.policy == EvictionPolicy::Lfu && entity.segment == Segment::Main && !matches!(entity.value, Slot::Cold(_))
     }
-    unsafe fn put(&mut self, key: String, value: Arc<T>) {
-        self.remove(&key);
 
-        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
-        self.clean(now);
+    fn in_pool(&self, entity: &CacheEntity<T>) -> bool {
+        matches!(self.policy, EvictionPolicy::Sampled { .. })
+            && entity.segment == Segment::Main
+            && !matches!(entity.value, Slot::Cold(_))
+    }
 
-        let exp = now + self.max_age_ns;
+    fn lfu_link(&mut self, id: NodeId) {
+        self.tick += 1;
+        let entity = &mut self.map[id];
+        entity.lfu_key = (entity.hits, self.tick);
+        self.lfu.insert(entity.lfu_key, id);
+    }
 
-        let new_entity = Box::new(CacheEntity {
-            key: key.clone(),
-            value,
+    fn lfu_unlink(&mut self, id: NodeId) {
+        self.lfu.remove(&self.map[id].lfu_key);
+    }
+    fn put(&mut self, key: Key, value: Option<Arc<T>>) {
+        if self.try_put(key, value).is_err() {
+            handle_alloc_error(Layout::new::<CacheEntity<T>>());
+        }
+    }
+    fn try_put(&mut self, key: Key, value: Option<Arc<T>>) -> Result<(), CacheError> {
+        match value {
+            Some(value) => self.try_write(key, value, self.max_age_ns),
+            None => self.try_put_with_ttl(key, None, self.negative_ttl_ns),
+        }
+    }
+    fn write(&mut self, key: Key, value: Arc<T>, ttl_ns: u128) {
+        if self.try_write(key, value, ttl_ns).is_err() {
+            handle_alloc_error(Layout::new::<CacheEntity<T>>());
+        }
+    }
+    // Writes through only once the entry is in, so that a failed insert
+    // reaches neither the backing store nor the other caches.
+    fn try_write(&mut self, key: Key, value: Arc<T>, ttl_ns: u128) -> Result<(), CacheError> {
+        self.try_put_with_ttl(key.clone(), Some(value.clone()), ttl_ns)?;
+        self.write_through(&key, &value, ttl_ns);
+        Ok(())
+    }
+    fn verify(&self, value: &T, checksum: u32) -> bool {
+        self.checksum.is_none_or(|checksum_of| checksum_of(value) == checksum)
+    }
+    // Every write of a value goes through here, so it also tells the other
+    // caches on the bus to drop their copy.
+    fn write_through(&self, key: &str, value: &Arc<T>, ttl_ns: u128) {
+        if let Some(backing) = &self.backing {
+            let ttl = remaining(ttl_ns, 0);
+            #[cfg(feature = "std")]
+            let queued = self.write_behind(|| write_behind::Write::Store(key.to_string(), value.clone(), ttl));
+            #[cfg(not(feature = "std"))]
+            let queued = false;
+            if !queued {
+                backing.store_with_ttl(&key.to_string(), value, ttl);
+            }
+        }
+        #[cfg(feature = "std")]
+        self.publish(Invalidation::Key(key.to_string()));
+    }
+    fn put_with_ttl(&mut self, key: Key, value: Option<Arc<T>>, ttl_ns: u128) {
+        if self.try_put_with_ttl(key, value, ttl_ns).is_err() {
+            handle_alloc_error(Layout::new::<CacheEntity<T>>());
+        }
+    }
+    // On failure the old entry for `key`, if any, is already gone.
+    fn try_put_with_ttl(&mut self, key: Key, value: Option<Arc<T>>, ttl_ns: u128) -> Result<(), CacheError> {
+        self.insert_entry(key, value, ttl_ns, true)
+    }
+    // Without `make_room` the hot set may end up over capacity, see `trim`.
+    #[cfg_attr(not(feature = "std"), allow(unused_variables))]
+    fn insert_entry(&mut self, key: Key, value: Option<Arc<T>>, ttl_ns: u128, make_room: bool) -> Result<(), CacheError> {
+        self.apply_promotions();
+        self.changed();
+        let replaced = self.remove(&key).is_some();
+        #[cfg(feature = "std")]
+        if let Some(store) = &self.store {
+            store.remove(&key);
+        }
+
+        let now = self.now();
+        if make_room {
+            self.clean(now);
+        }
+
+        let deadline = now.saturating_add(ttl_ns);
+        self.counters.inserted(&key, ttl_ns);
+        #[cfg(feature = "tracing")]
+        tracing::trace!(key = self.traced_key(&key), ttl_ms = (ttl_ns / 1_000_000) as u64, "insert");
+        let exp = self.idle_ns.map_or(deadline, |idle_ns| deadline.min(now + idle_ns));
+        let checksum = match (&value, self.checksum) {
+            (Some(value), Some(checksum_of)) => checksum_of(value),
+            _ => 0,
+        };
+
+        let id = self.map.try_insert(CacheEntity {
+            key,
+            value: match value {
+                Some(value) if self.weak_values => Slot::Weak(Arc::downgrade(&value)),
+                Some(value) => Slot::Hot(value),
+                None => Slot::Negative,
+            },
             exp,
+            deadline,
+            ttl_ns,
+            source: DEFAULT_SOURCE,
+            tags: Box::default(),
+            created: now,
+            last_access: 0,
+            accesses: 0,
+            hits: 0,
+            checksum,
+            segment: if self.sketch.is_some() { Segment::Window } else { Segment::Main },
+            size: 0,
+            lfu_key: (0, 0),
+            pool_index: 0,
             lru_prev: None,
-            lru_next: self.lru_head.clone(),
-            exp_prev: self.exp_tail.clone(),
+            lru_next: None,
+            exp_prev: None,
             exp_next: None,
-        });
-        let mut cur_entity = NonNull::from(Box::leak(new_entity));
-
-        let _ = self.map.insert(key, cur_entity.clone());
-        let old_lru_head = self.lru_head.replace(cur_entity.clone());
-        let old_exp_head = self.exp_head.replace(cur_entity.clone());
-        match self.map.len() {
-            0 | 1 => {
-                let _ = self.lru_tail.replace(cur_entity.clone());
-                let _ = self.exp_tail.replace(cur_entity);
-                return;
+            wheel_slot: 0,
+        })?;
+        if let Some(index) = &mut self.prefix_index {
+            index.insert(self.map[id].key.clone());
+        }
+        self.push_lru_front(id);
+        self.wheel.insert(&mut self.map, id);
+        if self.sketch.is_some() {
+            self.window_len += 1;
+        }
+        self.recount(id);
+        #[cfg(feature = "std")]
+        self.emit_insert(id, replaced);
+        if self.sketch.is_some() {
+            self.admit();
+        }
+        if make_room {
+            // Only over capacity if the capacity is 0.
+            self.trim();
+        }
+        Ok(())
+    }
+
+    // Re-encodes a hot entry into the cold list, evicting the coldest cold
+    // entry if that overflows it. Returns false if the entry can't be demoted.
+    fn demote(&mut self, id: NodeId) -> bool {
+        let Some(cold) = self.cold.as_ref() else {
+            return false;
+        };
+        let Slot::Hot(value) = &self.map[id].value else {
+            return false;
+        };
+        let Some(bytes) = cold.codec.encode(value) else {
+            return false;
+        };
+        let bytes = bytes.into_boxed_slice();
+        let max_cold = cold.max_entries;
+        self.remove_lru(id);
+        let entity = &mut self.map[id];
+        match entity.segment {
+            Segment::Window => self.window_len -= 1,
+            Segment::Protected => self.protected_len -= 1,
+            Segment::Pinned => self.pinned_len -= 1,
+            Segment::Main | Segment::Low | Segment::High => {}
+        }
+        entity.segment = Segment::Main;
+        entity.value = Slot::Cold(bytes);
+        entity.hits = 0;
+        self.cold_len += 1;
+        self.push_lru_front(id);
+        self.recount(id);
+        if self.cold_len > max_cold {
+            self.evict(self.cold_tail.unwrap());
+        }
+        true
+    }
+
+    fn promote(&mut self, id: NodeId, value: Arc<T>) {
+        self.remove_lru(id);
+        let entity = &mut self.map[id];
+        entity.value = Slot::Hot(value);
+        entity.hits = 0;
+        self.cold_len -= 1;
+        self.push_lru_front(id);
+        self.recount(id);
+        while self.hot_len() > self.max_numbers {
+            match self.victim() {
+                Some(tail) if tail != id => {
+                    if !self.demote(tail) {
+                        self.evict(tail);
+                    }
+                }
+                _ => break,
             }
-            _ => {
-                let mut old_lru_head = old_lru_head.unwrap();
-                let inner_old_lru_head = old_lru_head.as_mut();
-                inner_old_lru_head.lru_prev = Some(cur_entity.clone());
-                cur_entity.as_mut().lru_next = Some(old_lru_head.clone());
-
-                let mut old_exp_head = old_exp_head.unwrap();
-                let inner_old_exp_head = old_exp_head.as_mut();
-                inner_old_exp_head.exp_prev = Some(cur_entity.clone());
-                cur_entity.as_mut().exp_next = Some(old_exp_head.clone());
+        }
+        self.fit_bytes();
+    }
+
+    // Moves entries overflowing the TinyLFU window to the main list, then
+    // trims the hot set back to capacity by evicting whichever of the
+    // candidate and the main list's LRU entry is estimated less frequent.
+    fn admit(&mut self) {
+        while self.window_len > self.window_max {
+            let candidate = self.window_tail.unwrap();
+            self.remove_lru(candidate);
+            self.map[candidate].segment = Segment::Main;
+            self.window_len -= 1;
+            self.push_lru_front(candidate);
+            if self.hot_len() <= self.max_numbers {
+                continue;
+            }
+            let sketch = self.sketch.as_ref().unwrap();
+            match self.victim() {
+                Some(victim)
+                    if victim != candidate
+                        && sketch.frequency(&self.map[candidate].key) > sketch.frequency(&self.map[victim].key) =>
+                {
+                    if !self.demote(victim) {
+                        self.evict(victim);
+                    }
+                }
+                _ => {
+                    if !self.demote(candidate) {
+                        self.evict(candidate);
+                    }
+                }
             }
         }
     }
 
-    unsafe fn clean(&mut self, now: u128) {
-        if self.map.len() < self.max_numbers {
-            return;
+    fn push_lru_front(&mut self, id: NodeId) {
+        let (head, tail) = self.lru_list(id);
+        let old_lru_head = head.replace(id);
+        match old_lru_head {
+            Some(old_lru_head) => self.map[old_lru_head].lru_prev = Some(id),
+            None => *tail = Some(id),
         }
-        let mut cur = self.exp_tail.clone();
-        while cur.is_some() {
-            let e = cur.unwrap();
-            let b = e.as_ref();
-            if b.exp > now {
-                break;
-            }
-            self.remove(&b.key);
-            cur = b.exp_prev.clone();
+        let entity = &mut self.map[id];
+        entity.lru_prev = None;
+        entity.lru_next = old_lru_head;
+        if self.in_lfu(&self.map[id]) {
+            self.lfu_link(id);
         }
-        while self.map.len() >= self.max_numbers {
-            let key = self.lru_tail.map(|e| e.as_ref().key.clone()).unwrap();
-            self.remove(&key);
+        if self.in_pool(&self.map[id]) {
+            self.pool.insert(&mut self.map, id);
         }
     }
 
-    unsafe fn remove(&mut self, key: &String) {
-        let old = self.map.remove(key);
-        if old.is_none() {
+    fn clean(&mut self, now: u128) {
+        if self.hot_len() < self.max_numbers {
+            return;
+        }
+        self.evict_expired(now, usize::MAX);
+        if self.sketch.is_some() {
+            // Capacity is enforced by `admit` once the new entry is in.
             return;
         }
+        while self.hot_len() >= self.max_numbers {
+            let Some(tail) = self.victim() else {
+                break;
+            };
+            if !self.demote(tail) {
+                self.evict(tail);
+            }
+        }
+    }
 
-        let old = old.unwrap();
-        self.remove_lru(old.clone());
-        self.remove_exp(old.clone());
-        let _ = Box::from_raw(old.as_ptr());
+    // Evicts (or demotes) entries until the hot set fits the capacity, and
+    // the shard its byte budget, again.
+    fn trim(&mut self) {
+        while self.hot_len() > self.max_numbers {
+            let Some(victim) = self.victim() else {
+                break;
+            };
+            if !self.demote(victim) {
+                self.evict(victim);
+            }
+        }
+        self.fit_bytes();
     }
 
-    unsafe fn remove_lru(&mut self, mut non_null: NonNull<CacheEntity<T>>) {
-        let entity = non_null.as_mut();
-        let key = &entity.key;
-        entity.lru_prev.clone().inspect(|e| {
-            e.clone().as_mut().lru_next = entity.lru_next.clone()
-        });
-        entity.lru_next.clone().inspect(|e| {
-            e.clone().as_mut().lru_prev = entity.lru_prev.clone()
-        });
+    // Removes an entry for capacity, spilling it to the store if there is one.
+    fn evict(&mut self, id: NodeId) {
+        let entity = &self.map[id];
+        #[cfg(feature = "std")]
+        if let Some(store) = &self.store {
+            let expires_at = UNIX_EPOCH + Duration::from_nanos(u64::try_from(entity.exp).unwrap_or(u64::MAX));
+            let checksum = self.checksum.map(|_| entity.checksum);
+            match &entity.value {
+                Slot::Hot(value) => store.save(&entity.key, Spilled { value: &**value, expires_at, checksum }),
+                Slot::Cold(bytes) => {
+                    if let Some(value) = self.cold.as_ref().and_then(|cold| cold.codec.decode(bytes)) {
+                        store.save(&entity.key, Spilled { value: &value, expires_at, checksum });
+                    }
+                }
+                Slot::Weak(value) => {
+                    if let Some(value) = value.upgrade() {
+                        store.save(&entity.key, Spilled { value: &*value, expires_at, checksum });
+                    }
+                }
+                Slot::Negative => {}
+            }
+        }
+        let key = entity.key.clone();
+        if let Some(old) = self.remove(&key) {
+            self.report(old, EvictionReason::Capacity);
+        }
+        self.counters.evictions += 1;
+    }
 
+    // Looks for a key missing from memory in the spill store, which moves
+    // the entry back into memory, and then falls through to the backing store.
+    fn load_missing(&mut self, key: &str) -> Lookup<T> {
+        #[cfg(feature = "std")]
+        if let Some(store) = &self.store {
+            let spilled = store.load(key);
+            return self.load_spilled(key, spilled);
+        }
+        self.load_backing(key)
+    }
 
-        if let Some(lru_head) = self.lru_head.clone() {
-            if &lru_head.as_ref().key == key {
-                self.lru_head = entity.lru_next.clone();
+    // `load_missing` once the spill store has been read, which `get_async`
+    // does without holding the shard lock.
+    #[cfg(feature = "std")]
+    fn load_spilled(&mut self, key: &str, spilled: Option<Spilled<T>>) -> Lookup<T> {
+        if let Some(store) = self.store.clone() {
+            if let Some(Spilled { value, expires_at, checksum }) = spilled {
+                store.remove(key);
+                if checksum.is_some_and(|checksum| !self.verify(&value, checksum)) {
+                    return Lookup::Corrupted;
+                }
+                let now = self.now();
+                let exp = expires_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+                if exp > now {
+                    let value = Arc::new(value);
+                    self.put_with_ttl(key.into(), Some(value.clone()), exp - now);
+                    self.tag(key, "store");
+                    return Lookup::Hit(value);
+                }
             }
         }
-        if let Some(lru_tail) = self.lru_tail.clone() {
-            if &lru_tail.as_ref().key == key {
-                self.lru_tail = entity.lru_prev.clone();
+        self.load_backing(key)
+    }
+
+    fn load_backing(&mut self, key: &str) -> Lookup<T> {
+        if let Some(backing) = self.backing.clone() {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::info_span!("backing store load", key = self.traced_key(key)).entered();
+            if let Some(value) = backing.load(&key.to_string()) {
+                let value = Arc::new(value);
+                self.put_with_ttl(key.into(), Some(value.clone()), self.max_age_ns);
+                self.tag(key, "backing store");
+                return Lookup::Hit(value);
             }
         }
+        Lookup::Miss
     }
-    unsafe fn remove_exp(&mut self, mut non_null: NonNull<CacheEntity<T>>) {
-        let entity = non_null.as_mut();
-        let key = &entity.key;
-        entity.exp_prev.clone().inspect(|e| {
-            e.clone().as_mut().exp_next = entity.exp_next.clone()
-        });
-        entity.exp_next.clone().inspect(|e| {
-            e.clone().as_mut().exp_prev = entity.exp_prev.clone()
-        });
 
+    // Removes up to `budget` expired entries, roughly soonest expiry first.
+    // Returns how many were removed.
+    fn evict_expired(&mut self, now: u128, budget: usize) -> usize {
+        let mut removed = 0;
+        while removed < budget {
+            let Some(e) = self.wheel.peek_expired(&mut self.map, now) else {
+                break;
+            };
+            let key = self.map[e].key.clone();
+            self.expire(&key);
+            removed += 1;
+        }
+        #[cfg(feature = "tracing")]
+        if removed > 0 {
+            tracing::debug!(removed, "expiry sweep");
+        }
+        removed
+    }
 
-        if let Some(exp_head) = self.exp_head.clone() {
-            if &exp_head.as_ref().key == key {
-                self.exp_head = entity.exp_next.clone();
+    // Removes an entry that has expired, reporting it.
+    fn expire(&mut self, key: &str) {
+        if let Some(old) = self.remove(key) {
+            self.counters.expirations += 1;
+            self.report(old, EvictionReason::Expired);
+        }
+    }
+
+    fn remove(&mut self, key: &str) -> Option<CacheEntity<T>> {
+        self.apply_promotions();
+        let id = self.map.find(key)?;
+        self.changed();
+        if let Some(index) = &mut self.prefix_index {
+            index.remove(key);
+        }
+        self.remove_lru(id);
+        self.wheel.remove(&mut self.map, id);
+        let old = self.map.remove(id);
+        self.bytes -= old.size;
+        if !old.tags.is_empty() {
+            self.untag(key, &old.tags);
+        }
+        match (&old.value, old.segment) {
+            (Slot::Cold(_), _) => self.cold_len -= 1,
+            (_, Segment::Window) => self.window_len -= 1,
+            (_, Segment::Protected) => self.protected_len -= 1,
+            (_, Segment::Pinned) => self.pinned_len -= 1,
+            (_, Segment::Main | Segment::Low | Segment::High) => {}
+        }
+        Some(old)
+    }
+
+    // The live value of `key` in memory, without counting a hit or touching
+    // the eviction order.
+    fn peek(&self, key: &str) -> Option<Arc<T>> {
+        let entity = self.map.get(key)?;
+        let now = self.now();
+        if now > entity.exp {
+            return None;
+        }
+        match &entity.value {
+            Slot::Hot(value) => Some(value.clone()),
+            Slot::Cold(bytes) => self.cold.as_ref().and_then(|cold| cold.codec.decode(bytes)).map(Arc::new),
+            Slot::Negative => None,
+            Slot::Weak(value) => value.upgrade(),
+        }
+    }
+
+    // Removes `key` from memory, the spill store and the backing store.
+    // Returns the value if it was live.
+    fn take(&mut self, key: &str) -> Option<Arc<T>> {
+        #[cfg(feature = "std")]
+        if let Some(store) = &self.store {
+            store.remove(key);
+        }
+        if let Some(backing) = &self.backing {
+            #[cfg(feature = "std")]
+            let queued = self.write_behind(|| write_behind::Write::Delete(key.to_string()));
+            #[cfg(not(feature = "std"))]
+            let queued = false;
+            if !queued {
+                backing.delete(&key.to_string());
             }
         }
-        if let Some(exp_tail) = self.exp_tail.clone() {
-            if &exp_tail.as_ref().key == key {
-                self.exp_tail = entity.exp_prev.clone();
+        #[cfg(feature = "std")]
+        self.publish(Invalidation::Key(key.to_string()));
+        let old = self.remove(key)?;
+        let now = self.now();
+        if now > old.exp {
+            return None;
+        }
+        match old.value {
+            Slot::Hot(value) => Some(value),
+            Slot::Cold(bytes) => self.cold.as_ref().and_then(|cold| cold.codec.decode(&bytes)).map(Arc::new),
+            Slot::Negative => None,
+            Slot::Weak(value) => value.upgrade(),
+        }
+    }
+
+    fn remove_lru(&mut self, id: NodeId) {
+        let (prev, next) = (self.map[id].lru_prev, self.map[id].lru_next);
+        if let Some(e) = prev {
+            self.map[e].lru_next = next;
+        }
+        if let Some(e) = next {
+            self.map[e].lru_prev = prev;
+        }
+
+        let (head, tail) = self.lru_list(id);
+        if *head == Some(id) {
+            *head = next;
+        }
+        if *tail == Some(id) {
+            *tail = prev;
+        }
+        if self.in_lfu(&self.map[id]) {
+            self.lfu_unlink(id);
+        }
+        if self.in_pool(&self.map[id]) {
+            self.pool.remove(&mut self.map, id);
+        }
+    }
+    fn tag(&mut self, key: &str, source: &'static str) {
+        if let Some(id) = self.map.find(key) {
+            self.map[id].source = source;
+        }
+    }
+
+    fn set_exp(&mut self, id: NodeId, exp: u128) {
+        self.changed();
+        self.wheel.remove(&mut self.map, id);
+        self.map[id].exp = exp;
+        self.wheel.insert(&mut self.map, id);
+    }
+
+    // Writes one line per entry in LRU order. The lists may have been left
+    // half updated by a panic, so a dangling link ends a list rather than
+    // panicking and no more than `map.len()` entries are followed.
+    #[cfg(feature = "std")]
+    fn dump(&self, file: &mut File) -> io::Result<usize> {
+        let mut written = 0;
+        for head in self.lru_heads() {
+            let mut cur = head;
+            while let Some(b) = cur.and_then(|e| self.map.node(e)).filter(|_| written < self.map.len()) {
+                writeln!(file, "{:?}\t{}\t{}", b.key, b.exp, b.source)?;
+                written += 1;
+                cur = b.lru_next;
             }
         }
+        Ok(written)
+    }
+}
+
+/// Result of [`LocalCache::lookup`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum Lookup<T> {
+    Hit(Arc<T>),
+    /// The key was cached as absent with [`LocalCache::put_negative`].
+    Negative,
+    Miss,
+    /// The value failed its checksum and was evicted, see
+    /// [`LocalCacheBuilder::checksums`].
+    Corrupted,
+}
+
+pub struct LocalCacheBuilder<T, S = DefaultHashBuilder> {
+    max_numbers: usize,
+    max_age: Duration,
+    negative_ttl: Option<Duration>,
+    time_to_idle: Option<Duration>,
+    sliding: bool,
+    sweep_on_read: usize,
+    clock: Arc<dyn Clock>,
+    cold: Option<ColdStorage<T>>,
+    #[cfg(feature = "std")]
+    store: Option<Arc<dyn Store<T>>>,
+    backing: Option<Arc<dyn BackingStore<String, T>>>,
+    loader: Option<Arc<dyn CacheLoader<String, T>>>,
+    async_loader: Option<Arc<dyn AsyncCacheLoader<String, T>>>,
+    checksum: Option<fn(&T) -> u32>,
+    value_size: Option<fn(&T) -> usize>,
+    max_bytes: Option<usize>,
+    weak_values: bool,
+    policy: EvictionPolicy,
+    admission: Admission,
+    pinned_count: bool,
+    prefix_index: bool,
+    shards: usize,
+    // Relative shard capacities, see `LocalCacheBuilder::shard_weights`.
+    shard_weights: Option<Vec<usize>>,
+    router: Option<Arc<dyn ShardRouter>>,
+    // See `LocalCacheBuilder::shard_lock`.
+    shard_lock: Option<lock::NewLock>,
+    #[cfg(feature = "std")]
+    insert_queue: Option<(usize, QueueSpawner<T, S>)>,
+    #[cfg(feature = "std")]
+    read_repair: Option<(ReadRepair, RepairSpawner<T, S>)>,
+    #[cfg(feature = "std")]
+    invalidation_bus: Option<(Arc<dyn InvalidationBus>, InvalidationSpawner<T, S>)>,
+    #[cfg(feature = "std")]
+    on_expire: Option<(expire::ExpireHook<T>, ExpireSpawner<T, S>)>,
+    #[cfg(feature = "std")]
+    write_behind: Option<(WriteBehind, WriteBehindSpawner<T, S>)>,
+    #[cfg(feature = "std")]
+    thread_local_front: Option<(ThreadLocalFront, FrontAttacher<T, S>)>,
+    promotion_buffer: Option<usize>,
+    #[cfg(feature = "read-mostly")]
+    read_mostly: bool,
+    #[cfg(feature = "metrics")]
+    metrics_name: Option<String>,
+    #[cfg(feature = "tracing")]
+    trace_keys: bool,
+    hasher: S,
+}
+
+#[cfg(feature = "std")]
+type QueueSpawner<T, S> = fn(&Arc<[ShardLock<InnerLocalCache<T, S>>]>, usize) -> queue::InsertQueues<T>;
+#[cfg(feature = "std")]
+type RepairSpawner<T, S> =
+    fn(&Arc<[ShardLock<InnerLocalCache<T, S>>]>, Option<Arc<dyn CacheLoader<String, T>>>, ReadRepair) -> repair::RepairWorker<T, S>;
+#[cfg(feature = "std")]
+type InvalidationSpawner<T, S> =
+    fn(&Arc<[ShardLock<InnerLocalCache<T, S>>]>, Arc<dyn ShardRouter>, Arc<dyn InvalidationBus>) -> invalidation::InvalidationWorker<T, S>;
+#[cfg(feature = "std")]
+type ExpireSpawner<T, S> = fn(&Arc<[ShardLock<InnerLocalCache<T, S>>]>, expire::ExpireHook<T>);
+#[cfg(feature = "std")]
+type FrontAttacher<T, S> = fn(&Arc<[ShardLock<InnerLocalCache<T, S>>]>, ThreadLocalFront) -> front::Front<T>;
+#[cfg(feature = "std")]
+type WriteBehindSpawner<T, S> =
+    fn(&Arc<[ShardLock<InnerLocalCache<T, S>>]>, Arc<dyn BackingStore<String, T>>, WriteBehind) -> write_behind::WriteBehindWorker<T, S>;
+
+impl<T, S> LocalCacheBuilder<T, S> {
+    fn new(hasher: S) -> Self {
+        Self {
+            max_numbers: DEFAULT_MAX_NUMBERS,
+            max_age: Duration::from_secs(DEFAULT_MAX_AGE_SECS),
+            negative_ttl: None,
+            time_to_idle: None,
+            sliding: false,
+            sweep_on_read: 0,
+            clock: Arc::new(clock::DefaultClock::default()),
+            cold: None,
+            #[cfg(feature = "std")]
+            store: None,
+            backing: None,
+            loader: None,
+            async_loader: None,
+            checksum: None,
+            value_size: None,
+            max_bytes: None,
+            weak_values: false,
+            policy: EvictionPolicy::Lru,
+            admission: Admission::Always,
+            pinned_count: false,
+            prefix_index: false,
+            shards: DEFAULT_SHARDS,
+            shard_weights: None,
+            router: None,
+            shard_lock: None,
+            #[cfg(feature = "std")]
+            insert_queue: None,
+            #[cfg(feature = "std")]
+            read_repair: None,
+            #[cfg(feature = "std")]
+            invalidation_bus: None,
+            #[cfg(feature = "std")]
+            on_expire: None,
+            #[cfg(feature = "std")]
+            write_behind: None,
+            #[cfg(feature = "std")]
+            thread_local_front: None,
+            promotion_buffer: None,
+            #[cfg(feature = "read-mostly")]
+            read_mostly: false,
+            #[cfg(feature = "metrics")]
+            metrics_name: None,
+            #[cfg(feature = "tracing")]
+            trace_keys: false,
+            hasher,
+        }
+    }
+    pub fn max_entries(mut self, max_numbers: usize) -> Self {
+        self.max_numbers = max_numbers;
+        self
+    }
+    /// The default TTL; `None` or `Duration::MAX` for entries that never
+    /// expire, and are then only evicted for capacity.
+    pub fn ttl(mut self, max_age: impl Into<Option<Duration>>) -> Self {
+        self.max_age = max_age.into().unwrap_or(Duration::MAX);
+        self
+    }
+    /// TTL of negative entries; defaults to the regular TTL. Setting it also
+    /// caches failed loads, see [`LocalCache::get_or_try_insert_with`].
+    pub fn negative_ttl(mut self, negative_ttl: Duration) -> Self {
+        self.negative_ttl = Some(negative_ttl);
+        self
+    }
+    /// Also expires entries not read for `idle`, however long their TTL.
+    pub fn time_to_idle(mut self, idle: Duration) -> Self {
+        self.time_to_idle = Some(idle);
+        self
+    }
+    /// Restarts an entry's TTL on every read, so only entries left unread
+    /// for a whole TTL expire.
+    pub fn sliding_expiration(mut self) -> Self {
+        self.sliding = true;
+        self
+    }
+    /// Demotes entries pushed out of the `max_entries` hot set into an
+    /// encoded cold set instead of evicting them.
+    pub fn cold_storage(mut self, cold: ColdStorage<T>) -> Self {
+        self.cold = Some(cold);
+        self
+    }
+    /// Spills entries evicted for capacity to `store`, and checks it on
+    /// misses before reporting them.
+    #[cfg(feature = "std")]
+    pub fn store<P: Store<T> + 'static>(mut self, store: P) -> Self {
+        self.store = Some(Arc::new(store));
+        self
+    }
+    /// Makes the cache read/write-through: `put` and `remove` are mirrored to
+    /// `backing`, and misses are loaded from it.
+    pub fn backing_store<B: BackingStore<String, T> + 'static>(mut self, backing: B) -> Self {
+        self.backing = Some(Arc::new(backing));
+        self
+    }
+    pub fn eviction_policy(mut self, policy: EvictionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+    pub fn admission(mut self, admission: Admission) -> Self {
+        self.admission = admission;
+        self
+    }
+    /// Splits the cache into `shards` independently locked parts, each
+    /// holding an equal share of the capacity with its own LRU order.
+    /// Defaults to a single shard.
+    pub fn shards(mut self, shards: usize) -> Self {
+        self.shards = shards.max(1);
+        self.shard_weights = None;
+        self
+    }
+    /// Like [`shards`](Self::shards), one shard per weight, with the
+    /// capacities (entries, bytes and cold entries) split in proportion to
+    /// the weights rather than evenly. Gives shards that a skewed key
+    /// distribution or a custom [`shard_router`](Self::shard_router) sends
+    /// more keys to the room for them. Weights of 0 count as 1.
+    pub fn shard_weights(mut self, weights: &[usize]) -> Self {
+        self.shards = weights.len().max(1);
+        self.shard_weights = (!weights.is_empty()).then(|| weights.iter().map(|&weight| weight.max(1)).collect());
+        self
+    }
+    fn share(&self, total: usize, shard: usize) -> usize {
+        share(total, self.shards, self.shard_weights.as_deref(), shard)
+    }
+    /// Decides which shard each key lives on; defaults to [`HashRouter`].
+    pub fn shard_router<R: ShardRouter + 'static>(mut self, router: R) -> Self {
+        self.router = Some(Arc::new(router));
+        self
+    }
+    /// Reads the time for expiry from `clock` instead of `SystemClock`
+    /// (`JsClock` with the `wasm` feature on wasm32). Without the `std`
+    /// feature there is no system clock: until one is set, time stays at 0
+    /// and nothing expires by time.
+    pub fn clock<C: Clock + 'static>(mut self, clock: C) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+    pub fn build(self) -> LocalCache<T, S>
+    where
+        S: BuildHasher + Clone,
+    {
+        let shards: Arc<[_]> = (0..self.shards).map(|shard| ShardLock::new(InnerLocalCache::new(&self, shard), self.shard_lock)).collect();
+        let router = self.router.unwrap_or_else(|| Arc::new(HashRouter::default()));
+        #[cfg(feature = "std")]
+        if let Some((hook, spawn)) = self.on_expire {
+            spawn(&shards, hook);
+        }
+        #[cfg(feature = "std")]
+        let front = match self.thread_local_front {
+            Some((config, attach)) if !self.sliding && self.time_to_idle.is_none() => Some(attach(&shards, config)),
+            _ => None,
+        };
+        #[cfg(feature = "read-mostly")]
+        let snapshots = (self.read_mostly && !self.sliding && self.time_to_idle.is_none())
+            .then(|| read_mostly::ReadSnapshot::attach(&shards));
+        LocalCache {
+            max_numbers: AtomicUsize::new(self.max_numbers),
+            shard_weights: self.shard_weights,
+            #[cfg(feature = "std")]
+            queues: self.insert_queue.map(|(capacity, spawn)| spawn(&shards, capacity)),
+            #[cfg(feature = "std")]
+            _repair: self.read_repair.map(|(repair, spawn)| spawn(&shards, self.loader.clone(), repair)),
+            #[cfg(feature = "std")]
+            invalidation: self.invalidation_bus.map(|(bus, spawn)| spawn(&shards, router.clone(), bus)),
+            #[cfg(feature = "std")]
+            write_behind: match (self.write_behind, self.backing) {
+                (Some((config, spawn)), Some(backing)) => Some(spawn(&shards, backing, config)),
+                _ => None,
+            },
+            loader: self.loader,
+            async_loader: self.async_loader,
+            shards,
+            router,
+            created: self.clock.now_ns(),
+            clock: self.clock,
+            lease_ids: AtomicU64::new(0),
+            #[cfg(feature = "std")]
+            front,
+            #[cfg(feature = "read-mostly")]
+            snapshots,
+            #[cfg(feature = "metrics")]
+            metrics_name: self.metrics_name,
+            #[cfg(feature = "tracing")]
+            trace_keys: self.trace_keys,
+        }
+    }
+}
+
+impl<T: AsRef<[u8]>, S> LocalCacheBuilder<T, S> {
+    /// Stores a CRC-32 of each value's bytes, checked on every hit and when
+    /// an entry comes back from the spill store. Values failing the check
+    /// are evicted and reported as [`Lookup::Corrupted`].
+    pub fn checksums(mut self) -> Self {
+        self.checksum = Some(checksum::checksum_of::<T>);
+        self
     }
 }
 
 impl<T> LocalCache<T> {
+    /// A cache of `max_numbers` entries expiring after `max_age_secs`, or
+    /// never for `u64::MAX`.
     pub fn new(max_numbers: usize, max_age_secs: u64) -> Self {
-        Self(Mutex::new(InnerLocalCache::new(max_numbers, Duration::from_secs(max_age_secs).as_nanos())))
+        Self::new_with(max_numbers, Duration::from_secs(max_age_secs))
+    }
+    /// Like [`new`](Self::new), with a TTL of any granularity down to the
+    /// nanosecond, or `Duration::MAX` for none.
+    pub fn new_with(max_numbers: usize, max_age: Duration) -> Self {
+        Self::builder().max_entries(max_numbers).ttl(max_age).build()
+    }
+    pub fn builder() -> LocalCacheBuilder<T> {
+        LocalCacheBuilder::new(DefaultHashBuilder::default())
+    }
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// Like [`builder`](Self::builder), hashing keys with `hasher` instead of
+    /// [`DefaultHashBuilder`], e.g. a faster one for short keys.
+    pub fn builder_with_hasher(hasher: S) -> LocalCacheBuilder<T, S> {
+        LocalCacheBuilder::new(hasher)
+    }
+    fn shard_index(&self, key: &str) -> usize {
+        if self.shards.len() == 1 {
+            return 0;
+        }
+        self.router.shard(key, self.shards.len()) % self.shards.len()
+    }
+    fn lock(&self, key: &str) -> ShardGuard<'_, InnerLocalCache<T, S>> {
+        self.shards[self.shard_index(key)].lock()
+    }
+    pub fn get(&self, key: &str) -> Option<Arc<T>> {
+        #[cfg(feature = "read-mostly")]
+        if let Some(value) = self.read_snapshot(key) {
+            return Some(value);
+        }
+        #[cfg(feature = "std")]
+        let lookup = match &self.front {
+            Some(front) => self.front_lookup(front, key),
+            None => self.lookup(key),
+        };
+        #[cfg(not(feature = "std"))]
+        let lookup = self.lookup(key);
+        match lookup {
+            Lookup::Hit(value) => Some(value),
+            Lookup::Miss | Lookup::Corrupted => self.read_through(key),
+            Lookup::Negative => None,
+        }
     }
-    pub fn get(&self, key: &String) -> Option<Arc<T>> {
-        let mut local_cache = self.0.lock().unwrap();
-        unsafe { local_cache.get(key) }
+
+    /// Runs `f` on the value cached for `key`, without cloning its `Arc`.
+    /// `f` runs under the shard's lock, so keep it short and don't use the
+    /// cache from it. Misses are not loaded through the
+    /// [`loader`](LocalCacheBuilder::loader).
+    pub fn get_with_ref<R, F: FnOnce(&T) -> R>(&self, key: &str, f: F) -> Option<R> {
+        let mut local_cache = self.lock(key);
+        match local_cache.get(key) {
+            Lookup::Hit(value) => Some(f(&value)),
+            Lookup::Negative | Lookup::Miss | Lookup::Corrupted => None,
+        }
     }
 
-    pub fn put(&self, key: String, value: Arc<T>) {
-        let mut local_cache = self.0.lock().unwrap();
-        unsafe { local_cache.put(key, value) }
+    /// Like [`get`](Self::get), but returns `None` right away instead of
+    /// waiting while another thread holds the key's shard. Such a miss is
+    /// not counted in the stats.
+    pub fn try_get(&self, key: &str) -> Option<Arc<T>> {
+        let mut local_cache = self.shards[self.shard_index(key)].try_lock()?;
+        match local_cache.get(key) {
+            Lookup::Hit(value) => Some(value),
+            Lookup::Negative | Lookup::Miss | Lookup::Corrupted => None,
+        }
+    }
+
+    /// Like [`get`](Self::get), but tells negative entries apart from misses.
+    pub fn lookup(&self, key: &str) -> Lookup<T> {
+        let mut local_cache = self.lock(key);
+        local_cache.get(key)
+    }
+
+    /// Caches `value` with the default TTL. It is taken as a `T`, or as an
+    /// `Arc<T>` to share one already held elsewhere; reads hand out clones
+    /// of the `Arc` either way.
+    ///
+    /// Entries hold their key as a [`Key`], 24 bytes in the entry. Keys of
+    /// up to 22 bytes fit in those and take nothing more; longer ones go in
+    /// an `Arc<str>`, adding a 16-byte header and the key's bytes on the
+    /// heap, 104 bytes all told for a 64-byte URL (a `String` of that length
+    /// takes 88). A long `String` or `&str` key is copied into a new
+    /// `Arc<str>`; an `Arc<str>` is kept as is, so callers that already
+    /// hold their keys that way allocate nothing for the key and share its
+    /// bytes. Lookups take a `&str`, which an `&Arc<str>` derefs to.
+    ///
+    /// Aborts, as `Box::new` does, if memory for the entry can't be
+    /// allocated; [`try_put`](Self::try_put) returns an error instead.
+    pub fn put(&self, key: impl Into<Key>, value: impl Into<Arc<T>>) {
+        self.enqueue(key.into(), Some(value.into()), None, DEFAULT_SOURCE)
+    }
+
+    /// Like [`put`](Self::put), with `ttl` instead of the default TTL.
+    pub fn put_with_ttl(&self, key: impl Into<Key>, value: impl Into<Arc<T>>, ttl: Duration) {
+        self.enqueue(key.into(), Some(value.into()), Some(ttl), DEFAULT_SOURCE)
+    }
+
+    /// Like [`put`](Self::put), labelling the entry with where it came from
+    /// (a loader name, say), as reported by [`source`](Self::source) and in
+    /// dumps. Entries are otherwise labelled `"put"`, `"warmup"`, `"import"`,
+    /// `"loader"`, `"lease"`, `"store"` or `"backing store"` by the path that
+    /// inserted them.
+    pub fn put_with_source(&self, key: impl Into<Key>, value: impl Into<Arc<T>>, source: &'static str) {
+        self.enqueue(key.into(), Some(value.into()), None, source)
+    }
+
+    /// Like [`put`](Self::put), with a priority deciding how early the entry
+    /// is evicted for capacity. Demoted to cold storage, it loses its priority.
+    pub fn put_with_priority(&self, key: impl Into<Key>, value: impl Into<Arc<T>>, priority: Priority) {
+        let (key, value) = (key.into(), value.into());
+        let mut local_cache = self.lock(&key);
+        local_cache.put(key.clone(), Some(value));
+        let segment = match priority {
+            Priority::Low => Segment::Low,
+            Priority::Normal => return,
+            Priority::High => Segment::High,
+        };
+        if let Some(id) = local_cache.map.find(&key) {
+            local_cache.resegment(id, segment);
+        }
+    }
+
+    /// The source label of the live entry for `key`, if any.
+    pub fn source(&self, key: &str) -> Option<&'static str> {
+        let local_cache = self.lock(key);
+        let now = local_cache.now();
+        let entity = local_cache.map.get(key)?;
+        (entity.exp >= now).then_some(entity.source)
+    }
+
+    /// Like [`put`](Self::put), but returns [`CacheError::AllocFailed`]
+    /// instead of aborting when memory for the entry can't be allocated.
+    /// Always applied directly, even with an insert queue.
+    pub fn try_put(&self, key: impl Into<Key>, value: impl Into<Arc<T>>) -> Result<(), CacheError> {
+        let (key, value) = (key.into(), value.into());
+        let mut local_cache = self.lock(&key);
+        local_cache.try_put(key, Some(value))
+    }
+
+    /// Fallible [`put_with_ttl`](Self::put_with_ttl), see [`try_put`](Self::try_put).
+    pub fn try_put_with_ttl(&self, key: impl Into<Key>, value: impl Into<Arc<T>>, ttl: Duration) -> Result<(), CacheError> {
+        let (key, value) = (key.into(), value.into());
+        let mut local_cache = self.lock(&key);
+        local_cache.try_write(key, value, ttl_ns(ttl))
+    }
+
+    /// Removes `key`, returning its value if it was cached and live.
+    pub fn remove(&self, key: &str) -> Option<Arc<T>> {
+        let mut local_cache = self.lock(key);
+        local_cache.take(key)
+    }
+
+    /// Remembers that `key` does not exist upstream, for the negative TTL.
+    pub fn put_negative(&self, key: impl Into<Key>) {
+        self.enqueue(key.into(), None, None, DEFAULT_SOURCE)
+    }
+
+    /// Best-effort dump of keys and expiry timestamps (ns since epoch) to `path`,
+    /// meant for a panic hook: shards are only try-locked (a poisoned lock is
+    /// still read) and a held lock is skipped instead of waited on.
+    /// Returns the number of entries written.
+    ///
+    /// Not for a signal handler: creating the file and formatting the lines
+    /// aren't async-signal-safe.
+    #[cfg(feature = "std")]
+    pub fn dump_unlocked_best_effort<P: AsRef<Path>>(&self, path: P) -> io::Result<usize> {
+        let mut file = File::create(path)?;
+        let mut written = 0;
+        for (i, shard) in self.shards.iter().enumerate() {
+            let Some(local_cache) = shard.try_lock() else {
+                writeln!(file, "# shard {}: locked, skipped", i)?;
+                continue;
+            };
+            writeln!(file, "# shard {}: {} entries", i, local_cache.map.len())?;
+            written += local_cache.dump(&mut file)?;
+        }
+        Ok(written)
     }
 }
 
@@ -202,16 +1723,488 @@ fn test() {
     println!("Hello, world!");
     let local_cache: LocalCache<String> = LocalCache::new(1, 360);
 
-    assert_eq!(None, local_cache.get(&"x".to_string()));
+    assert_eq!(None, local_cache.get("x"));
     local_cache.put(String::from("x"), Arc::new(String::from("abc")));
-    println!("{:?}", local_cache.get(&"x".to_string()));
+    println!("{:?}", local_cache.get("x"));
 
     local_cache.put(String::from("x"), Arc::new(String::from("abc")));
-    println!("{:?}", local_cache.get(&"x".to_string()));
+    println!("{:?}", local_cache.get("x"));
+
+    assert_eq!(None, local_cache.get("y"));
+    local_cache.put(String::from("y"), Arc::new(String::from("123")));
+    println!("{:?}", local_cache.get("y"));
+
+    assert_eq!(None, local_cache.get("x"));
+}
+
+#[test]
+fn test_dump() {
+    let local_cache: LocalCache<String> = LocalCache::new(4, 360);
+    local_cache.put_with_source(String::from("x"), Arc::new(String::from("abc")), "test");
+    local_cache.put(String::from("y"), Arc::new(String::from("123")));
+
+    let path = std::env::temp_dir().join(format!("local-cache-dump-{}", std::process::id()));
+    assert_eq!(2, local_cache.dump_unlocked_best_effort(&path).unwrap());
+    let dump = std::fs::read_to_string(&path).unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert!(dump.starts_with("# shard 0: 2 entries\n\"y\"\t"));
+    assert!(dump.contains("\n\"x\"\t"));
+    assert!(dump.ends_with("\ttest\n"));
+    assert_eq!(Some("test"), local_cache.source("x"));
+    assert_eq!(Some("put"), local_cache.source("y"));
+}
+
+#[test]
+fn test_negative() {
+    let local_cache: LocalCache<String> = LocalCache::builder()
+        .max_entries(4)
+        .ttl(Duration::from_secs(360))
+        .negative_ttl(Duration::from_millis(20))
+        .build();
+    local_cache.put(String::from("x"), Arc::new(String::from("abc")));
+    local_cache.put_negative(String::from("y"));
+    assert_eq!(Lookup::Negative, local_cache.lookup("y"));
+    assert_eq!(None, local_cache.get("y"));
+
+    std::thread::sleep(Duration::from_millis(30));
+    assert_eq!(Lookup::Miss, local_cache.lookup("y"));
+    assert_eq!(Lookup::Hit(Arc::new(String::from("abc"))), local_cache.lookup("x"));
+}
+
+#[test]
+fn test_cold_storage() {
+    use crate::testing::Utf8;
+
+    let local_cache: LocalCache<String> = LocalCache::builder()
+        .max_entries(1)
+        .cold_storage(ColdStorage::new(Utf8, 2).promote_after(2))
+        .build();
+    local_cache.put(String::from("x"), Arc::new(String::from("abc")));
+    local_cache.put(String::from("y"), Arc::new(String::from("123")));
+    assert_eq!(1, local_cache.shards[0].lock().cold_len);
+
+    assert_eq!(Some(Arc::new(String::from("abc"))), local_cache.get("x"));
+    let shard = local_cache.shards[0].lock();
+    assert!(matches!(shard.map[shard.lru_head.unwrap()].value, Slot::Hot(_)));
+    drop(shard);
+    assert_eq!(Some(Arc::new(String::from("abc"))), local_cache.get("x"));
+    let shard = local_cache.shards[0].lock();
+    assert_eq!("x", &shard.map[shard.lru_head.unwrap()].key[..]);
+    drop(shard);
+
+    local_cache.put(String::from("z"), Arc::new(String::from("xyz")));
+    local_cache.put(String::from("w"), Arc::new(String::from("789")));
+    assert_eq!(2, local_cache.shards[0].lock().cold_len);
+    assert_eq!(None, local_cache.get("y"));
+    assert_eq!(Some(Arc::new(String::from("xyz"))), local_cache.get("z"));
+
+    // A value that doesn't encode is evicted instead of going cold.
+    struct Even;
+    impl ValueCodec<u32> for Even {
+        fn encode(&self, value: &u32) -> Option<Vec<u8>> {
+            value.is_multiple_of(2).then(|| value.to_le_bytes().to_vec())
+        }
+        fn decode(&self, bytes: &[u8]) -> Option<u32> {
+            Some(u32::from_le_bytes(bytes.try_into().ok()?))
+        }
+    }
+    let local_cache: LocalCache<u32> = LocalCache::builder().max_entries(1).cold_storage(ColdStorage::new(Even, 2)).build();
+    local_cache.put("a", 1);
+    local_cache.put("b", 2);
+    assert_eq!(0, local_cache.shards[0].lock().cold_len);
+    assert_eq!(None, local_cache.get("a"));
+    local_cache.put("c", 3);
+    assert_eq!(1, local_cache.shards[0].lock().cold_len);
+    assert_eq!(Some(Arc::new(2)), local_cache.get("b"));
+}
+
+#[test]
+fn test_shards() {
+    let local_cache: LocalCache<usize> = LocalCache::builder()
+        .max_entries(64)
+        .shards(4)
+        .ttl(Duration::from_millis(20))
+        .build();
+    for i in 0..64 {
+        local_cache.put(i.to_string(), Arc::new(i));
+    }
+    let len = |local_cache: &LocalCache<usize>| local_cache.shards.iter().map(|s| s.lock().map.len()).sum::<usize>();
+    assert!(len(&local_cache) > 32);
+    assert!(local_cache.shards.iter().all(|s| s.lock().map.len() <= 16));
+
+    std::thread::sleep(Duration::from_millis(30));
+    let report = local_cache.run_maintenance(1);
+    assert_eq!(4, report.expired);
+    assert_eq!(4, report.pending_shards);
+    let report = local_cache.run_maintenance(usize::MAX);
+    assert_eq!(0, report.pending_shards);
+    assert_eq!(0, len(&local_cache));
+}
+
+#[test]
+fn test_backing_store() {
+    use crate::testing::Db;
 
-    assert_eq!(None, local_cache.get(&"y".to_string()));
+    let db = Db::default();
+    let rows = db.0.clone();
+    rows.lock().unwrap().insert("x".to_string(), "abc".to_string());
+    let local_cache: LocalCache<String> = LocalCache::builder().max_entries(1).backing_store(db).build();
+
+    assert_eq!(Some(Arc::new(String::from("abc"))), local_cache.get("x"));
     local_cache.put(String::from("y"), Arc::new(String::from("123")));
-    println!("{:?}", local_cache.get(&"y".to_string()));
+    assert_eq!(Some(&String::from("123")), rows.lock().unwrap().get("y"));
+    // Evicted by capacity, then read through again.
+    assert_eq!(Some(Arc::new(String::from("abc"))), local_cache.get("x"));
+
+    assert_eq!(Some(Arc::new(String::from("abc"))), local_cache.remove("x"));
+    assert!(!rows.lock().unwrap().contains_key("x"));
+    assert_eq!(None, local_cache.get("x"));
+
+    // Warming and importing fill memory only.
+    local_cache.warm([(String::from("w"), String::from("456"))]);
+    local_cache.import([(String::from("v"), Arc::new(String::from("789")), std::time::Duration::from_secs(60))]);
+    assert_eq!(Some(Arc::new(String::from("789"))), local_cache.get("v"));
+    assert!(["w", "v"].iter().all(|key| !rows.lock().unwrap().contains_key(*key)));
+}
+
+#[test]
+fn test_tiny_lfu() {
+    let local_cache: LocalCache<usize> = LocalCache::builder().max_entries(10).admission(Admission::TinyLfu).build();
+    for _ in 0..5 {
+        for i in 0..9 {
+            local_cache.put(i.to_string(), Arc::new(i));
+            local_cache.get(&i.to_string());
+        }
+    }
+    for i in 100..200 {
+        local_cache.put(i.to_string(), Arc::new(i));
+    }
+    assert!((0..9).all(|i| local_cache.get(&i.to_string()).is_some()));
+    assert_eq!(Some(Arc::new(199)), local_cache.get("199"));
+    assert!(local_cache.shards[0].lock().map.len() <= 10);
+}
+
+#[test]
+fn test_checksums() {
+    let local_cache: LocalCache<Vec<u8>> = LocalCache::builder().checksums().build();
+    local_cache.put(String::from("x"), Arc::new(b"abc".to_vec()));
+    local_cache.put(String::from("y"), Arc::new(b"123".to_vec()));
+    assert_eq!(Lookup::Hit(Arc::new(b"abc".to_vec())), local_cache.lookup("x"));
+
+    // Flip a bit of the stored entry behind the cache's back.
+    let mut shard = local_cache.shards[0].lock();
+    let id = shard.map.find("y").unwrap();
+    shard.map[id].checksum ^= 1;
+    drop(shard);
+    assert_eq!(Lookup::Corrupted, local_cache.lookup("y"));
+    assert_eq!(Lookup::Miss, local_cache.lookup("y"));
+}
+
+#[test]
+fn test_lfu() {
+    let local_cache: LocalCache<usize> = LocalCache::builder().max_entries(3).eviction_policy(EvictionPolicy::Lfu).build();
+    for (i, key) in ["a", "b", "c"].into_iter().enumerate() {
+        local_cache.put(key.to_string(), Arc::new(i));
+    }
+    for _ in 0..3 {
+        local_cache.get("a");
+    }
+    local_cache.get("b");
+    local_cache.get("b");
+
+    local_cache.put(String::from("d"), Arc::new(3));
+    assert_eq!(None, local_cache.get("c"));
+    local_cache.put(String::from("e"), Arc::new(4));
+    assert_eq!(None, local_cache.get("d"));
+    assert!(["a", "b", "e"].iter().all(|key| local_cache.get(key.as_ref()).is_some()));
+}
+
+#[test]
+fn test_fifo() {
+    let local_cache: LocalCache<usize> = LocalCache::builder().max_entries(2).eviction_policy(EvictionPolicy::Fifo).build();
+    local_cache.put(String::from("a"), Arc::new(0));
+    local_cache.put(String::from("b"), Arc::new(1));
+    local_cache.get("a");
+    local_cache.put(String::from("c"), Arc::new(2));
+    assert_eq!(None, local_cache.get("a"));
+    assert_eq!(Some(Arc::new(1)), local_cache.get("b"));
+}
+
+#[test]
+fn test_sampled() {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    // Ticks on every read, so no two accesses share a time.
+    let ticks = Arc::new(AtomicU64::new(1));
+    let clock = move || u128::from(ticks.fetch_add(1, Ordering::Relaxed));
+    let local_cache: LocalCache<usize> =
+        LocalCache::builder().max_entries(10).eviction_policy(EvictionPolicy::Sampled { samples: 1000 }).clock(clock).build();
+    for i in 0..10 {
+        local_cache.put(i.to_string(), Arc::new(i));
+    }
+    for i in 0..9 {
+        local_cache.get(&i.to_string());
+    }
+    local_cache.put(String::from("x"), Arc::new(10));
+    assert_eq!(None, local_cache.get("9"));
+    local_cache.remove("3");
+    local_cache.fork().put(String::from("y"), Arc::new(11));
+    let shard = local_cache.shards[0].lock();
+    assert_eq!(9, shard.pool.entries.len());
+    assert!(shard.pool.entries.iter().enumerate().all(|(i, &id)| shard.map[id].pool_index == i));
+}
+
+#[test]
+fn test_slru() {
+    let local_cache: LocalCache<usize> = LocalCache::builder()
+        .max_entries(4)
+        .eviction_policy(EvictionPolicy::Slru { protected_percent: 50 })
+        .build();
+    for (i, key) in ["a", "b", "c", "d"].into_iter().enumerate() {
+        local_cache.put(key.to_string(), Arc::new(i));
+    }
+    local_cache.get("a");
+    local_cache.get("b");
+    // A scan of one-hit wonders only churns the probation segment.
+    for i in 10..20 {
+        local_cache.put(i.to_string(), Arc::new(i));
+    }
+    assert_eq!(Some(Arc::new(0)), local_cache.get("a"));
+    assert_eq!(Some(Arc::new(1)), local_cache.get("b"));
+    assert_eq!(None, local_cache.get("c"));
+
+    // The protected segment holds two entries; "a" goes back to probation.
+    local_cache.get("19");
+    local_cache.get("b");
+    local_cache.put(String::from("x"), Arc::new(30));
+    local_cache.put(String::from("y"), Arc::new(31));
+    assert_eq!(None, local_cache.get("a"));
+    assert_eq!(Some(Arc::new(19)), local_cache.get("19"));
+}
+
+#[test]
+fn test_try_put() {
+    use crate::testing::Db;
+
+    let local_cache: LocalCache<String> = LocalCache::new(1, 360);
+    assert_eq!(Ok(()), local_cache.try_put(String::from("x"), Arc::new(String::from("abc"))));
+    assert_eq!(Ok(()), local_cache.try_put_with_ttl(String::from("y"), Arc::new(String::from("123")), Duration::from_secs(1)));
+    assert_eq!(None, local_cache.get("x"));
+    assert_eq!(Some(Arc::new(String::from("123"))), local_cache.get("y"));
+    assert_eq!("cache entry allocation failed", CacheError::AllocFailed.to_string());
+
+    // A failed insert drops the old entry but writes nothing through.
+    let db = Db::default();
+    let local_cache: LocalCache<String> = LocalCache::builder().max_entries(4).backing_store(db.clone()).build();
+    local_cache.put("x", String::from("abc"));
+    slab::FAIL_INSERTS.set(true);
+    assert_eq!(Err(CacheError::AllocFailed), local_cache.try_put("x", String::from("def")));
+    assert_eq!(Err(CacheError::AllocFailed), local_cache.try_put_with_ttl("x", String::from("ghi"), Duration::from_secs(1)));
+    slab::FAIL_INSERTS.set(false);
+    assert_eq!(0, local_cache.stats().entries);
+    assert_eq!(1, db.1.load(Ordering::Relaxed));
+    assert_eq!(Some(Arc::new(String::from("abc"))), local_cache.get("x"));
+}
+
+#[test]
+fn test_time_to_idle() {
+    let local_cache: LocalCache<usize> = LocalCache::builder()
+        .ttl(Duration::from_secs(360))
+        .time_to_idle(Duration::from_millis(40))
+        .build();
+    local_cache.put(String::from("x"), Arc::new(0));
+    local_cache.put(String::from("y"), Arc::new(1));
+    for _ in 0..3 {
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(Some(Arc::new(0)), local_cache.get("x"));
+    }
+    assert_eq!(None, local_cache.get("y"));
+}
+
+#[test]
+fn test_plain_values() {
+    let local_cache: LocalCache<String> = LocalCache::new(4, 360);
+    local_cache.put(String::from("a"), String::from("x"));
+    let shared = Arc::new(String::from("y"));
+    local_cache.put_with_ttl(String::from("b"), shared.clone(), Duration::from_secs(5));
+    assert_eq!(Some(Arc::new(String::from("x"))), local_cache.get("a"));
+    assert!(Arc::ptr_eq(&shared, &local_cache.get("b").unwrap()));
+    assert_eq!(Arc::new(String::from("z")), local_cache.entry(String::from("c")).or_insert(String::from("z")));
+}
+
+#[test]
+fn test_new_with() {
+    let local_cache: LocalCache<usize> = LocalCache::new_with(4, Duration::from_millis(250));
+    local_cache.put(String::from("x"), Arc::new(1));
+    let ttl = local_cache.export()[0].2;
+    assert!(ttl <= Duration::from_millis(250) && ttl > Duration::from_millis(200));
+    std::thread::sleep(Duration::from_millis(260));
+    assert_eq!(None, local_cache.get("x"));
+}
+
+#[test]
+fn test_no_ttl() {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    let secs = Arc::new(AtomicU64::new(1_000));
+    let now = secs.clone();
+    let local_cache: LocalCache<usize> = LocalCache::builder()
+        .max_entries(2)
+        .ttl(None)
+        .clock(move || Duration::from_secs(now.load(Ordering::Relaxed)).as_nanos())
+        .build();
+    local_cache.put(String::from("a"), Arc::new(1));
+    local_cache.put_with_ttl(String::from("b"), Arc::new(2), Duration::from_secs(10));
+    match local_cache.entry(String::from("a")) {
+        Entry::Occupied(entry) => assert_eq!(Duration::MAX, entry.ttl()),
+        Entry::Vacant(_) => unreachable!(),
+    }
+    secs.store(1_000 + 100 * 365 * 24 * 3600, Ordering::Relaxed);
+    assert_eq!(1, local_cache.evict_expired());
+    assert_eq!(Some(Arc::new(1)), local_cache.get("a"));
+    local_cache.put(String::from("c"), Arc::new(3));
+    local_cache.put(String::from("d"), Arc::new(4));
+    assert_eq!(None, local_cache.get("a"));
+
+    let local_cache: LocalCache<usize> = LocalCache::new(4, u64::MAX);
+    local_cache.put_with_ttl(String::from("x"), Arc::new(1), Duration::MAX);
+    local_cache.put(String::from("y"), Arc::new(2));
+    assert!(local_cache.export().iter().all(|(_, _, ttl)| *ttl == Duration::MAX));
+}
+
+#[test]
+fn test_sliding_expiration() {
+    let local_cache: LocalCache<usize> = LocalCache::builder()
+        .ttl(Duration::from_millis(40))
+        .sliding_expiration()
+        .build();
+    local_cache.put(String::from("x"), Arc::new(0));
+    local_cache.put_with_ttl(String::from("y"), Arc::new(1), Duration::from_secs(360));
+    for _ in 0..3 {
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(Some(Arc::new(0)), local_cache.get("x"));
+    }
+    std::thread::sleep(Duration::from_millis(50));
+    assert_eq!(None, local_cache.get("x"));
+    assert_eq!(Some(Arc::new(1)), local_cache.get("y"));
+}
+
+#[test]
+fn test_priority() {
+    let local_cache: LocalCache<usize> = LocalCache::new(3, 360);
+    local_cache.put_with_priority(String::from("high"), Arc::new(0), Priority::High);
+    local_cache.put_with_priority(String::from("low"), Arc::new(1), Priority::Low);
+    local_cache.put(String::from("a"), Arc::new(2));
+    local_cache.get("low");
+
+    local_cache.put(String::from("b"), Arc::new(3));
+    assert_eq!(None, local_cache.get("low"));
+    local_cache.put(String::from("c"), Arc::new(4));
+    local_cache.put(String::from("d"), Arc::new(5));
+    assert_eq!(Some(Arc::new(0)), local_cache.get("high"));
+    assert_eq!(None, local_cache.get("b"));
+}
+
+#[test]
+fn test_hasher() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::BuildHasherDefault;
+
+    let local_cache = LocalCache::builder_with_hasher(BuildHasherDefault::<DefaultHasher>::default())
+        .shards(4)
+        .build();
+    for i in 0..10 {
+        local_cache.put(i.to_string(), Arc::new(i));
+    }
+    assert_eq!(Some(Arc::new(5)), local_cache.get("5"));
+    local_cache.namespace("n").put("x", Arc::new(10));
+    assert_eq!(Some(Arc::new(10)), local_cache.get("n:x"));
+    assert_eq!(11, local_cache.stats().entries);
+}
+
+#[test]
+fn test_drop() {
+    let value = Arc::new(0);
+    let local_cache: LocalCache<usize> = LocalCache::builder().shards(2).build();
+    local_cache.put(String::from("a"), value.clone());
+    local_cache.put(String::from("b"), value.clone());
+    // A dropped fork releases its copies too.
+    local_cache.fork();
+    assert_eq!(3, Arc::strong_count(&value));
+    drop(local_cache);
+    assert_eq!(1, Arc::strong_count(&value));
+}
+
+#[test]
+fn test_try_get() {
+    let local_cache: LocalCache<usize> = LocalCache::new(4, 360);
+    local_cache.put(String::from("a"), Arc::new(1));
+    assert_eq!(Some(Arc::new(1)), local_cache.try_get("a"));
+    let shard = local_cache.shards[0].lock();
+    assert_eq!(None, local_cache.try_get("a"));
+    drop(shard);
+    assert_eq!((1, 0), (local_cache.stats().hits, local_cache.stats().misses));
+}
+
+#[test]
+fn test_get_with_ref() {
+    let local_cache: LocalCache<(String, usize)> = LocalCache::new(4, 360);
+    local_cache.put(String::from("a"), (String::from("alice"), 30));
+    assert_eq!(Some(30), local_cache.get_with_ref("a", |user| user.1));
+    assert_eq!(None, local_cache.get_with_ref("b", |user| user.1));
+    assert_eq!(1, local_cache.stats().hits);
+}
+
+#[test]
+fn test_poisoned_shard() {
+    use std::panic::{self, AssertUnwindSafe};
+
+    // Panics under the shard lock while sizing the value.
+    let local_cache: LocalCache<String> =
+        LocalCache::builder().max_bytes(1 << 20).value_size(|value| if value == "boom" { panic!() } else { 0 }).build();
+    local_cache.put(String::from("a"), Arc::new(String::from("x")));
+    let panicked = panic::catch_unwind(AssertUnwindSafe(|| local_cache.put(String::from("b"), Arc::new(String::from("boom")))));
+    assert!(panicked.is_err());
+    assert!(local_cache.shards[0].is_poisoned());
+
+    assert_eq!(Some(Arc::new(String::from("x"))), local_cache.get("a"));
+    assert_eq!(Some(Arc::new(String::from("x"))), local_cache.try_get("a"));
+    local_cache.put(String::from("c"), Arc::new(String::from("y")));
+    local_cache.remove("b");
+    assert_eq!(2, local_cache.stats().entries);
+    std::thread::scope(|scope| scope.spawn(|| local_cache.get("c")).join().unwrap());
+}
+
+#[test]
+fn test_arc_str_keys() {
+    let local_cache: LocalCache<usize> = LocalCache::new(16, 360);
+    let key: Arc<str> = Arc::from("https://example.com/a/rather/long/path");
+    local_cache.put(key.clone(), 1);
+    // The entry holds the caller's key rather than a copy.
+    assert_eq!(2, Arc::strong_count(&key));
+    assert_eq!(Some(Arc::new(1)), local_cache.get(&key));
+    local_cache.put("short", 2);
+    assert_eq!(Some(Arc::new(2)), local_cache.remove("short"));
+    assert_eq!(Some(Arc::new(1)), local_cache.remove(&key));
+    assert_eq!(1, Arc::strong_count(&key));
+}
+
+#[test]
+fn test_shard_capacities() {
+    let capacities = |local_cache: &LocalCache<usize>| local_cache.shards.iter().map(|shard| shard.lock().max_numbers).collect::<Vec<_>>();
+    let local_cache: LocalCache<usize> = LocalCache::builder().max_entries(10).shards(8).build();
+    assert_eq!(vec![2, 2, 1, 1, 1, 1, 1, 1], capacities(&local_cache));
+    for i in 0..100 {
+        local_cache.put(i.to_string(), i);
+    }
+    assert!(local_cache.stats().entries <= 10);
+    local_cache.set_max_entries(5);
+    assert_eq!(5, capacities(&local_cache).iter().sum::<usize>());
+
+    let weighted: LocalCache<usize> = LocalCache::builder().max_entries(10).shard_weights(&[1, 1, 1]).build();
+    assert_eq!(vec![3, 3, 4], capacities(&weighted));
 
-    assert_eq!(None, local_cache.get(&"x".to_string()));
+    let empty: LocalCache<usize> = LocalCache::builder().max_entries(0).build();
+    empty.put("a", 1);
+    assert_eq!((0, None), (empty.stats().entries, empty.get("a")));
 }
@@ -0,0 +1,144 @@
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use core::future::Future;
+use core::hash::BuildHasher;
+use core::pin::Pin;
+
+use crate::{LocalCache, LocalCacheBuilder, Lookup};
+
+/// Where misses are loaded from, see [`LocalCacheBuilder::loader`].
+///
+/// Called without holding any lock, so concurrent misses on a key may each
+/// call it; see [`acquire_lease`](LocalCache::acquire_lease) to prevent that.
+pub trait CacheLoader<K, T>: Send + Sync {
+    /// The value for `key`, or `None` if there is none.
+    fn load(&self, key: &K) -> Option<T>;
+}
+
+/// Async flavor of [`CacheLoader`], see [`LocalCacheBuilder::async_loader`].
+pub trait AsyncCacheLoader<K, T>: Send + Sync {
+    fn load<'a>(&'a self, key: &'a K) -> Pin<Box<dyn Future<Output = Option<T>> + Send + 'a>>;
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    // Loads a miss on `key` through the loader, if there is one.
+    pub(crate) fn read_through(&self, key: &str) -> Option<Arc<T>> {
+        let loader = self.loader.as_ref()?;
+        self.load_with(key.into(), || loader.load(&key.to_string()).ok_or(())).ok()
+    }
+
+    /// Like [`get`](Self::get), loading misses through the
+    /// [`async_loader`](LocalCacheBuilder::async_loader), or the sync
+    /// [`loader`](LocalCacheBuilder::loader) if there is no async one.
+    /// Spilled entries are read with [`Store::load_async`], without holding
+    /// the shard lock.
+    pub async fn get_async(&self, key: &str) -> Option<Arc<T>>
+    where
+        T: Send,
+    {
+        #[cfg(feature = "std")]
+        let lookup = self.lookup_async(key).await;
+        #[cfg(not(feature = "std"))]
+        let lookup = self.lookup(key);
+        match lookup {
+            Lookup::Hit(value) => return Some(value),
+            Lookup::Negative => return None,
+            Lookup::Miss | Lookup::Corrupted => {}
+        }
+        let Some(loader) = &self.async_loader else {
+            return self.read_through(key);
+        };
+        let owned = key.to_string();
+        let load = loader.load(&owned);
+        #[cfg(feature = "tracing")]
+        let load = tracing::Instrument::instrument(load, tracing::info_span!("loader", key = self.traced_key(key)));
+        let loaded = load.await;
+        self.cache_loaded(key.into(), loaded.ok_or(())).ok()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Send, S: BuildHasher> LocalCache<T, S> {
+    // Like `lookup`, awaiting the spill store instead of reading it under
+    // the shard lock.
+    async fn lookup_async(&self, key: &str) -> Lookup<T> {
+        let store = {
+            let mut local_cache = self.lock(key);
+            let resident = local_cache.find_resident(key);
+            match (resident, local_cache.store.clone()) {
+                (None, Some(store)) => store,
+                (resident, _) => {
+                    let lookup = resident.unwrap_or_else(|| local_cache.load_missing(key));
+                    local_cache.record_get(key, &lookup);
+                    return lookup;
+                }
+            }
+        };
+        let spilled = store.load_async(key).await;
+        let mut local_cache = self.lock(key);
+        // The key may have been put in the meantime, which beats the store.
+        let lookup = local_cache.find_resident(key).unwrap_or_else(|| local_cache.load_spilled(key, spilled));
+        local_cache.record_get(key, &lookup);
+        lookup
+    }
+}
+
+impl<T, S> LocalCacheBuilder<T, S> {
+    /// Makes [`get`](LocalCache::get) read-through: misses are loaded from
+    /// `loader` and cached as if by
+    /// [`get_or_try_insert_with`](LocalCache::get_or_try_insert_with), a
+    /// `None` being the error. Negative entries and
+    /// [`try_get`](LocalCache::try_get) don't call it.
+    pub fn loader<L: CacheLoader<String, T> + 'static>(mut self, loader: L) -> Self {
+        self.loader = Some(Arc::new(loader));
+        self
+    }
+
+    /// Like [`loader`](Self::loader) for [`get_async`](LocalCache::get_async)
+    /// only; plain `get` keeps using the sync loader, if any.
+    pub fn async_loader<L: AsyncCacheLoader<String, T> + 'static>(mut self, loader: L) -> Self {
+        self.async_loader = Some(Arc::new(loader));
+        self
+    }
+}
+
+#[test]
+fn test_loader() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{Context, Poll, Waker};
+    use std::time::Duration;
+
+    struct Parse(Arc<AtomicUsize>);
+    impl CacheLoader<String, usize> for Parse {
+        fn load(&self, key: &String) -> Option<usize> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            key.parse().ok()
+        }
+    }
+    struct Len;
+    impl AsyncCacheLoader<String, usize> for Len {
+        fn load<'a>(&'a self, key: &'a String) -> Pin<Box<dyn Future<Output = Option<usize>> + Send + 'a>> {
+            Box::pin(async move { Some(key.len()) })
+        }
+    }
+
+    let loads = Arc::new(AtomicUsize::new(0));
+    let local_cache: LocalCache<usize> = LocalCache::builder().loader(Parse(loads.clone())).negative_ttl(Duration::from_secs(60)).build();
+    assert_eq!(Some(Arc::new(42)), local_cache.get("42"));
+    assert_eq!(Some(Arc::new(42)), local_cache.get("42"));
+    assert_eq!(None, local_cache.get("x"));
+    assert_eq!(None, local_cache.get("x"));
+    assert_eq!(Lookup::Negative, local_cache.lookup("x"));
+    assert_eq!(None, local_cache.try_get("7"));
+    assert_eq!(Arc::new(7), local_cache.get_or_insert_with(String::from("7"), || 0));
+    assert_eq!(3, loads.load(Ordering::Relaxed));
+
+    let local_cache: LocalCache<usize> = LocalCache::builder().async_loader(Len).build();
+    let mut cx = Context::from_waker(Waker::noop());
+    let key = String::from("abc");
+    let mut future = std::pin::pin!(local_cache.get_async(&key));
+    assert_eq!(Poll::Ready(Some(Arc::new(3))), future.as_mut().poll(&mut cx));
+    assert_eq!(Some(Arc::new(3)), local_cache.get(&key));
+    assert_eq!(None, local_cache.get("abcd"));
+}
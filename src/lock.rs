@@ -0,0 +1,212 @@
+use alloc::boxed::Box;
+use core::cell::UnsafeCell;
+use core::hint;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use crate::LocalCacheBuilder;
+
+/// A lock for the shards, see [`LocalCacheBuilder::shard_lock`]; for
+/// example a newtype around a `spin::Mutex<()>` whose guards are forgotten
+/// in `lock` and released with `force_unlock` in `unlock`.
+///
+/// # Safety
+///
+/// Once `lock` returns, or `try_lock` returns true, no other call may take
+/// the lock until `unlock` is called.
+pub unsafe trait RawLock: Send + Sync {
+    /// Waits until the lock is free and takes it.
+    fn lock(&self);
+    /// Takes the lock if it is free, without waiting.
+    fn try_lock(&self) -> bool;
+    /// Releases the lock.
+    ///
+    /// # Safety
+    ///
+    /// Only called while the lock is held, by whoever holds it.
+    unsafe fn unlock(&self);
+}
+
+/// A [`RawLock`] that spins until the lock is free; what shards are locked
+/// with when the `std` feature is off.
+#[derive(Debug, Default)]
+pub struct SpinLock(AtomicBool);
+
+unsafe impl RawLock for SpinLock {
+    fn lock(&self) {
+        while !self.try_lock() {
+            while self.0.load(Ordering::Relaxed) {
+                hint::spin_loop();
+            }
+        }
+    }
+
+    fn try_lock(&self) -> bool {
+        self.0.compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed).is_ok()
+    }
+
+    unsafe fn unlock(&self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+// Makes the lock of a new shard, see `LocalCacheBuilder::shard_lock`.
+pub(crate) type NewLock = fn() -> Box<dyn RawLock>;
+
+enum Raw {
+    #[cfg(feature = "std")]
+    Std(std::sync::Mutex<()>),
+    User(Box<dyn RawLock>),
+}
+
+// A shard behind a `std::sync::Mutex`, or the `RawLock` set with
+// `LocalCacheBuilder::shard_lock`.
+pub(crate) struct ShardLock<X> {
+    raw: Raw,
+    // Kept to lock the shards of a fork the same way.
+    new_lock: Option<NewLock>,
+    value: UnsafeCell<X>,
+}
+
+// Safe as `Mutex<X>` is: the value is only reached through a guard, and
+// only one guard exists at a time.
+unsafe impl<X: Send> Send for ShardLock<X> {}
+unsafe impl<X: Send> Sync for ShardLock<X> {}
+
+impl<X> ShardLock<X> {
+    pub(crate) fn new(value: X, new_lock: Option<NewLock>) -> Self {
+        let raw = match new_lock {
+            Some(new_lock) => Raw::User(new_lock()),
+            #[cfg(feature = "std")]
+            None => Raw::Std(std::sync::Mutex::new(())),
+            #[cfg(not(feature = "std"))]
+            None => Raw::User(Box::new(SpinLock::default())),
+        };
+        Self {
+            raw,
+            new_lock,
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    // A lock of the same kind around `value`.
+    pub(crate) fn sibling(&self, value: X) -> Self {
+        Self::new(value, self.new_lock)
+    }
+
+    // Ignores poisoning, see `crate::lock`.
+    pub(crate) fn lock(&self) -> ShardGuard<'_, X> {
+        match &self.raw {
+            #[cfg(feature = "std")]
+            Raw::Std(mutex) => self.guard(Some(crate::lock(mutex))),
+            Raw::User(raw) => {
+                raw.lock();
+                self.guard(None)
+            }
+        }
+    }
+
+    // `None` if the lock is held, poisoned or not.
+    pub(crate) fn try_lock(&self) -> Option<ShardGuard<'_, X>> {
+        match &self.raw {
+            #[cfg(feature = "std")]
+            Raw::Std(mutex) => match mutex.try_lock() {
+                Ok(guard) => Some(self.guard(Some(guard))),
+                Err(std::sync::TryLockError::Poisoned(poisoned)) => Some(self.guard(Some(poisoned.into_inner()))),
+                Err(std::sync::TryLockError::WouldBlock) => None,
+            },
+            Raw::User(raw) => raw.try_lock().then(|| self.guard(None)),
+        }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn is_poisoned(&self) -> bool {
+        match &self.raw {
+            #[cfg(feature = "std")]
+            Raw::Std(mutex) => mutex.is_poisoned(),
+            Raw::User(_) => false,
+        }
+    }
+
+    fn guard<'a>(&'a self, std: Option<StdGuard<'a>>) -> ShardGuard<'a, X> {
+        ShardGuard { shard: self, _std: std, _not_send: PhantomData }
+    }
+}
+
+#[cfg(feature = "std")]
+type StdGuard<'a> = std::sync::MutexGuard<'a, ()>;
+// Never made without `std`.
+#[cfg(not(feature = "std"))]
+type StdGuard<'a> = PhantomData<&'a ()>;
+
+pub(crate) struct ShardGuard<'a, X> {
+    shard: &'a ShardLock<X>,
+    // Holds a std lock; `None` for a `RawLock`, released on drop.
+    _std: Option<StdGuard<'a>>,
+    // Released on the thread that took it, like a `MutexGuard`.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<X> Deref for ShardGuard<'_, X> {
+    type Target = X;
+
+    fn deref(&self) -> &X {
+        // SAFETY: the guard holds the lock.
+        unsafe { &*self.shard.value.get() }
+    }
+}
+
+impl<X> DerefMut for ShardGuard<'_, X> {
+    fn deref_mut(&mut self) -> &mut X {
+        // SAFETY: the guard holds the lock.
+        unsafe { &mut *self.shard.value.get() }
+    }
+}
+
+impl<X> Drop for ShardGuard<'_, X> {
+    fn drop(&mut self) {
+        match &self.shard.raw {
+            #[cfg(feature = "std")]
+            Raw::Std(_) => {}
+            // SAFETY: the guard holds the lock.
+            Raw::User(raw) => unsafe { raw.unlock() },
+        }
+    }
+}
+
+impl<T, S> LocalCacheBuilder<T, S> {
+    /// Locks each shard with an `L` instead of a `std::sync::Mutex`, or a
+    /// [`SpinLock`] without the `std` feature. Forks lock theirs the same
+    /// way.
+    pub fn shard_lock<L: RawLock + Default + 'static>(mut self) -> Self {
+        self.shard_lock = Some(|| Box::new(L::default()));
+        self
+    }
+}
+
+#[test]
+fn test_shard_lock() {
+    use std::sync::Arc;
+    use std::thread;
+
+    use crate::LocalCache;
+
+    let local_cache: LocalCache<usize> = LocalCache::builder().max_entries(1000).shards(4).shard_lock::<SpinLock>().build();
+    thread::scope(|scope| {
+        for t in 0..4 {
+            let local_cache = &local_cache;
+            scope.spawn(move || {
+                for i in 0..100 {
+                    local_cache.put((t * 100 + i).to_string(), Arc::new(i));
+                }
+            });
+        }
+    });
+    assert_eq!(400, local_cache.stats().entries);
+    let shard = local_cache.shards[0].lock();
+    assert!(local_cache.shards[0].try_lock().is_none());
+    drop(shard);
+    assert!(local_cache.shards[0].try_lock().is_some());
+    assert_eq!(Some(Arc::new(7)), local_cache.fork().get("107"));
+}
@@ -0,0 +1,136 @@
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::{LocalCache, LocalCacheBuilder};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    /// Expired entries removed.
+    pub expired: usize,
+    /// Shards that ran out of budget with expired entries left.
+    pub pending_shards: usize,
+}
+
+impl<T: Send + Sync, S: BuildHasher + Send> LocalCache<T, S> {
+    /// Sweeps expired entries from every shard, removing at most
+    /// `per_shard_budget` entries per shard so a cycle finishes in bounded
+    /// time. Shards are swept in parallel on scoped threads, one shard lock
+    /// held per thread at a time. Call again while `pending_shards > 0` to
+    /// finish the backlog. With the `metrics` feature, each run also
+    /// publishes metrics, see `publish_metrics`.
+    pub fn run_maintenance(&self, per_shard_budget: usize) -> MaintenanceReport {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get()).min(self.shards.len());
+        let report = if threads <= 1 {
+            self.maintain_shards(&AtomicUsize::new(0), per_shard_budget)
+        } else {
+            self.maintain_in_parallel(threads, per_shard_budget)
+        };
+        #[cfg(feature = "metrics")]
+        self.publish_metrics();
+        report
+    }
+
+    fn maintain_in_parallel(&self, threads: usize, per_shard_budget: usize) -> MaintenanceReport {
+        let next_shard = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..threads)
+                .map(|_| scope.spawn(|| self.maintain_shards(&next_shard, per_shard_budget)))
+                .collect();
+            workers.into_iter().fold(MaintenanceReport::default(), |total, worker| {
+                let report = worker.join().unwrap();
+                MaintenanceReport {
+                    expired: total.expired + report.expired,
+                    pending_shards: total.pending_shards + report.pending_shards,
+                }
+            })
+        })
+    }
+
+    // Claims shards off the shared counter until none are left.
+    fn maintain_shards(&self, next_shard: &AtomicUsize, budget: usize) -> MaintenanceReport {
+        let mut report = MaintenanceReport::default();
+        loop {
+            let i = next_shard.fetch_add(1, Ordering::Relaxed);
+            let Some(shard) = self.shards.get(i) else {
+                return report;
+            };
+            let mut shard = shard.lock();
+            let local_cache = &mut *shard;
+            let now = local_cache.now();
+            let expired = local_cache.evict_expired(now, budget);
+            report.expired += expired;
+            if expired == budget && local_cache.wheel.peek_expired(&mut local_cache.map, now).is_some() {
+                report.pending_shards += 1;
+            }
+        }
+    }
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// Removes every expired entry now, one shard at a time, and returns how
+    /// many were removed. See [`run_maintenance`](Self::run_maintenance) for
+    /// a bounded, parallel sweep.
+    pub fn evict_expired(&self) -> usize {
+        let mut removed = 0;
+        for shard in self.shards.iter() {
+            let mut local_cache = shard.lock();
+            let now = local_cache.now();
+            removed += local_cache.evict_expired(now, usize::MAX);
+        }
+        removed
+    }
+}
+
+impl<T, S> LocalCacheBuilder<T, S> {
+    /// Has every lookup also remove up to `budget` expired entries from its
+    /// shard, soonest expiry first, so that a read-only workload reclaims
+    /// them without [`run_maintenance`](LocalCache::run_maintenance). An
+    /// expired entry a lookup comes across is removed either way.
+    pub fn sweep_on_read(mut self, budget: usize) -> Self {
+        self.sweep_on_read = budget;
+        self
+    }
+}
+
+#[test]
+fn test_evict_expired() {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let local_cache: LocalCache<usize> = LocalCache::builder().shards(2).build();
+    for i in 0..5 {
+        local_cache.put_with_ttl(i.to_string(), Arc::new(i), Duration::from_millis(10));
+    }
+    local_cache.put(String::from("x"), Arc::new(5));
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!(5, local_cache.evict_expired());
+    assert_eq!(0, local_cache.evict_expired());
+    assert_eq!(1, local_cache.stats().entries);
+}
+
+#[test]
+fn test_sweep_on_read() {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let local_cache: LocalCache<usize> = LocalCache::new(10, 360);
+    local_cache.put_with_ttl(String::from("a"), Arc::new(1), Duration::from_millis(10));
+    local_cache.put_with_ttl(String::from("b"), Arc::new(2), Duration::from_millis(10));
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!(None, local_cache.get("a"));
+    let stats = local_cache.stats();
+    assert_eq!((1, 1), (stats.entries, stats.expirations));
+
+    let local_cache: LocalCache<usize> = LocalCache::builder().sweep_on_read(2).build();
+    for i in 0..5 {
+        local_cache.put_with_ttl(i.to_string(), Arc::new(i), Duration::from_millis(10));
+    }
+    local_cache.put(String::from("x"), Arc::new(5));
+    thread::sleep(Duration::from_millis(20));
+    assert_eq!(Some(Arc::new(5)), local_cache.get("x"));
+    assert_eq!(4, local_cache.stats().entries);
+    local_cache.get("x");
+    local_cache.get("x");
+    assert_eq!(1, local_cache.stats().entries);
+}
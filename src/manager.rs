@@ -0,0 +1,162 @@
+use std::hash::BuildHasher;
+use std::sync::{Arc, Mutex, Weak};
+
+use crate::{lock, InnerLocalCache, LocalCache};
+
+/// A cache whose memory a [`CacheManager`] can keep in check. Implemented by
+/// every [`LocalCache`], whatever its value type.
+pub trait ManagedCache: Send + Sync {
+    /// See [`LocalCache::memory_usage`].
+    fn memory_usage(&self) -> usize;
+    /// Evicts entries as for capacity until about `bytes` are freed or none
+    /// are left to evict. Returns the bytes freed.
+    fn shrink_by(&self, bytes: usize) -> usize;
+}
+
+impl<T, S: BuildHasher> InnerLocalCache<T, S> {
+    fn shrink_by(&mut self, bytes: usize) -> usize {
+        let mut freed = 0;
+        while freed < bytes {
+            let Some(victim) = self.victim().or(self.cold_tail) else {
+                break;
+            };
+            freed += self.entry_size(&self.map[victim]);
+            self.evict(victim);
+        }
+        // Evicted nodes leave their slots behind for reuse otherwise.
+        self.shrink_to_fit();
+        freed
+    }
+}
+
+impl<T: Send + Sync, S: BuildHasher + Send> ManagedCache for LocalCache<T, S> {
+    fn memory_usage(&self) -> usize {
+        LocalCache::memory_usage(self)
+    }
+
+    // Spread over the shards, going round again while some of them still
+    // have entries and the others ran out.
+    fn shrink_by(&self, bytes: usize) -> usize {
+        let mut freed = 0;
+        loop {
+            let before = freed;
+            for shard in self.shards.iter() {
+                if freed >= bytes {
+                    return freed;
+                }
+                let share = (bytes - freed).div_ceil(self.shards.len());
+                freed += shard.lock().shrink_by(share);
+            }
+            if freed == before {
+                return freed;
+            }
+        }
+    }
+}
+
+struct Registered {
+    name: String,
+    cache: Weak<dyn ManagedCache>,
+    weight: usize,
+}
+
+/// One memory budget shared by many caches, e.g. one per subsystem.
+///
+/// Each cache is entitled to a share of the budget in proportion to its
+/// weight. [`enforce`](Self::enforce) checks the total and, if it is over,
+/// has the caches over their share evict the excess between them. Call
+/// it periodically, like [`LocalCache::run_maintenance`].
+pub struct CacheManager {
+    max_bytes: usize,
+    caches: Mutex<Vec<Registered>>,
+}
+
+impl CacheManager {
+    pub fn new(max_bytes: usize) -> Self {
+        Self { max_bytes, caches: Mutex::new(Vec::new()) }
+    }
+
+    /// Adds `cache` under `name` with a `weight` of at least 1. Only a weak
+    /// reference is kept: a cache dropped elsewhere is unregistered.
+    pub fn register<C: ManagedCache + 'static>(&self, name: impl Into<String>, cache: &Arc<C>, weight: usize) {
+        let cache: Arc<dyn ManagedCache> = cache.clone();
+        lock(&self.caches).push(Registered { name: name.into(), cache: Arc::downgrade(&cache), weight: weight.max(1) });
+    }
+
+    /// Removes the cache registered under `name`. Returns false if none was.
+    pub fn unregister(&self, name: &str) -> bool {
+        let mut caches = lock(&self.caches);
+        let len = caches.len();
+        caches.retain(|registered| registered.name != name);
+        caches.len() < len
+    }
+
+    /// Bytes used by each registered cache, as
+    /// [`memory_usage`](LocalCache::memory_usage) counts them.
+    pub fn memory_usage(&self) -> Vec<(String, usize)> {
+        self.live().into_iter().map(|(name, cache, _)| (name, cache.memory_usage())).collect()
+    }
+
+    /// Brings the caches back under the budget if they are over it, taking
+    /// from each in proportion to how far it exceeds its share. Returns the
+    /// bytes freed.
+    pub fn enforce(&self) -> usize {
+        let caches = self.live();
+        let total_weight: usize = caches.iter().map(|(_, _, weight)| weight).sum();
+        let usage: Vec<_> = caches.iter().map(|(_, cache, _)| cache.memory_usage()).collect();
+        let total: usize = usage.iter().sum();
+        if total <= self.max_bytes {
+            return 0;
+        }
+        let excess = total - self.max_bytes;
+        let over: Vec<_> = caches
+            .iter()
+            .zip(&usage)
+            .map(|((_, _, weight), &used)| used.saturating_sub((self.max_bytes as u128 * *weight as u128 / total_weight as u128) as usize))
+            .collect();
+        let total_over: usize = over.iter().sum();
+        let mut freed = 0;
+        for ((_, cache, _), over) in caches.iter().zip(over) {
+            if over > 0 {
+                let share = (excess as u128 * over as u128).div_ceil(total_over as u128) as usize;
+                freed += cache.shrink_by(share);
+            }
+        }
+        freed
+    }
+
+    // The caches still alive, forgetting the dropped ones.
+    fn live(&self) -> Vec<(String, Arc<dyn ManagedCache>, usize)> {
+        let mut caches = lock(&self.caches);
+        caches.retain(|registered| registered.cache.strong_count() > 0);
+        caches.iter().filter_map(|registered| Some((registered.name.clone(), registered.cache.upgrade()?, registered.weight))).collect()
+    }
+}
+
+#[test]
+fn test_cache_manager() {
+    use std::mem;
+
+    let sessions: Arc<LocalCache<String>> = Arc::new(LocalCache::builder().value_size(String::capacity).shards(2).build());
+    let pages: Arc<LocalCache<Vec<u8>>> = Arc::new(LocalCache::builder().value_size(Vec::capacity).build());
+    for i in 0..100 {
+        sessions.put(i.to_string(), "s".repeat(1000));
+        pages.put(i.to_string(), vec![0; 1000]);
+    }
+    let empty: LocalCache<String> = LocalCache::new(16, 360);
+    let slack = 2 * empty.memory_usage() + 100 * mem::size_of::<usize>();
+    let manager = CacheManager::new(100_000);
+    manager.register("sessions", &sessions, 3);
+    manager.register("pages", &pages, 1);
+    assert!(manager.enforce() > 100_000);
+    assert!(sessions.memory_usage() + pages.memory_usage() <= 100_000 + slack);
+    // Pages, with a quarter of the weight, gave up the most.
+    assert!(pages.memory_usage() * 2 < sessions.memory_usage());
+    assert!(sessions.get("99").is_some() && sessions.get("0").is_none());
+    assert_eq!(0, manager.enforce());
+
+    drop(pages);
+    assert_eq!(vec![String::from("sessions")], manager.memory_usage().into_iter().map(|(name, _)| name).collect::<Vec<_>>());
+    assert!(manager.unregister("sessions"));
+    assert!(!manager.unregister("sessions"));
+}
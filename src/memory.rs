@@ -0,0 +1,232 @@
+use alloc::string::String;
+use core::hash::BuildHasher;
+use core::mem;
+
+use crate::slab::NodeId;
+use crate::{CacheEntity, InnerLocalCache, Link, LocalCache, LocalCacheBuilder, Slot};
+
+impl<T, S: BuildHasher> InnerLocalCache<T, S> {
+    fn memory_usage(&self) -> usize {
+        let slots = self.map.capacity() * mem::size_of::<NodeId>();
+        let vacant = (self.map.node_capacity() - self.map.len()) * mem::size_of::<CacheEntity<T>>();
+        slots + vacant + self.map.values().map(|entity| self.entry_size(entity)).sum::<usize>()
+    }
+
+    // The entry's node, key, tags and value.
+    pub(crate) fn entry_size(&self, entity: &CacheEntity<T>) -> usize {
+        let mut bytes = mem::size_of::<CacheEntity<T>>() + entity.key.heap_size();
+        bytes += entity.tags.iter().map(|tag| mem::size_of::<String>() + tag.capacity()).sum::<usize>();
+        bytes += match &entity.value {
+            // The Arc's two counts, the value, and whatever it owns on the heap.
+            Slot::Hot(value) => {
+                2 * mem::size_of::<usize>() + mem::size_of::<T>() + self.value_size.map_or(0, |value_size| value_size(value))
+            }
+            Slot::Cold(bytes) => bytes.len(),
+            Slot::Negative => 0,
+            // The value belongs to whoever keeps it alive.
+            Slot::Weak(_) => 0,
+        };
+        bytes
+    }
+
+    // Moves nodes into the slots of removed ones, freeing the room left, and
+    // updates every link to the nodes moved.
+    pub(crate) fn shrink_to_fit(&mut self) {
+        // Made first, as the ring's ids aren't remapped.
+        self.apply_promotions();
+        let moved = self.map.shrink_to_fit();
+        if moved.is_empty() {
+            return;
+        }
+        // The read copy has ids too.
+        self.changed();
+        let link = |link: Link| link.map(|id| moved.get(&id).copied().unwrap_or(id));
+        for entity in self.map.values_mut() {
+            entity.lru_prev = link(entity.lru_prev);
+            entity.lru_next = link(entity.lru_next);
+            entity.exp_prev = link(entity.exp_prev);
+            entity.exp_next = link(entity.exp_next);
+        }
+        for end in [
+            &mut self.lru_head,
+            &mut self.lru_tail,
+            &mut self.window_head,
+            &mut self.window_tail,
+            &mut self.protected_head,
+            &mut self.protected_tail,
+            &mut self.pinned_head,
+            &mut self.pinned_tail,
+            &mut self.low_head,
+            &mut self.low_tail,
+            &mut self.high_head,
+            &mut self.high_tail,
+            &mut self.cold_head,
+            &mut self.cold_tail,
+        ] {
+            *end = link(*end);
+        }
+        self.wheel.relink(link);
+        self.pool.relink(link);
+        for id in self.lfu.values_mut() {
+            *id = link(Some(*id)).unwrap();
+        }
+    }
+
+    // Recounts an entry against the byte budget after it changed.
+    pub(crate) fn recount(&mut self, id: NodeId) {
+        if self.max_bytes.is_none() {
+            return;
+        }
+        let size = self.entry_size(&self.map[id]);
+        let entity = &mut self.map[id];
+        self.bytes = self.bytes - entity.size + size;
+        entity.size = size;
+    }
+
+    // Evicts (or demotes) entries until the shard fits its byte budget,
+    // dropping cold entries once no hot one is left. Pinned entries stay.
+    pub(crate) fn fit_bytes(&mut self) {
+        let Some(max_bytes) = self.max_bytes else {
+            return;
+        };
+        while self.bytes > max_bytes {
+            let Some(victim) = self.victim().or(self.cold_tail) else {
+                break;
+            };
+            if !self.demote(victim) {
+                self.evict(victim);
+            }
+        }
+    }
+}
+
+impl<T, S> LocalCacheBuilder<T, S> {
+    /// Tells [`memory_usage`](LocalCache::memory_usage) how many heap bytes
+    /// a value owns beyond `size_of::<T>()`, like a `String`'s capacity.
+    pub fn value_size(mut self, value_size: fn(&T) -> usize) -> Self {
+        self.value_size = Some(value_size);
+        self
+    }
+
+    /// Evicts entries whenever the cache holds more than `max_bytes`, as
+    /// counted by [`memory_usage`](LocalCache::memory_usage) less the key
+    /// map's slots, on top of the `max_entries` limit. Set a
+    /// [`value_size`](Self::value_size) hook for values that own heap
+    /// memory, or they count only their inline size. The budget is split
+    /// between the shards evenly, or by their
+    /// [`shard_weights`](Self::shard_weights).
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// Estimated bytes held by the cache: map slots, entry nodes, keys, tags
+    /// and values. Values count only their inline size unless a
+    /// [`value_size`](LocalCacheBuilder::value_size) hook is set; the spill
+    /// store is not included.
+    pub fn memory_usage(&self) -> usize {
+        self.shards.iter().map(|shard| shard.lock().memory_usage()).sum()
+    }
+
+    /// Makes room in the key map for `additional` more entries, split
+    /// evenly between the shards.
+    pub fn reserve(&self, additional: usize) {
+        let per_shard = additional.div_ceil(self.shards.len());
+        for shard in self.shards.iter() {
+            shard.lock().map.reserve(per_shard);
+        }
+    }
+
+    /// Releases key map capacity left over from entries that are gone, e.g.
+    /// after a burst of inserts has expired, moving entries into the slots
+    /// of removed ones so that their nodes' memory can be freed too.
+    pub fn shrink_to_fit(&self) {
+        for shard in self.shards.iter() {
+            let mut local_cache = shard.lock();
+            local_cache.shrink_to_fit();
+            local_cache.tags.shrink_to_fit();
+            local_cache.leases.shrink_to_fit();
+        }
+    }
+}
+
+#[test]
+fn test_memory_usage() {
+    use std::sync::Arc;
+
+    let local_cache: LocalCache<String> = LocalCache::new(16, 360);
+    let empty = local_cache.memory_usage();
+    local_cache.put(String::from("a"), Arc::new(String::from("x")));
+    let one = local_cache.memory_usage();
+    assert!(one > empty + mem::size_of::<CacheEntity<String>>());
+
+    let local_cache: LocalCache<String> = LocalCache::builder().value_size(String::capacity).build();
+    local_cache.put(String::from("a"), Arc::new("x".repeat(1000)));
+    assert!(local_cache.memory_usage() >= one + 999);
+}
+
+#[test]
+fn test_max_bytes() {
+    use std::sync::Arc;
+
+    let local_cache: LocalCache<String> = LocalCache::builder().value_size(String::capacity).max_bytes(10_000).build();
+    for i in 0..20 {
+        local_cache.put(i.to_string(), Arc::new("x".repeat(1000)));
+    }
+    assert!(local_cache.memory_usage() <= 10_000 + 64 * mem::size_of::<usize>());
+    assert_eq!(None, local_cache.get("0"));
+    assert!(local_cache.get("19").is_some());
+    assert!(local_cache.shards[0].lock().map.len() < 10);
+
+    local_cache.put(String::from("huge"), Arc::new("x".repeat(20_000)));
+    assert_eq!(None, local_cache.get("huge"));
+    local_cache.put(String::from("a"), Arc::new(String::new()));
+    local_cache.put_tagged(String::from("b"), Arc::new(String::new()), ["t"]);
+    let shard = local_cache.shards[0].lock();
+    assert_eq!(shard.bytes, shard.map.values().map(|entity| entity.size).sum::<usize>());
+}
+
+#[test]
+fn test_reserve_shrink_to_fit() {
+    use std::sync::Arc;
+
+    let local_cache: LocalCache<usize> = LocalCache::builder().max_entries(4096).shards(2).build();
+    local_cache.reserve(1000);
+    assert!(local_cache.shards.iter().all(|shard| shard.lock().map.capacity() >= 500));
+    for i in 0..1000 {
+        local_cache.put(i.to_string(), Arc::new(i));
+    }
+    for i in 1..1000 {
+        local_cache.remove(&i.to_string());
+    }
+    let before = local_cache.memory_usage();
+    local_cache.shrink_to_fit();
+    assert!(local_cache.memory_usage() < before);
+    assert_eq!(Some(Arc::new(0)), local_cache.get("0"));
+}
+
+#[test]
+fn test_shrink_to_fit_moves_nodes() {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let local_cache: LocalCache<u64> = LocalCache::new(8, 360);
+    for i in 0..8 {
+        local_cache.put_with_ttl(i.to_string(), Arc::new(i), Duration::from_secs(60 + i));
+    }
+    for i in 0..4 {
+        local_cache.remove(&i.to_string());
+    }
+    local_cache.get("5");
+    local_cache.shrink_to_fit();
+    assert_eq!(4, local_cache.shards[0].lock().map.node_capacity());
+    // The LRU and expiry orders carried over to the moved nodes.
+    let lru: Vec<_> = local_cache.iter_lru().map(|(key, _)| key).collect();
+    assert_eq!(vec!["5", "7", "6", "4"], lru);
+    let mut shard = local_cache.shards[0].lock();
+    let now = shard.now();
+    assert_eq!(2, shard.evict_expired(now + Duration::from_millis(65_500).as_nanos(), usize::MAX));
+    assert!(shard.map.get("6").is_some() && shard.map.get("5").is_none());
+}
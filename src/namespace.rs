@@ -0,0 +1,84 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::hash::BuildHasher;
+use core::time::Duration;
+
+use crate::{DefaultHashBuilder, LocalCache, Lookup};
+
+/// A view of the cache that prefixes every key with `"<name>:"`, see
+/// [`LocalCache::namespace`].
+pub struct Namespace<'a, T, S = DefaultHashBuilder> {
+    local_cache: &'a LocalCache<T, S>,
+    prefix: String,
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// A handle whose keys live under `"<name>:"`, sharing this cache's
+    /// capacity and LRU order with everything else in it.
+    pub fn namespace(&self, name: &str) -> Namespace<'_, T, S> {
+        Namespace { local_cache: self, prefix: format!("{}:", name) }
+    }
+}
+
+impl<T, S: BuildHasher> Namespace<'_, T, S> {
+    fn key(&self, key: &str) -> String {
+        let mut full = String::with_capacity(self.prefix.len() + key.len());
+        full.push_str(&self.prefix);
+        full.push_str(key);
+        full
+    }
+
+    pub fn name(&self) -> &str {
+        &self.prefix[..self.prefix.len() - 1]
+    }
+
+    pub fn get(&self, key: &str) -> Option<Arc<T>> {
+        self.local_cache.get(&self.key(key))
+    }
+
+    pub fn lookup(&self, key: &str) -> Lookup<T> {
+        self.local_cache.lookup(&self.key(key))
+    }
+
+    pub fn put(&self, key: &str, value: impl Into<Arc<T>>) {
+        let value = value.into();
+        self.local_cache.put(self.key(key), value)
+    }
+
+    pub fn put_with_ttl(&self, key: &str, value: impl Into<Arc<T>>, ttl: Duration) {
+        let value = value.into();
+        self.local_cache.put_with_ttl(self.key(key), value, ttl)
+    }
+
+    pub fn remove(&self, key: &str) -> Option<Arc<T>> {
+        self.local_cache.remove(&self.key(key))
+    }
+
+    /// Removes every entry of this namespace. Returns the number removed.
+    pub fn clear(&self) -> usize {
+        self.local_cache.invalidate_prefix(&self.prefix)
+    }
+}
+
+#[test]
+fn test_namespace() {
+    let local_cache: LocalCache<usize> = LocalCache::new(3, 360);
+    let sessions = local_cache.namespace("sessions");
+    let avatars = local_cache.namespace("avatars");
+    assert_eq!("sessions", sessions.name());
+    sessions.put("1", Arc::new(1));
+    avatars.put("1", Arc::new(10));
+    assert_eq!(Some(Arc::new(1)), sessions.get("1"));
+    assert_eq!(Some(Arc::new(10)), local_cache.get("avatars:1"));
+
+    // One capacity for all namespaces.
+    avatars.put("2", Arc::new(20));
+    avatars.put("3", Arc::new(30));
+    assert_eq!(None, sessions.get("1"));
+
+    sessions.put("2", Arc::new(2));
+    assert_eq!(2, avatars.clear());
+    assert_eq!(None, avatars.get("3"));
+    assert_eq!(Some(Arc::new(2)), sessions.get("2"));
+}
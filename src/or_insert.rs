@@ -0,0 +1,206 @@
+use alloc::sync::Arc;
+use core::convert::Infallible;
+use core::hash::BuildHasher;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::sync::mpsc;
+#[cfg(feature = "std")]
+use std::thread;
+
+#[cfg(feature = "std")]
+use crate::{InnerLocalCache, Slot};
+use crate::{Key, LocalCache, Lookup};
+
+#[cfg(feature = "std")]
+impl<T, S: BuildHasher> InnerLocalCache<T, S> {
+    // The value cached for `key` even if it has expired, as long as it is
+    // still in memory and hot.
+    fn stale(&self, key: &str) -> Option<Arc<T>> {
+        let entity = self.map.get(key)?;
+        match &entity.value {
+            Slot::Hot(value) => Some(value.clone()),
+            Slot::Weak(value) => value.upgrade(),
+            Slot::Cold(_) | Slot::Negative => None,
+        }
+    }
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// Returns the cached value for `key`, or caches and returns `f()`.
+    /// `f` runs without holding the shard lock; concurrent misses may each
+    /// run it, see [`acquire_lease`](Self::acquire_lease) to prevent that.
+    /// A negative entry counts as a miss and is replaced.
+    pub fn get_or_insert_with<F: FnOnce() -> T>(&self, key: impl Into<Key>, f: F) -> Arc<T> {
+        let key = key.into();
+        if let Some(value) = self.get(&key) {
+            return value;
+        }
+        self.load_with(key, || Ok::<_, Infallible>(f())).unwrap_or_else(|never| match never {})
+    }
+
+    /// Like [`get_or_insert_with`](Self::get_or_insert_with) for a loader
+    /// that also says how long its value stays fresh, like a token and the
+    /// lifetime it was issued with.
+    pub fn get_or_insert_with_ttl<F: FnOnce() -> (T, Duration)>(&self, key: impl Into<Key>, f: F) -> Arc<T> {
+        let key = key.into();
+        if let Some(value) = self.get(&key) {
+            return value;
+        }
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!("loader", key = self.traced_key(&key)).entered();
+        let (value, ttl) = f();
+        #[cfg(feature = "tracing")]
+        drop(span);
+        let value = Arc::new(value);
+        self.enqueue(key, Some(value.clone()), Some(ttl), "loader");
+        value
+    }
+
+    /// Like [`get_or_insert_with`](Self::get_or_insert_with) for a loader
+    /// that can fail. Errors are handed back and not cached, unless a
+    /// [`negative_ttl`](crate::LocalCacheBuilder::negative_ttl) is set: then
+    /// the key is cached as negative, and until that expires this method
+    /// returns `Ok(None)` without calling `f`. A panicking `f` leaves the
+    /// cache untouched.
+    pub fn get_or_try_insert_with<F, E>(&self, key: impl Into<Key>, f: F) -> Result<Option<Arc<T>>, E>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        let key = key.into();
+        match self.lookup(&key) {
+            Lookup::Hit(value) => return Ok(Some(value)),
+            Lookup::Negative => return Ok(None),
+            Lookup::Miss | Lookup::Corrupted => {}
+        }
+        self.load_with(key, f).map(Some)
+    }
+
+    // Runs `f` for a miss on `key` and caches what it returns, as described
+    // for `get_or_try_insert_with`.
+    pub(crate) fn load_with<F, E>(&self, key: Key, f: F) -> Result<Arc<T>, E>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!("loader", key = self.traced_key(&key)).entered();
+        let loaded = f();
+        #[cfg(feature = "tracing")]
+        drop(span);
+        self.cache_loaded(key, loaded)
+    }
+
+    pub(crate) fn cache_loaded<E>(&self, key: Key, loaded: Result<T, E>) -> Result<Arc<T>, E> {
+        match loaded {
+            Ok(value) => {
+                let value = Arc::new(value);
+                self.enqueue(key, Some(value.clone()), None, "loader");
+                Ok(value)
+            }
+            Err(err) => {
+                if self.lock(&key).negative_caching {
+                    self.enqueue(key, None, None, "loader");
+                }
+                Err(err)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Send + Sync + 'static, S: BuildHasher + Send + 'static> LocalCache<T, S> {
+    /// Like [`get_or_insert_with`](Self::get_or_insert_with), but waits at
+    /// most `timeout` for `f`. If it takes longer, returns the expired value
+    /// still cached for `key` if there is one, or `fallback`, while `f` keeps
+    /// running on its own thread and caches its value when it finishes.
+    pub fn get_or_insert_with_timeout<F>(&self, key: impl Into<Key>, timeout: Duration, fallback: Arc<T>, f: F) -> Arc<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let key = key.into();
+        // Taken first: the lookup removes the entry if it has expired.
+        let stale = self.lock(&key).stale(&key);
+        if let Some(value) = self.get(&key) {
+            return value;
+        }
+        let (sender, receiver) = mpsc::sync_channel(1);
+        let shards = self.shards.clone();
+        let index = self.shard_index(&key);
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!("loader", key = self.traced_key(&key), timeout_ms = timeout.as_millis() as u64);
+        thread::spawn(move || {
+            #[cfg(feature = "tracing")]
+            let _span = span.entered();
+            let value = Arc::new(f());
+            shards[index].lock().insert(key, Some(value.clone()), None, "loader");
+            let _ = sender.send(value);
+        });
+        receiver.recv_timeout(timeout).unwrap_or_else(|_| stale.unwrap_or(fallback))
+    }
+}
+
+#[test]
+fn test_get_or_insert_with_timeout() {
+    let local_cache: LocalCache<usize> = LocalCache::new(4, 360);
+    assert_eq!(Arc::new(1), local_cache.get_or_insert_with(String::from("x"), || 1));
+    assert_eq!(Arc::new(1), local_cache.get_or_insert_with(String::from("x"), || 2));
+
+    let slow = || {
+        thread::sleep(Duration::from_millis(50));
+        3
+    };
+    let value = local_cache.get_or_insert_with_timeout(String::from("y"), Duration::from_millis(5), Arc::new(0), slow);
+    assert_eq!(Arc::new(0), value);
+    assert_eq!(None, local_cache.get("y"));
+    thread::sleep(Duration::from_millis(100));
+    assert_eq!(Some(Arc::new(3)), local_cache.get("y"));
+
+    let value = local_cache.get_or_insert_with_timeout(String::from("z"), Duration::from_secs(5), Arc::new(0), || 4);
+    assert_eq!(Arc::new(4), value);
+
+    // An expired value beats the fallback.
+    local_cache.put_with_ttl("w", 1, Duration::from_millis(1));
+    thread::sleep(Duration::from_millis(5));
+    let value = local_cache.get_or_insert_with_timeout(String::from("w"), Duration::from_millis(5), Arc::new(0), slow);
+    assert_eq!(Arc::new(1), value);
+}
+
+#[test]
+fn test_get_or_try_insert_with() {
+    use std::cell::Cell;
+
+    let local_cache: LocalCache<usize> = LocalCache::new(4, 360);
+    assert_eq!(Err("down"), local_cache.get_or_try_insert_with(String::from("x"), || Err("down")));
+    assert_eq!(Lookup::Miss, local_cache.lookup("x"));
+    assert_eq!(Ok::<_, &str>(Some(Arc::new(1))), local_cache.get_or_try_insert_with(String::from("x"), || Ok(1)));
+    assert_eq!(Ok::<_, &str>(Some(Arc::new(1))), local_cache.get_or_try_insert_with(String::from("x"), || Err("down")));
+
+    let panicking = || local_cache.get_or_try_insert_with(String::from("y"), || -> Result<usize, ()> { panic!() });
+    let panicked = std::panic::catch_unwind(std::panic::AssertUnwindSafe(panicking));
+    assert!(panicked.is_err());
+    assert_eq!(Ok::<_, ()>(Some(Arc::new(2))), local_cache.get_or_try_insert_with(String::from("y"), || Ok(2)));
+
+    // A cached failure is handed back without calling the loader again.
+    let local_cache: LocalCache<usize> = LocalCache::builder().negative_ttl(Duration::from_secs(10)).build();
+    let calls = Cell::new(0);
+    let failing = || {
+        calls.set(calls.get() + 1);
+        Err("down")
+    };
+    assert_eq!(Err("down"), local_cache.get_or_try_insert_with(String::from("x"), failing));
+    assert_eq!(Lookup::Negative, local_cache.lookup("x"));
+    assert_eq!(Ok(None), local_cache.get_or_try_insert_with(String::from("x"), failing));
+    assert_eq!(1, calls.get());
+}
+
+#[test]
+fn test_get_or_insert_with_ttl() {
+    let local_cache: LocalCache<&str> = LocalCache::new(4, 360);
+    let token = local_cache.get_or_insert_with_ttl(String::from("token"), || ("t1", Duration::from_millis(5)));
+    assert_eq!(Arc::new("t1"), token);
+    let token = local_cache.get_or_insert_with_ttl(String::from("token"), || unreachable!());
+    assert_eq!(Arc::new("t1"), token);
+    assert_eq!(Some("loader"), local_cache.source("token"));
+    thread::sleep(Duration::from_millis(10));
+    let token = local_cache.get_or_insert_with_ttl(String::from("token"), || ("t2", Duration::from_secs(60)));
+    assert_eq!(Arc::new("t2"), token);
+}
@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use std::ptr::{self, NonNull};
+use std::sync::{PoisonError, RwLock};
+use std::time::Duration;
+
+use crate::checksum::crc32;
+use crate::{ttl_ns, Clock, SystemClock, ValueCodec, NEVER};
+
+// A record is this header, then the key, then the value. The header holds
+// the kind, the key and value lengths, the expiry time, a CRC-32 of the
+// value and one of the header and key, so a torn write at the end of the
+// file is told apart from a record without reading every value.
+const HEADER: usize = 37;
+const PUT: u8 = 1;
+const DELETE: u8 = 2;
+// The file is grown by at least this much at a time, ahead of the writes.
+const MIN_GROWTH: u64 = 1 << 20;
+
+// A read-only shared mapping of the whole file.
+struct Mapping {
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+// The mapping is only read, and only unmapped by its owner.
+unsafe impl Send for Mapping {}
+unsafe impl Sync for Mapping {}
+
+impl Mapping {
+    fn new(file: &File, len: u64) -> io::Result<Self> {
+        let len = usize::try_from(len).map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "file too large to map"))?;
+        let ptr = unsafe { libc::mmap(ptr::null_mut(), len, libc::PROT_READ, libc::MAP_SHARED, file.as_raw_fd(), 0) };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { ptr: NonNull::new(ptr.cast()).expect("mmap returned null"), len })
+    }
+
+    fn bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr.as_ptr().cast(), self.len) };
+    }
+}
+
+// Where a key's latest value is in the file.
+#[derive(Clone, Copy)]
+struct Record {
+    offset: usize,
+    len: usize,
+    exp: u128,
+    crc: u32,
+}
+
+struct Log {
+    file: File,
+    mapping: Mapping,
+    // End of the last record; the file is longer, zeroed past it.
+    len: u64,
+    index: HashMap<String, Record>,
+    // Bytes of records superseded by later ones, see `compact`.
+    garbage: u64,
+}
+
+impl Log {
+    fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        let mut capacity = file.metadata()?.len();
+        if capacity == 0 {
+            capacity = MIN_GROWTH;
+            file.set_len(capacity)?;
+        }
+        let mapping = Mapping::new(&file, capacity)?;
+        let mut log = Self { file, mapping, len: 0, index: HashMap::new(), garbage: 0 };
+        log.replay();
+        Ok(log)
+    }
+
+    // Rebuilds the index from the records, up to the first torn one. Only
+    // the last record's value can be torn by a crash, so only its checksum
+    // is checked.
+    fn replay(&mut self) {
+        let bytes = self.mapping.bytes();
+        let mut records = Vec::new();
+        let mut at = 0;
+        while let Some((kind, key, record)) = parse(bytes, at) {
+            at = record.offset + record.len;
+            records.push((kind, key, record));
+        }
+        if records.last().is_some_and(|(_, _, record)| crc32(&bytes[record.offset..][..record.len]) != record.crc) {
+            records.pop();
+        }
+        for (kind, key, record) in records {
+            let replaced = match kind {
+                PUT => self.index.insert(key.to_string(), record),
+                _ => {
+                    self.garbage += (HEADER + key.len()) as u64;
+                    self.index.remove(key)
+                }
+            };
+            if let Some(old) = replaced {
+                self.garbage += (HEADER + key.len() + old.len) as u64;
+            }
+            self.len = (record.offset + record.len) as u64;
+        }
+    }
+
+    fn append(&mut self, kind: u8, key: &str, value: &[u8], exp: u128) -> io::Result<Record> {
+        let crc = crc32(value);
+        let record = encode(kind, key, value, exp, crc);
+        let end = self.len + record.len() as u64;
+        if end > self.mapping.len as u64 {
+            let capacity = end.max(2 * self.mapping.len as u64).max(MIN_GROWTH);
+            self.file.set_len(capacity)?;
+            self.mapping = Mapping::new(&self.file, capacity)?;
+        }
+        self.file.write_all_at(&record, self.len)?;
+        let offset = (self.len as usize) + HEADER + key.len();
+        self.len = end;
+        Ok(Record { offset, len: value.len(), exp, crc })
+    }
+
+    // The value of a record, unless it fails its checksum.
+    fn value(&self, record: &Record) -> Option<&[u8]> {
+        let value = &self.mapping.bytes()[record.offset..][..record.len];
+        (crc32(value) == record.crc).then_some(value)
+    }
+}
+
+// The kind and key of the record at `at` and where its value is, if its
+// header and key are whole.
+fn parse(bytes: &[u8], at: usize) -> Option<(u8, &str, Record)> {
+    let (header, rest) = bytes.get(at..)?.split_first_chunk::<HEADER>()?;
+    let field = |range: std::ops::Range<usize>| &header[range];
+    let kind = header[0];
+    if kind != PUT && kind != DELETE {
+        return None;
+    }
+    let key_len = u32::from_le_bytes(field(1..5).try_into().unwrap()) as usize;
+    let len = usize::try_from(u64::from_le_bytes(field(5..13).try_into().unwrap())).ok()?;
+    let exp = u128::from_le_bytes(field(13..29).try_into().unwrap());
+    let crc = u32::from_le_bytes(field(29..33).try_into().unwrap());
+    let header_crc = u32::from_le_bytes(field(33..37).try_into().unwrap());
+    let key = rest.get(..key_len)?;
+    if crc32(&[&header[..33], key].concat()) != header_crc || rest.len() < key_len.checked_add(len)? {
+        return None;
+    }
+    let key = std::str::from_utf8(key).ok()?;
+    Some((kind, key, Record { offset: at + HEADER + key_len, len, exp, crc }))
+}
+
+fn encode(kind: u8, key: &str, value: &[u8], exp: u128, crc: u32) -> Vec<u8> {
+    let mut record = Vec::with_capacity(HEADER + key.len() + value.len());
+    record.push(kind);
+    record.extend_from_slice(&(key.len() as u32).to_le_bytes());
+    record.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    record.extend_from_slice(&exp.to_le_bytes());
+    record.extend_from_slice(&crc.to_le_bytes());
+    let header_crc = crc32(&[&record, key.as_bytes()].concat());
+    record.extend_from_slice(&header_crc.to_le_bytes());
+    record.extend_from_slice(key.as_bytes());
+    record.extend_from_slice(value);
+    record
+}
+
+/// A cache kept in a memory-mapped, append-only file with an index of it in
+/// memory, for large artifacts that should survive restarts.
+///
+/// Opening the file rebuilds the index from it, without decoding any value;
+/// a record torn by a crash is dropped along with anything after it. Every
+/// write appends a record; reads decode the value straight from the
+/// mapping, which leaves caching the file's pages to the OS. Superseded
+/// and expired records take up room until [`compact`](Self::compact).
+/// Only one process may have the file open at a time.
+pub struct PersistentLocalCache<T, C> {
+    path: PathBuf,
+    codec: C,
+    ttl: Duration,
+    log: RwLock<Log>,
+    _marker: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T, C: ValueCodec<T>> PersistentLocalCache<T, C> {
+    /// Opens the cache in the file at `path`, creating it if needed.
+    /// Entries expire after `ttl` unless inserted with a TTL of their own.
+    pub fn open<P: AsRef<Path>>(path: P, codec: C, ttl: Duration) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let log = Log::open(&path)?;
+        Ok(Self { path, codec, ttl, log: RwLock::new(log), _marker: std::marker::PhantomData })
+    }
+
+    /// The value of `key`, decoded afresh, unless it is missing, expired,
+    /// fails its checksum or fails to decode.
+    pub fn get(&self, key: &str) -> Option<T> {
+        let log = self.log.read().unwrap_or_else(PoisonError::into_inner);
+        let record = log.index.get(key)?;
+        if SystemClock.now_ns() > record.exp {
+            return None;
+        }
+        self.codec.decode(log.value(record)?)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        let log = self.log.read().unwrap_or_else(PoisonError::into_inner);
+        log.index.get(key).is_some_and(|record| SystemClock.now_ns() <= record.exp)
+    }
+
+    pub fn put(&self, key: &str, value: &T) -> io::Result<()> {
+        self.put_with_ttl(key, value, self.ttl)
+    }
+
+    /// Like [`put`](Self::put), with `ttl` instead of the default TTL.
+    pub fn put_with_ttl(&self, key: &str, value: &T, ttl: Duration) -> io::Result<()> {
+        let Some(bytes) = self.codec.encode(value) else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "value failed to encode"));
+        };
+        let exp = match ttl_ns(ttl) {
+            NEVER => NEVER,
+            ttl_ns => SystemClock.now_ns().saturating_add(ttl_ns),
+        };
+        let mut log = self.log.write().unwrap_or_else(PoisonError::into_inner);
+        let record = log.append(PUT, key, &bytes, exp)?;
+        if let Some(old) = log.index.insert(key.to_string(), record) {
+            log.garbage += (HEADER + key.len() + old.len) as u64;
+        }
+        Ok(())
+    }
+
+    /// Removes `key`. Returns whether it had an entry, expired or not.
+    pub fn remove(&self, key: &str) -> io::Result<bool> {
+        let mut log = self.log.write().unwrap_or_else(PoisonError::into_inner);
+        let Some(old) = log.index.get(key).copied() else {
+            return Ok(false);
+        };
+        log.append(DELETE, key, &[], 0)?;
+        log.index.remove(key);
+        log.garbage += (2 * HEADER + 2 * key.len() + old.len) as u64;
+        Ok(true)
+    }
+
+    /// Entries in the index, expired ones included until compacted.
+    pub fn len(&self) -> usize {
+        self.log.read().unwrap_or_else(PoisonError::into_inner).index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Bytes of records in the file, and how many of them are superseded.
+    pub fn file_usage(&self) -> (u64, u64) {
+        let log = self.log.read().unwrap_or_else(PoisonError::into_inner);
+        (log.len, log.garbage)
+    }
+
+    /// Makes every write so far durable.
+    pub fn flush(&self) -> io::Result<()> {
+        self.log.read().unwrap_or_else(PoisonError::into_inner).file.sync_data()
+    }
+
+    /// Rewrites the file with only the live entries, dropping superseded,
+    /// removed and expired records. Reads and writes wait for it to finish.
+    pub fn compact(&self) -> io::Result<()> {
+        let mut log = self.log.write().unwrap_or_else(PoisonError::into_inner);
+        let tmp = self.path.with_extension("compact");
+        let _ = fs::remove_file(&tmp);
+        let mut compacted = Log::open(&tmp)?;
+        let now = SystemClock.now_ns();
+        for (key, record) in &log.index {
+            if let Some(value) = log.value(record).filter(|_| now <= record.exp) {
+                let copy = compacted.append(PUT, key, value, record.exp)?;
+                compacted.index.insert(key.clone(), copy);
+            }
+        }
+        compacted.file.sync_data()?;
+        fs::rename(&tmp, &self.path)?;
+        *log = compacted;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_persistent_local_cache() {
+    use crate::testing::Utf8;
+
+    let path = std::env::temp_dir().join(format!("local-cache-persistent-{}", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let cache = PersistentLocalCache::open(&path, Utf8, Duration::MAX).unwrap();
+    cache.put("a", &String::from("1")).unwrap();
+    cache.put("a", &String::from("2")).unwrap();
+    cache.put("b", &"x".repeat(3 << 20)).unwrap();
+    cache.put_with_ttl("c", &String::from("3"), Duration::ZERO).unwrap();
+    assert!(cache.remove("b").unwrap());
+    assert!(!cache.remove("b").unwrap());
+    std::thread::sleep(Duration::from_millis(1));
+    assert_eq!(Some(String::from("2")), cache.get("a"));
+    assert_eq!(None, cache.get("b"));
+    assert_eq!(None, cache.get("c"));
+    cache.flush().unwrap();
+    drop(cache);
+
+    // Reopening rebuilds the index; a torn record at the end is dropped.
+    let file = OpenOptions::new().write(true).open(&path).unwrap();
+    let (len, _) = PersistentLocalCache::open(&path, Utf8, Duration::MAX).unwrap().file_usage();
+    file.write_all_at(&encode(PUT, "d", b"45", NEVER, crc32(b"45"))[..HEADER + 2], len).unwrap();
+    let cache = PersistentLocalCache::open(&path, Utf8, Duration::MAX).unwrap();
+    assert_eq!(Some(String::from("2")), cache.get("a"));
+    assert!(!cache.contains_key("d"));
+    assert_eq!(2, cache.len());
+
+    let (_, garbage) = cache.file_usage();
+    assert!(garbage > 3 << 20);
+    cache.compact().unwrap();
+    assert_eq!((HEADER as u64 + 2, 0), cache.file_usage());
+    assert_eq!(Some(String::from("2")), cache.get("a"));
+    cache.put("e", &String::from("5")).unwrap();
+    drop(cache);
+    let cache = PersistentLocalCache::open(&path, Utf8, Duration::MAX).unwrap();
+    assert_eq!((Some(String::from("2")), Some(String::from("5"))), (cache.get("a"), cache.get("e")));
+    let _ = fs::remove_file(&path);
+}
@@ -0,0 +1,102 @@
+use alloc::sync::Arc;
+use core::hash::BuildHasher;
+
+use crate::{InnerLocalCache, Key, LocalCache, LocalCacheBuilder, Segment, Slot};
+
+impl<T, S: BuildHasher> InnerLocalCache<T, S> {
+    // Moves a live entry to the pinned list, decoding it if it is cold.
+    // Returns false if there is no such entry.
+    fn pin(&mut self, key: &str) -> bool {
+        let Some(id) = self.map.find(key) else {
+            return false;
+        };
+        let now = self.now();
+        let entity = &self.map[id];
+        if now > entity.exp {
+            return false;
+        }
+        if entity.segment == Segment::Pinned {
+            return true;
+        }
+        if let Slot::Cold(bytes) = &entity.value {
+            let Some(value) = self.cold.as_ref().and_then(|cold| cold.codec.decode(bytes)) else {
+                return false;
+            };
+            self.remove_lru(id);
+            self.map[id].value = Slot::Hot(Arc::new(value));
+            self.cold_len -= 1;
+            self.push_lru_front(id);
+            self.recount(id);
+        }
+        self.resegment(id, Segment::Pinned);
+        self.trim();
+        true
+    }
+
+    fn unpin(&mut self, key: &str) -> bool {
+        let Some(id) = self.map.find(key) else {
+            return false;
+        };
+        if self.map[id].segment != Segment::Pinned {
+            return false;
+        }
+        self.resegment(id, Segment::Main);
+        self.trim();
+        true
+    }
+}
+
+impl<T, S> LocalCacheBuilder<T, S> {
+    /// Counts pinned entries against `max_entries`, so pinning shrinks the
+    /// room left for other entries. By default they are on top of it.
+    pub fn pinned_counts_against_capacity(mut self) -> Self {
+        self.pinned_count = true;
+        self
+    }
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// Like [`put`](Self::put), and pins the entry, see [`pin`](Self::pin).
+    pub fn put_pinned(&self, key: impl Into<Key>, value: impl Into<Arc<T>>) {
+        let (key, value) = (key.into(), value.into());
+        let mut local_cache = self.lock(&key);
+        local_cache.put(key.clone(), Some(value));
+        local_cache.pin(&key);
+    }
+
+    /// Exempts the live entry for `key` from capacity eviction until it is
+    /// unpinned, replaced by a `put` or removed. It still expires with its
+    /// TTL. Returns false if there is no live entry for `key`.
+    pub fn pin(&self, key: &str) -> bool {
+        self.lock(key).pin(key)
+    }
+
+    /// Makes a pinned entry evictable again. Returns false if `key` wasn't pinned.
+    pub fn unpin(&self, key: &str) -> bool {
+        self.lock(key).unpin(key)
+    }
+}
+
+#[test]
+fn test_pin() {
+    let local_cache: LocalCache<usize> = LocalCache::new(2, 360);
+    local_cache.put_pinned(String::from("a"), Arc::new(0));
+    for i in 1..5 {
+        local_cache.put(i.to_string(), Arc::new(i));
+    }
+    assert_eq!(Some(Arc::new(0)), local_cache.get("a"));
+    assert_eq!(3, local_cache.shards[0].lock().map.len());
+
+    assert!(local_cache.unpin("a"));
+    assert!(!local_cache.unpin("a"));
+    assert_eq!(None, local_cache.get("3"));
+    assert!(local_cache.pin("4"));
+    assert!(!local_cache.pin("x"));
+
+    let local_cache: LocalCache<usize> = LocalCache::builder().max_entries(2).pinned_counts_against_capacity().build();
+    local_cache.put_pinned(String::from("a"), Arc::new(0));
+    local_cache.put(String::from("b"), Arc::new(1));
+    local_cache.put(String::from("c"), Arc::new(2));
+    assert_eq!(None, local_cache.get("b"));
+    assert_eq!(Some(Arc::new(0)), local_cache.get("a"));
+}
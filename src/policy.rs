@@ -0,0 +1,174 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cell::Cell;
+use core::hash::BuildHasher;
+
+use crate::keymap::KeyMap;
+use crate::slab::NodeId;
+use crate::{DefaultHashBuilder, Link};
+
+/// Which resident entry is evicted when the cache is full, see
+/// [`LocalCacheBuilder::eviction_policy`](crate::LocalCacheBuilder::eviction_policy).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Least recently used.
+    #[default]
+    Lru,
+    /// Least frequently used, by hits since insertion; ties go to the entry
+    /// accessed longest ago. Hits update a frequency index instead of
+    /// moving the entry in the LRU list.
+    Lfu,
+    /// Insertion order: hits don't reorder anything, so `get` does no
+    /// bookkeeping beyond the lookup. Re-putting a key counts as a new insert.
+    Fifo,
+    /// Segmented LRU: new entries start in a probation segment and move to a
+    /// protected segment holding up to `protected_percent` of the capacity
+    /// when hit again. Entries overflowing the protected segment drop back to
+    /// probation, and eviction takes probation's LRU entry first.
+    Slru { protected_percent: u8 },
+    /// Approximate LRU, as in Redis: hits only record their time, like
+    /// under `Fifo`, and eviction takes whichever of `samples` entries
+    /// picked at random was used longest ago. More samples come closer to
+    /// exact LRU at the cost of slower evictions.
+    Sampled { samples: usize },
+}
+
+/// How early an entry is evicted for capacity, see
+/// [`LocalCache::put_with_priority`](crate::LocalCache::put_with_priority).
+/// Entries of a higher priority are only evicted once no lower priority entry is left.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+/// Decides whether a new entry may displace a resident one once the cache
+/// is full, see [`LocalCacheBuilder::admission`](crate::LocalCacheBuilder::admission).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    /// Every new entry is admitted and the LRU entry is evicted.
+    #[default]
+    Always,
+    /// W-TinyLFU: new entries land in a small LRU window (1% of the
+    /// capacity). Entries leaving the window are only admitted to the main
+    /// LRU if a frequency sketch estimates them to be more popular than the
+    /// entry they would evict, so a one-off scan can't flush hot keys.
+    TinyLfu,
+}
+
+const SKETCH_DEPTH: usize = 4;
+const SKETCH_MAX_COUNT: u8 = 15;
+const SKETCH_SEEDS: [u64; SKETCH_DEPTH] = [
+    0x97cb_3127_8f4a_c2b1,
+    0xc3a5_c85c_97cb_3127,
+    0xb492_b66f_be98_f273,
+    0x9ae1_6a3b_2f90_404f,
+];
+
+/// Count-min sketch of key access frequencies. Counters saturate at 15 and
+/// are all halved every `10 * capacity` increments, so old popularity fades.
+#[derive(Clone)]
+pub(crate) struct FrequencySketch {
+    counters: Vec<u8>,
+    mask: usize,
+    increments: usize,
+    reset_at: usize,
+    hasher: DefaultHashBuilder,
+}
+
+impl FrequencySketch {
+    pub(crate) fn new(capacity: usize) -> Self {
+        let width = capacity.max(64).next_power_of_two();
+        Self {
+            counters: vec![0; width * SKETCH_DEPTH],
+            mask: width - 1,
+            increments: 0,
+            reset_at: capacity.max(1) * 10,
+            hasher: DefaultHashBuilder::default(),
+        }
+    }
+
+    fn indexes(&self, key: &str) -> [usize; SKETCH_DEPTH] {
+        let hash = BuildHasher::hash_one(&self.hasher, key);
+        let width = self.mask + 1;
+        core::array::from_fn(|row| {
+            let spread = hash.wrapping_mul(SKETCH_SEEDS[row]);
+            row * width + ((spread >> 32) as usize & self.mask)
+        })
+    }
+
+    pub(crate) fn increment(&mut self, key: &str) {
+        for i in self.indexes(key) {
+            if self.counters[i] < SKETCH_MAX_COUNT {
+                self.counters[i] += 1;
+            }
+        }
+        self.increments += 1;
+        if self.increments >= self.reset_at {
+            self.counters.iter_mut().for_each(|c| *c /= 2);
+            self.increments /= 2;
+        }
+    }
+
+    pub(crate) fn frequency(&self, key: &str) -> u8 {
+        self.indexes(key).into_iter().map(|i| self.counters[i]).min().unwrap()
+    }
+}
+
+/// The main list's entries under [`EvictionPolicy::Sampled`], in no
+/// particular order, to draw eviction candidates from.
+#[derive(Clone)]
+pub(crate) struct SamplePool {
+    pub(crate) entries: Vec<NodeId>,
+    // Xorshift state; never 0.
+    rng: Cell<u64>,
+}
+
+impl SamplePool {
+    pub(crate) fn new() -> Self {
+        Self { entries: Vec::new(), rng: Cell::new(BuildHasher::hash_one(&DefaultHashBuilder::default(), 0u8) | 1) }
+    }
+
+    pub(crate) fn relink(&mut self, link: impl Fn(Link) -> Link) {
+        for id in self.entries.iter_mut() {
+            *id = link(Some(*id)).unwrap();
+        }
+    }
+
+    // Entries keep their index in the pool, to be removed in O(1).
+    pub(crate) fn insert<T, S>(&mut self, map: &mut KeyMap<T, S>, id: NodeId) {
+        map[id].pool_index = self.entries.len();
+        self.entries.push(id);
+    }
+
+    pub(crate) fn remove<T, S>(&mut self, map: &mut KeyMap<T, S>, id: NodeId) {
+        let index = map[id].pool_index;
+        self.entries.swap_remove(index);
+        if let Some(moved) = self.entries.get(index).copied() {
+            map[moved].pool_index = index;
+        }
+    }
+
+    fn next(&self) -> u64 {
+        let mut x = self.rng.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng.set(x);
+        x
+    }
+
+    // The least recently used of `samples` entries drawn with replacement.
+    pub(crate) fn oldest<T, S>(&self, map: &KeyMap<T, S>, samples: usize) -> Link {
+        if self.entries.is_empty() {
+            return None;
+        }
+        let len = self.entries.len() as u64;
+        (0..samples.max(1)).map(|_| self.entries[(self.next() % len) as usize]).min_by_key(|&id| {
+            let entity = &map[id];
+            entity.last_access.max(entity.created)
+        })
+    }
+}
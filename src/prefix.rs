@@ -0,0 +1,70 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::hash::BuildHasher;
+use core::ops::Bound;
+
+use crate::{InnerLocalCache, LocalCache, LocalCacheBuilder};
+
+impl<T, S: BuildHasher> InnerLocalCache<T, S> {
+    fn keys_with_prefix(&self, prefix: &str) -> Vec<String> {
+        match &self.prefix_index {
+            Some(index) => index
+                .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+                .take_while(|key| key.starts_with(prefix))
+                .map(|key| key.to_string())
+                .collect(),
+            None => self.map.keys().filter(|key| key.starts_with(prefix)).map(String::from).collect(),
+        }
+    }
+}
+
+impl<T, S> LocalCacheBuilder<T, S> {
+    /// Keeps an ordered index of the keys so that
+    /// [`invalidate_prefix`](LocalCache::invalidate_prefix) only visits
+    /// matching keys instead of scanning the whole cache. The index shares
+    /// the keys with the entries, at the cost of a tree node per key.
+    pub fn prefix_index(mut self) -> Self {
+        self.prefix_index = true;
+        self
+    }
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// Removes every entry whose key starts with `prefix`, here and in the
+    /// spill store (the backing store is left alone).
+    /// Returns the number of entries removed.
+    pub fn invalidate_prefix(&self, prefix: &str) -> usize {
+        let mut removed = 0;
+        for shard in self.shards.iter() {
+            let mut local_cache = shard.lock();
+            for key in local_cache.keys_with_prefix(prefix) {
+                #[cfg(feature = "std")]
+                if let Some(store) = &local_cache.store {
+                    store.remove(&key);
+                }
+                if local_cache.remove(&key).is_some() {
+                    removed += 1;
+                }
+            }
+        }
+        removed
+    }
+}
+
+#[test]
+fn test_invalidate_prefix() {
+    use std::sync::Arc;
+
+    for local_cache in [LocalCache::<usize>::new(16, 360), LocalCache::builder().prefix_index().shards(2).build()] {
+        for key in ["user:4:name", "user:42:name", "user:42:profile", "user:43:name", "user:420"] {
+            local_cache.put(key.to_string(), Arc::new(key.len()));
+        }
+        assert_eq!(2, local_cache.invalidate_prefix("user:42:"));
+        assert_eq!(None, local_cache.get("user:42:profile"));
+        assert!(local_cache.get("user:420").is_some());
+        assert!(local_cache.get("user:4:name").is_some());
+        assert_eq!(0, local_cache.invalidate_prefix("user:42:"));
+        let index = local_cache.shards[0].lock().prefix_index.as_ref().map(|index| index.len());
+        assert!(index.is_none_or(|len| len == local_cache.shards[0].lock().map.len()));
+    }
+}
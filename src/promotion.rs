@@ -0,0 +1,185 @@
+use alloc::boxed::Box;
+use core::hash::BuildHasher;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use crate::slab::NodeId;
+use crate::{InnerLocalCache, LocalCacheBuilder};
+
+// A fixed-size ring of the ids of hit nodes, pushed to by any number of
+// readers without the shard's lock and drained under it, like Caffeine's
+// read buffers. It's lossy: a hit that finds the ring full, or loses the
+// race for a slot, is dropped rather than waited on.
+pub(crate) struct HitRing {
+    // An id plus one, or 0 for a slot not written yet.
+    slots: Box<[AtomicU64]>,
+    // Slots claimed by pushes and drained, counting from the start.
+    tail: AtomicUsize,
+    head: AtomicUsize,
+}
+
+impl HitRing {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self { slots: (0..capacity).map(|_| AtomicU64::new(0)).collect(), tail: AtomicUsize::new(0), head: AtomicUsize::new(0) }
+    }
+
+    // Returns false if the ring is full, for the caller to drain it.
+    pub(crate) fn push(&self, id: NodeId) -> bool {
+        // The head never passes the tail, so load it first.
+        let head = self.head.load(Ordering::Acquire);
+        let tail = self.tail.load(Ordering::Relaxed);
+        if tail - head >= self.slots.len() {
+            return false;
+        }
+        if self.tail.compare_exchange(tail, tail + 1, Ordering::Relaxed, Ordering::Relaxed).is_ok() {
+            self.slots[tail % self.slots.len()].store(u64::from(id.to_bits()) + 1, Ordering::Release);
+        }
+        true
+    }
+
+    pub(crate) fn is_full(&self) -> bool {
+        let head = self.head.load(Ordering::Acquire);
+        self.tail.load(Ordering::Relaxed) - head >= self.slots.len()
+    }
+
+    // Takes the ids pushed so far, in order, up to the first slot claimed
+    // but not written yet. Only called under the shard's lock.
+    fn drain(&self, mut f: impl FnMut(NodeId)) {
+        let tail = self.tail.load(Ordering::Acquire);
+        let mut head = self.head.load(Ordering::Relaxed);
+        while head < tail {
+            let bits = self.slots[head % self.slots.len()].swap(0, Ordering::Acquire);
+            if bits == 0 {
+                break;
+            }
+            f(NodeId::from_bits((bits - 1) as u32));
+            head += 1;
+        }
+        self.head.store(head, Ordering::Release);
+    }
+
+    // A ring of the same size holding the ids not drained yet.
+    pub(crate) fn fork(&self) -> Self {
+        let forked = Self::new(self.slots.len());
+        let tail = self.tail.load(Ordering::Acquire);
+        for i in self.head.load(Ordering::Relaxed)..tail {
+            match self.slots[i % self.slots.len()].load(Ordering::Acquire) {
+                0 => break,
+                bits => forked.push(NodeId::from_bits((bits - 1) as u32)),
+            };
+        }
+        forked
+    }
+}
+
+impl<T, S: BuildHasher> InnerLocalCache<T, S> {
+    // Records a hit for the eviction policy, straight away or, with a
+    // promotion buffer, once the buffer fills or the shard is next written.
+    pub(crate) fn touch_buffered(&mut self, id: NodeId) {
+        let Some(promotions) = &self.promotions else {
+            return self.touch(id);
+        };
+        if !promotions.push(id) {
+            // Held up by a reader still writing its slot.
+            self.apply_promotions();
+            self.touch(id);
+        } else if promotions.is_full() {
+            self.apply_promotions();
+        }
+    }
+
+    // Makes the buffered hits, in the order they were recorded. Readers
+    // outside the lock may have pushed the id of a node removed since, so
+    // ids of vacant slots are skipped; one whose slot was reused gives the
+    // new node a stray hit, which only shifts its recency.
+    pub(crate) fn apply_promotions(&mut self) {
+        let Some(promotions) = self.promotions.clone() else {
+            return;
+        };
+        promotions.drain(|id| {
+            if self.map.node(id).is_some() {
+                self.touch(id);
+            }
+        });
+    }
+}
+
+impl<T, S> LocalCacheBuilder<T, S> {
+    /// Records up to `capacity` hits per shard in a fixed-size ring instead
+    /// of reordering the eviction lists on every `get`, and makes them in a
+    /// batch when the ring is full or before the shard's next insert or
+    /// removal, as Caffeine does. Shortens the time `get` holds the lock; in
+    /// return, [`iter_lru`](crate::LocalCache::iter_lru) and evictions not
+    /// made by an insert may see the order as of a few hits ago.
+    ///
+    /// The ring is pushed to without the lock, so hits served from the
+    /// copy kept by `read_mostly` are recorded in it too; one that finds it
+    /// full while the shard is locked is dropped.
+    pub fn promotion_buffer(mut self, capacity: usize) -> Self {
+        self.promotion_buffer = (capacity > 0).then_some(capacity);
+        self
+    }
+}
+
+#[test]
+fn test_promotion_buffer() {
+    use std::sync::Arc;
+
+    use crate::LocalCache;
+
+    let local_cache: LocalCache<usize> = LocalCache::builder().max_entries(3).promotion_buffer(2).build();
+    for i in 0..3 {
+        local_cache.put(i.to_string(), Arc::new(i));
+    }
+    let lru = || local_cache.iter_lru().map(|(key, _)| key).collect::<Vec<_>>();
+    local_cache.get("0");
+    assert_eq!(vec!["2", "1", "0"], lru());
+    // The buffer filled up.
+    local_cache.get("1");
+    assert_eq!(vec!["1", "0", "2"], lru());
+
+    // Buffered hits are made before an insert evicts.
+    local_cache.get("2");
+    local_cache.put(String::from("3"), Arc::new(3));
+    assert_eq!(vec!["3", "2", "1"], lru());
+    local_cache.get("1");
+    local_cache.remove("1");
+    assert_eq!(vec!["3", "2"], lru());
+}
+
+#[test]
+fn test_hit_ring() {
+    use std::collections::HashSet;
+    use std::sync::Mutex;
+    use std::thread;
+
+    let ring = HitRing::new(4);
+    assert!((0..4).all(|i| ring.push(NodeId::from_bits(i))));
+    assert!(ring.is_full() && !ring.push(NodeId::from_bits(4)));
+    let mut drained = Vec::new();
+    ring.drain(|id| drained.push(id.to_bits()));
+    assert_eq!(vec![0, 1, 2, 3], drained);
+
+    // Pushed from several threads at once, each id comes out at most once,
+    // in the order its thread pushed it.
+    let drained = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for t in 0..4 {
+            let (ring, drained) = (&ring, &drained);
+            scope.spawn(move || {
+                for i in 0..1000 {
+                    if !ring.push(NodeId::from_bits(t * 1000 + i)) {
+                        let mut drained = drained.lock().unwrap();
+                        ring.drain(|id| drained.push(id.to_bits()));
+                    }
+                }
+            });
+        }
+    });
+    let mut drained = drained.into_inner().unwrap();
+    ring.drain(|id| drained.push(id.to_bits()));
+    assert_eq!(drained.len(), drained.iter().collect::<HashSet<_>>().len());
+    for t in 0..4 {
+        let own: Vec<_> = drained.iter().filter(|&&id| id / 1000 == t).collect();
+        assert!(own.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
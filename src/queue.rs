@@ -0,0 +1,181 @@
+use alloc::sync::Arc;
+use core::hash::BuildHasher;
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::sync::mpsc::{self, Receiver, SyncSender};
+#[cfg(feature = "std")]
+use std::thread::{self, JoinHandle};
+
+#[cfg(feature = "std")]
+use crate::{LocalCacheBuilder, ShardLock};
+use crate::{ttl_ns, InnerLocalCache, Key, LocalCache, DEFAULT_SOURCE};
+
+// Inserts applied per lock acquisition.
+#[cfg(feature = "std")]
+const BATCH: usize = 64;
+
+#[cfg(feature = "std")]
+type Shards<T, S> = Arc<[ShardLock<InnerLocalCache<T, S>>]>;
+
+#[cfg(feature = "std")]
+pub(crate) enum Queued<T> {
+    Insert {
+        key: Key,
+        value: Option<Arc<T>>,
+        // `None` uses the default (or negative) TTL.
+        ttl_ns: Option<u128>,
+        source: &'static str,
+    },
+    Barrier(SyncSender<()>),
+}
+
+#[cfg(feature = "std")]
+// One bounded queue and applier thread per shard. Dropping it closes the
+// queues and waits for the threads to drain them.
+pub(crate) struct InsertQueues<T> {
+    senders: Vec<SyncSender<Queued<T>>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+#[cfg(feature = "std")]
+impl<T: Send + Sync + 'static> InsertQueues<T> {
+    pub(crate) fn spawn<S: BuildHasher + Send + 'static>(shards: &Shards<T, S>, capacity: usize) -> Self {
+        let (senders, workers) = (0..shards.len())
+            .map(|i| {
+                let (sender, receiver) = mpsc::sync_channel(capacity);
+                let shards = shards.clone();
+                let worker = thread::Builder::new()
+                    .name(format!("local-cache-insert-{}", i))
+                    .spawn(move || apply(&shards[i], receiver))
+                    .expect("failed to spawn insert queue thread");
+                (sender, worker)
+            })
+            .unzip();
+        Self { senders, workers }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Drop for InsertQueues<T> {
+    fn drop(&mut self) {
+        drop(std::mem::take(&mut self.senders));
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+fn apply<T, S: BuildHasher>(shard: &ShardLock<InnerLocalCache<T, S>>, receiver: Receiver<Queued<T>>) {
+    while let Ok(first) = receiver.recv() {
+        let mut local_cache = shard.lock();
+        let mut next = Some(first);
+        for _ in 0..BATCH {
+            match next.take().or_else(|| receiver.try_recv().ok()) {
+                Some(Queued::Insert { key, value, ttl_ns, source }) => local_cache.insert(key, value, ttl_ns, source),
+                Some(Queued::Barrier(done)) => {
+                    let _ = done.send(());
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+impl<T, S: BuildHasher> InnerLocalCache<T, S> {
+    pub(crate) fn insert(&mut self, key: Key, value: Option<Arc<T>>, ttl_ns: Option<u128>, source: &'static str) {
+        let tagged = (source != DEFAULT_SOURCE).then(|| key.clone());
+        match ttl_ns {
+            None => self.put(key, value),
+            Some(ttl) => match value {
+                Some(value) => self.write(key, value, ttl),
+                None => self.put_with_ttl(key, None, ttl),
+            },
+        }
+        if let Some(key) = tagged {
+            self.tag(&key, source);
+        }
+    }
+
+    // Like `insert`, but only fills memory, as `load_missing` does: nothing
+    // is written through to the backing store or published on the bus.
+    pub(crate) fn fill(&mut self, key: Key, value: Option<Arc<T>>, ttl_ns: u128, source: &'static str) {
+        let tagged = key.clone();
+        self.put_with_ttl(key, value, ttl_ns);
+        self.tag(&tagged, source);
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: Send + Sync + 'static, S: BuildHasher + Send + 'static> LocalCacheBuilder<T, S> {
+    /// Funnels `put`, `put_with_ttl` and `put_negative` through a bounded
+    /// queue per shard, applied in batches by that shard's own thread.
+    /// Inserts block while their queue holds `capacity` pending entries;
+    /// reads and removals stay direct and may not see queued inserts yet,
+    /// see [`LocalCache::wait_for_inserts`].
+    pub fn insert_queue(mut self, capacity: usize) -> Self {
+        self.insert_queue = Some((capacity, InsertQueues::spawn));
+        self
+    }
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    // Hands the insert to the shard's queue, or applies it directly when
+    // queues are off (or the shard's thread is gone).
+    pub(crate) fn enqueue(&self, key: Key, value: Option<Arc<T>>, ttl: Option<Duration>, source: &'static str) {
+        let ttl_ns = ttl.map(ttl_ns);
+        let index = self.shard_index(&key);
+        #[cfg(feature = "std")]
+        let (key, value, ttl_ns, source) = match &self.queues {
+            Some(queues) => match queues.senders[index].send(Queued::Insert { key, value, ttl_ns, source }) {
+                Ok(()) => return,
+                Err(mpsc::SendError(Queued::Insert { key, value, ttl_ns, source })) => (key, value, ttl_ns, source),
+                Err(mpsc::SendError(Queued::Barrier(_))) => return,
+            },
+            None => (key, value, ttl_ns, source),
+        };
+        let mut local_cache = self.shards[index].lock();
+        local_cache.insert(key, value, ttl_ns, source)
+    }
+
+    /// Blocks until every insert queued before this call has been applied.
+    /// Returns immediately without [`insert_queue`](LocalCacheBuilder::insert_queue).
+    #[cfg(feature = "std")]
+    pub fn wait_for_inserts(&self) {
+        let Some(queues) = &self.queues else {
+            return;
+        };
+        let pending: Vec<_> = queues
+            .senders
+            .iter()
+            .filter_map(|sender| {
+                let (done, wait) = mpsc::sync_channel(1);
+                sender.send(Queued::Barrier(done)).ok().map(|()| wait)
+            })
+            .collect();
+        for wait in pending {
+            let _ = wait.recv();
+        }
+    }
+}
+
+#[test]
+fn test_insert_queue() {
+    let local_cache: LocalCache<usize> = LocalCache::builder().max_entries(8192).shards(4).insert_queue(8).build();
+    thread::scope(|scope| {
+        for t in 0..4 {
+            let local_cache = &local_cache;
+            scope.spawn(move || {
+                for i in t * 1000..(t + 1) * 1000 {
+                    local_cache.put(i.to_string(), Arc::new(i));
+                }
+            });
+        }
+    });
+    local_cache.wait_for_inserts();
+    assert!((0..4000).all(|i| local_cache.get(&i.to_string()) == Some(Arc::new(i))));
+
+    local_cache.put_with_ttl(String::from("x"), Arc::new(1), Duration::from_millis(10));
+    local_cache.wait_for_inserts();
+    assert_eq!(Some(Arc::new(1)), local_cache.get("x"));
+}
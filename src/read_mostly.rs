@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+
+use crate::promotion::HitRing;
+use crate::slab::NodeId;
+use crate::{InnerLocalCache, LocalCache, LocalCacheBuilder, ShardLock, Slot};
+
+// Each hot entry's value, expiry time and node.
+type Entries<T> = HashMap<String, (Arc<T>, u128, NodeId)>;
+
+// An immutable copy of a shard's hot entries, which `get` reads without
+// taking the shard's lock.
+pub(crate) struct ReadSnapshot<T> {
+    entries: ArcSwap<Entries<T>>,
+    // Set by every write to the shard until the copy is rebuilt.
+    stale: AtomicBool,
+    // Hits served from the copy, for the stats.
+    pub(crate) hits: AtomicU64,
+    // The shard's, to record the hits in, see `promotion_buffer`.
+    promotions: Option<Arc<HitRing>>,
+}
+
+impl<T> ReadSnapshot<T> {
+    fn new(promotions: Option<Arc<HitRing>>) -> Self {
+        Self {
+            entries: ArcSwap::from_pointee(HashMap::new()),
+            stale: AtomicBool::new(true),
+            hits: AtomicU64::new(0),
+            promotions,
+        }
+    }
+
+    // Gives every shard a copy of its own, stale until first rebuilt.
+    pub(crate) fn attach<S>(shards: &[ShardLock<InnerLocalCache<T, S>>]) -> Vec<Arc<Self>> {
+        shards
+            .iter()
+            .map(|shard| {
+                let mut local_cache = shard.lock();
+                let snapshot = Arc::new(Self::new(local_cache.promotions.clone()));
+                local_cache.snapshot = Some(snapshot.clone());
+                local_cache.stale_reads = 0;
+                snapshot
+            })
+            .collect()
+    }
+
+    pub(crate) fn mark_stale(&self) {
+        self.stale.store(true, Ordering::Release);
+    }
+}
+
+impl<T, S: BuildHasher> InnerLocalCache<T, S> {
+    // Counts a locked read while the copy is stale and rebuilds it once there
+    // have been as many such reads as entries, so rebuilding costs at most
+    // one entry per locked read.
+    pub(crate) fn refresh_snapshot(&mut self) {
+        let Some(snapshot) = &self.snapshot else {
+            return;
+        };
+        if !snapshot.stale.load(Ordering::Relaxed) {
+            return;
+        }
+        self.stale_reads += 1;
+        if self.stale_reads < self.map.len() {
+            return;
+        }
+        let now = self.now();
+        let entries = self
+            .map
+            .iter()
+            .filter(|(_, entity)| now <= entity.exp)
+            .filter_map(|(id, entity)| match &entity.value {
+                Slot::Hot(value) => Some((entity.key.to_string(), (value.clone(), entity.exp, id))),
+                Slot::Cold(_) | Slot::Negative | Slot::Weak(_) => None,
+            })
+            .collect();
+        let snapshot = self.snapshot.as_ref().unwrap();
+        snapshot.entries.store(Arc::new(entries));
+        snapshot.stale.store(false, Ordering::Release);
+        self.stale_reads = 0;
+    }
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    // A live hit from the shard's read copy, if it is current. Everything
+    // else goes through the lock.
+    pub(crate) fn read_snapshot(&self, key: &str) -> Option<Arc<T>> {
+        let shard = self.shard_index(key);
+        let snapshot = &self.snapshots.as_ref()?[shard];
+        if snapshot.stale.load(Ordering::Acquire) {
+            return None;
+        }
+        let entries = snapshot.entries.load();
+        let (value, exp, id) = entries.get(key)?;
+        if self.clock.now_ns() > *exp {
+            return None;
+        }
+        snapshot.hits.fetch_add(1, Ordering::Relaxed);
+        if let Some(promotions) = &snapshot.promotions {
+            // Full: drained here if the shard is free, or the hit is dropped.
+            if !promotions.push(*id) {
+                if let Some(mut local_cache) = self.shards[shard].try_lock() {
+                    local_cache.apply_promotions();
+                }
+            }
+        }
+        Some(value.clone())
+    }
+}
+
+impl<T, S> LocalCacheBuilder<T, S> {
+    /// For read-heavy loads: `get` serves hits from a copy of each shard
+    /// swapped in atomically, without taking the shard's lock. Writes go
+    /// through the lock as usual and mark the copy stale; until it is
+    /// rebuilt, once the locked reads since make up for the shard's size,
+    /// reads take the lock too. Hits served from the copy count towards
+    /// [`stats`](LocalCache::stats) hits but not the minute windows, and
+    /// don't update the entry's hit count or cold promotion, nor check its
+    /// checksum, nor its recency without a
+    /// [`promotion_buffer`](Self::promotion_buffer). Has no effect with sliding expiration or
+    /// [`time_to_idle`](Self::time_to_idle), which need every read.
+    pub fn read_mostly(mut self) -> Self {
+        self.read_mostly = true;
+        self
+    }
+}
+
+#[test]
+fn test_read_mostly() {
+    let local_cache: LocalCache<usize> = LocalCache::builder().shards(2).read_mostly().build();
+    for i in 0..10 {
+        local_cache.put(i.to_string(), Arc::new(i));
+    }
+    // The first locked reads rebuild the copies.
+    for _ in 0..2 {
+        for i in 0..10 {
+            assert_eq!(Some(Arc::new(i)), local_cache.get(&i.to_string()));
+        }
+    }
+    let snapshot_hits = || local_cache.snapshots.as_ref().unwrap().iter().map(|s| s.hits.load(Ordering::Relaxed)).sum::<u64>();
+    let before = snapshot_hits();
+    assert!(before > 0);
+    assert_eq!(Some(Arc::new(3)), local_cache.get("3"));
+    assert_eq!(before + 1, snapshot_hits());
+    assert_eq!(20 + 1, local_cache.stats().hits);
+
+    local_cache.put(String::from("3"), Arc::new(30));
+    assert_eq!(Some(Arc::new(30)), local_cache.get("3"));
+    local_cache.remove("4");
+    assert_eq!(None, local_cache.get("4"));
+    assert_eq!(None, local_cache.get("x"));
+}
+
+#[test]
+fn test_read_mostly_promotions() {
+    let local_cache: LocalCache<usize> = LocalCache::builder().max_entries(3).read_mostly().promotion_buffer(8).build();
+    for i in 0..3 {
+        local_cache.put(i.to_string(), i);
+    }
+    for i in 0..3 {
+        assert_eq!(Some(Arc::new(i)), local_cache.get(&i.to_string()));
+    }
+    // Served from the copy, and still counted as the most recent hit.
+    assert_eq!(Some(Arc::new(0)), local_cache.get("0"));
+    assert_eq!(1, local_cache.snapshots.as_ref().unwrap()[0].hits.load(Ordering::Relaxed));
+    local_cache.put("3", 3);
+    assert_eq!(vec!["3", "0", "2"], local_cache.iter_lru().map(|(key, _)| key).collect::<Vec<_>>());
+}
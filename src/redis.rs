@@ -0,0 +1,257 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::{lock, BackingStore, ValueCodec};
+
+// Redis's own limit on a bulk string, `proto-max-bulk-len`.
+const DEFAULT_MAX_VALUE_LEN: usize = 512 << 20;
+
+// A reply to a command; arrays aren't asked for.
+#[derive(Debug, PartialEq, Eq)]
+enum Reply {
+    Status(String),
+    Integer(i64),
+    Bulk(Option<Vec<u8>>),
+}
+
+/// [`BackingStore`] over a Redis server, shared by every instance of a
+/// service as the second tier behind each one's in-process cache.
+///
+/// Values are stored encoded by `codec` under their key with `prefix` in
+/// front, and expire in Redis after the TTL they are cached for locally.
+/// Speaks RESP over one plain TCP connection, without TLS, reconnecting
+/// on the next call after a failure; failures are not reported, a lookup
+/// that fails is a miss. Calls are made under the shard lock, so each
+/// waits for a round trip, up to the timeout.
+pub struct RedisStore<C> {
+    addr: String,
+    codec: C,
+    prefix: String,
+    password: Option<String>,
+    timeout: Duration,
+    max_value_len: usize,
+    connection: Mutex<Option<BufReader<TcpStream>>>,
+}
+
+impl<C> RedisStore<C> {
+    /// A store for the server at `addr`, e.g. `"127.0.0.1:6379"`, not
+    /// connected yet, see [`connect`](Self::connect).
+    pub fn new(addr: impl Into<String>, codec: C) -> Self {
+        Self {
+            addr: addr.into(),
+            codec,
+            prefix: String::new(),
+            password: None,
+            timeout: Duration::from_secs(1),
+            max_value_len: DEFAULT_MAX_VALUE_LEN,
+            connection: Mutex::new(None),
+        }
+    }
+
+    /// Puts `prefix` in front of every key, e.g. `"sessions:"` to tell
+    /// caches sharing a server apart.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Authenticates with `AUTH password` on connecting.
+    pub fn password(mut self, password: impl Into<String>) -> Self {
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Gives up on a call after `timeout` instead of 1s.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Rejects replies of more than `max_value_len` bytes instead of
+    /// allocating for them, by default 512 MiB, the most Redis itself
+    /// accepts. A longer reply fails the call and drops the connection.
+    pub fn max_value_len(mut self, max_value_len: usize) -> Self {
+        self.max_value_len = max_value_len;
+        self
+    }
+
+    /// Connects and authenticates now, to find out about a wrong address
+    /// or password up front.
+    pub fn connect(self) -> io::Result<Self> {
+        *lock(&self.connection) = Some(self.open()?);
+        Ok(self)
+    }
+
+    fn open(&self) -> io::Result<BufReader<TcpStream>> {
+        let stream = TcpStream::connect(&self.addr)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+        stream.set_nodelay(true)?;
+        let mut connection = BufReader::new(stream);
+        if let Some(password) = &self.password {
+            send(&mut connection, &[b"AUTH", password.as_bytes()], self.max_value_len)?;
+        }
+        Ok(connection)
+    }
+
+    // Sends a command, connecting first if needed, and drops the connection
+    // if anything goes wrong.
+    fn command(&self, args: &[&[u8]]) -> io::Result<Reply> {
+        let mut connection = lock(&self.connection);
+        if connection.is_none() {
+            *connection = Some(self.open()?);
+        }
+        let reply = send(connection.as_mut().unwrap(), args, self.max_value_len);
+        if reply.is_err() {
+            *connection = None;
+        }
+        reply
+    }
+
+    fn key(&self, key: &str) -> Vec<u8> {
+        [self.prefix.as_bytes(), key.as_bytes()].concat()
+    }
+}
+
+fn send(connection: &mut BufReader<TcpStream>, args: &[&[u8]], max_len: usize) -> io::Result<Reply> {
+    let mut command = format!("*{}\r\n", args.len()).into_bytes();
+    for arg in args {
+        command.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        command.extend_from_slice(arg);
+        command.extend_from_slice(b"\r\n");
+    }
+    connection.get_mut().write_all(&command)?;
+    read_reply(connection, max_len)
+}
+
+fn read_reply(reader: &mut impl BufRead, max_len: usize) -> io::Result<Reply> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let line = line.strip_suffix("\r\n").ok_or_else(|| invalid("truncated reply"))?;
+    let (kind, rest) = line.split_at_checked(1).ok_or_else(|| invalid("empty reply"))?;
+    match kind {
+        "+" => Ok(Reply::Status(rest.to_string())),
+        "-" => Err(io::Error::other(rest.to_string())),
+        ":" => rest.parse().map(Reply::Integer).map_err(|_| invalid("bad integer")),
+        "$" => {
+            let Ok(len) = usize::try_from(rest.parse::<i64>().map_err(|_| invalid("bad length"))?) else {
+                return Ok(Reply::Bulk(None));
+            };
+            if len > max_len {
+                return Err(invalid("reply too long"));
+            }
+            let mut bytes = vec![0; len + 2];
+            reader.read_exact(&mut bytes)?;
+            bytes.truncate(len);
+            Ok(Reply::Bulk(Some(bytes)))
+        }
+        _ => Err(invalid("unexpected reply")),
+    }
+}
+
+impl<T, C: ValueCodec<T>> BackingStore<String, T> for RedisStore<C> {
+    fn load(&self, key: &String) -> Option<T> {
+        match self.command(&[b"GET", &self.key(key)]) {
+            Ok(Reply::Bulk(Some(bytes))) => self.codec.decode(&bytes),
+            _ => None,
+        }
+    }
+
+    fn store(&self, key: &String, value: &T) {
+        self.store_with_ttl(key, value, Duration::MAX)
+    }
+
+    fn delete(&self, key: &String) {
+        let _ = self.command(&[b"DEL", &self.key(key)]);
+    }
+
+    fn store_with_ttl(&self, key: &String, value: &T, ttl: Duration) {
+        let key = self.key(key);
+        let Some(value) = self.codec.encode(value) else {
+            let _ = self.command(&[b"DEL", &key]);
+            return;
+        };
+        let _ = if ttl >= Duration::from_secs(u64::MAX) {
+            self.command(&[b"SET", &key, &value])
+        } else {
+            // Redis takes whole milliseconds, and at least one.
+            let ms = ttl.as_millis().clamp(1, i64::MAX as u128).to_string();
+            self.command(&[b"SET", &key, &value, b"PX", ms.as_bytes()])
+        };
+    }
+}
+
+#[test]
+fn test_redis_store() {
+    use std::collections::HashMap;
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::thread;
+
+    use crate::testing::Utf8;
+    use crate::LocalCache;
+
+    // Just enough of a server: its keys with the PX they were set with.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    type Keys = HashMap<String, (Vec<u8>, Option<String>)>;
+    let keys: Arc<Mutex<Keys>> = Arc::default();
+    let server_keys = keys.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let mut reader = BufReader::new(stream.unwrap());
+            let keys = server_keys.clone();
+            thread::spawn(move || loop {
+                let mut line = String::new();
+                if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                    return;
+                }
+                let args: Vec<Vec<u8>> = (0..line[1..].trim().parse::<usize>().unwrap())
+                    .map(|_| match read_reply(&mut reader, usize::MAX).unwrap() {
+                        Reply::Bulk(Some(arg)) => arg,
+                        reply => panic!("{reply:?}"),
+                    })
+                    .collect();
+                let text = |i: usize| String::from_utf8(args[i].clone()).unwrap();
+                let reply = match &*text(0) {
+                    "AUTH" if text(1) == "secret" => "+OK\r\n".to_string(),
+                    "AUTH" => "-WRONGPASS\r\n".to_string(),
+                    "SET" => {
+                        lock(&keys).insert(text(1), (args[2].clone(), (args.len() == 5).then(|| text(4))));
+                        "+OK\r\n".to_string()
+                    }
+                    "GET" => match lock(&keys).get(&text(1)) {
+                        Some((value, _)) => format!("${}\r\n{}\r\n", value.len(), String::from_utf8_lossy(value)),
+                        None => "$-1\r\n".to_string(),
+                    },
+                    "DEL" => format!(":{}\r\n", lock(&keys).remove(&text(1)).is_some() as u8),
+                    _ => "-ERR unknown command\r\n".to_string(),
+                };
+                reader.get_mut().write_all(reply.as_bytes()).unwrap();
+            });
+        }
+    });
+
+    assert!(RedisStore::new(&*addr, Utf8).password("wrong").connect().is_err());
+    let store = RedisStore::new(&*addr, Utf8).password("secret").prefix("s:").connect().unwrap();
+    let local_cache: LocalCache<String> = LocalCache::builder().ttl(Duration::from_secs(60)).backing_store(store).build();
+    local_cache.put(String::from("a"), String::from("1"));
+    local_cache.put_with_ttl(String::from("b"), String::from("2"), Duration::from_millis(1500));
+    assert_eq!(Some(&(b"1".to_vec(), Some(String::from("60000")))), lock(&keys).get("s:a"));
+    assert_eq!(Some(&(b"2".to_vec(), Some(String::from("1500")))), lock(&keys).get("s:b"));
+
+    // Another instance reads what this one wrote.
+    let other: LocalCache<String> = LocalCache::builder().backing_store(RedisStore::new(&*addr, Utf8).prefix("s:")).build();
+    assert_eq!(Some(Arc::new(String::from("1"))), other.get("a"));
+    other.remove("a");
+    assert!(!lock(&keys).contains_key("s:a"));
+    assert_eq!(None, other.get("c"));
+
+    let mut reply: &[u8] = b"$9223372036854775807\r\n";
+    assert_eq!(io::ErrorKind::InvalidData, read_reply(&mut reply, DEFAULT_MAX_VALUE_LEN).unwrap_err().kind());
+    let mut reply: &[u8] = b"$3\r\nabc\r\n";
+    assert_eq!(Reply::Bulk(Some(b"abc".to_vec())), read_reply(&mut reply, 3).unwrap());
+}
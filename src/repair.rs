@@ -0,0 +1,197 @@
+use std::hash::BuildHasher;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crate::{CacheLoader, InnerLocalCache, LocalCacheBuilder, Lookup, ShardLock, Slot};
+
+// Sampled hits waiting for the repair thread; further samples are dropped.
+const REPAIR_QUEUE: usize = 256;
+
+type Shards<T, S> = Arc<[ShardLock<InnerLocalCache<T, S>>]>;
+type Loader<T> = Option<Arc<dyn CacheLoader<String, T>>>;
+
+/// Sampled read-repair, see [`LocalCacheBuilder::read_repair`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReadRepair {
+    every: u64,
+    update: bool,
+}
+
+impl ReadRepair {
+    /// Checks a `fraction` of hits, rounded to one in every N hits of a shard.
+    pub fn new(fraction: f64) -> Self {
+        let every = if fraction > 0.0 { (1.0 / fraction).round().max(1.0) as u64 } else { u64::MAX };
+        Self { every, update: false }
+    }
+
+    /// Also overwrites diverged entries with the value re-fetched, or
+    /// removes them if there no longer is one.
+    pub fn update_on_divergence(mut self) -> Self {
+        self.update = true;
+        self
+    }
+}
+
+// Per-shard sampling state and the handle to the repair thread.
+pub(crate) struct Sampler<T> {
+    every: u64,
+    hits: u64,
+    shard: usize,
+    sender: SyncSender<(usize, String, Arc<T>)>,
+}
+
+impl<T, S: BuildHasher> InnerLocalCache<T, S> {
+    pub(crate) fn sample_hit(&mut self, key: &str, lookup: &Lookup<T>) {
+        let (Some(sampler), Lookup::Hit(value)) = (&mut self.repair, lookup) else {
+            return;
+        };
+        sampler.hits += 1;
+        if sampler.hits % sampler.every == 0 {
+            let _ = sampler.sender.try_send((sampler.shard, key.to_string(), value.clone()));
+        }
+    }
+}
+
+// The repair thread. Dropping it detaches the shards from it and waits for
+// it to finish the checks already queued.
+pub(crate) struct RepairWorker<T, S> {
+    shards: Shards<T, S>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl<T: PartialEq + Send + Sync + 'static, S: BuildHasher + Send + 'static> RepairWorker<T, S> {
+    pub(crate) fn spawn(shards: &Shards<T, S>, loader: Loader<T>, repair: ReadRepair) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(REPAIR_QUEUE);
+        for (shard, local_cache) in shards.iter().enumerate() {
+            local_cache.lock().repair = Some(Sampler { every: repair.every, hits: 0, shard, sender: sender.clone() });
+        }
+        let worker_shards = shards.clone();
+        let worker = thread::Builder::new()
+            .name("local-cache-repair".to_string())
+            .spawn(move || check(&worker_shards, loader, receiver, repair.update))
+            .expect("failed to spawn read repair thread");
+        Self { shards: shards.clone(), worker: Some(worker) }
+    }
+}
+
+impl<T, S> Drop for RepairWorker<T, S> {
+    fn drop(&mut self) {
+        for shard in self.shards.iter() {
+            shard.lock().repair = None;
+        }
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn check<T: PartialEq, S: BuildHasher>(
+    shards: &[ShardLock<InnerLocalCache<T, S>>],
+    loader: Loader<T>,
+    receiver: Receiver<(usize, String, Arc<T>)>,
+    update: bool,
+) {
+    for (shard, key, cached) in receiver {
+        // Loaded without the lock, like any other loader or backing store call.
+        let fresh = match &loader {
+            Some(loader) => loader.load(&key),
+            None => {
+                let Some(backing) = shards[shard].lock().backing.clone() else {
+                    continue;
+                };
+                backing.load(&key)
+            }
+        };
+        let diverged = fresh.as_ref() != Some(&*cached);
+        let mut local_cache = shards[shard].lock();
+        local_cache.counters.repair_checks += 1;
+        if !diverged {
+            continue;
+        }
+        local_cache.counters.repair_divergences += 1;
+        // Only repair the entry if it still holds the value that was checked.
+        let Some(id) = local_cache.map.find(&key) else {
+            continue;
+        };
+        if !update || !matches!(&local_cache.map[id].value, Slot::Hot(value) if Arc::ptr_eq(value, &cached)) {
+            continue;
+        }
+        match fresh {
+            Some(fresh) => {
+                let checksum = local_cache.checksum.map_or(0, |checksum_of| checksum_of(&fresh));
+                let entity = &mut local_cache.map[id];
+                entity.checksum = checksum;
+                entity.value = Slot::Hot(Arc::new(fresh));
+                local_cache.recount(id);
+                local_cache.changed();
+            }
+            None => {
+                local_cache.remove(&key);
+            }
+        }
+    }
+}
+
+impl<T: PartialEq + Send + Sync + 'static, S: BuildHasher + Send + 'static> LocalCacheBuilder<T, S> {
+    /// On a sample of hits, re-fetches the value on a background thread and
+    /// compares it with the cached one. Values come from the
+    /// [`loader`](Self::loader), or without one from the
+    /// [`backing_store`](Self::backing_store); with neither this does
+    /// nothing. Checks and divergences are counted in
+    /// [`stats`](crate::LocalCache::stats).
+    pub fn read_repair(mut self, repair: ReadRepair) -> Self {
+        self.read_repair = Some((repair, RepairWorker::spawn));
+        self
+    }
+}
+
+#[test]
+fn test_read_repair() {
+    use std::time::Duration;
+
+    use crate::testing::Db;
+    use crate::LocalCache;
+
+    let db = Db::default();
+    let rows = db.0.clone();
+    let local_cache: LocalCache<usize> = LocalCache::builder()
+        .backing_store(db)
+        .read_repair(ReadRepair::new(0.5).update_on_divergence())
+        .build();
+    local_cache.put(String::from("x"), Arc::new(1));
+    rows.lock().unwrap().insert(String::from("x"), 2);
+
+    let wait_for_checks = |checks: u64| {
+        while local_cache.stats().repair_checks < checks {
+            thread::sleep(Duration::from_millis(1));
+        }
+    };
+    assert_eq!(Some(Arc::new(1)), local_cache.get("x"));
+    assert_eq!(Some(Arc::new(1)), local_cache.get("x"));
+    wait_for_checks(1);
+    assert_eq!(1, local_cache.stats().repair_divergences);
+    assert_eq!(Some(Arc::new(2)), local_cache.get("x"));
+    assert_eq!(Some(Arc::new(2)), local_cache.get("x"));
+    wait_for_checks(2);
+    assert_eq!(1, local_cache.stats().repair_divergences);
+
+    // The loader is asked rather than the backing store.
+    struct Double;
+    impl CacheLoader<String, usize> for Double {
+        fn load(&self, key: &String) -> Option<usize> {
+            key.parse().ok().map(|n: usize| 2 * n)
+        }
+    }
+    let local_cache: LocalCache<usize> = LocalCache::builder()
+        .backing_store(Db::default())
+        .loader(Double)
+        .read_repair(ReadRepair::new(1.0).update_on_divergence())
+        .build();
+    local_cache.put(String::from("3"), Arc::new(3));
+    assert_eq!(Some(Arc::new(3)), local_cache.get("3"));
+    while local_cache.stats().repair_checks < 1 {
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(Some(Arc::new(6)), local_cache.get("3"));
+}
@@ -0,0 +1,95 @@
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::hash::BuildHasher;
+
+#[cfg(feature = "std")]
+use crate::CacheEvent;
+use crate::{CacheEntity, InnerLocalCache, LocalCache, Slot};
+
+/// Why an entry left the cache, see [`LocalCache::put_with_report`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionReason {
+    /// Pushed out to make room.
+    Capacity,
+    /// Cleaned up after its TTL ran out.
+    Expired,
+}
+
+/// An entry removed by the cache itself. `value` is `None` for negative
+/// entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Evicted<T> {
+    pub key: String,
+    pub value: Option<Arc<T>>,
+    pub reason: EvictionReason,
+    /// See [`LocalCache::put_with_source`].
+    pub source: &'static str,
+}
+
+impl<T, S: BuildHasher> InnerLocalCache<T, S> {
+    // Records a removed entry while a report is being collected, and hands
+    // expired ones to the `on_expire` hook.
+    pub(crate) fn report(&mut self, entity: CacheEntity<T>, reason: EvictionReason) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(key = self.traced_key(&entity.key), ?reason, source = entity.source, "evict");
+        #[cfg(feature = "std")]
+        self.emit(|| match reason {
+            EvictionReason::Capacity => CacheEvent::Evict { key: entity.key.to_string() },
+            EvictionReason::Expired => CacheEvent::Expire { key: entity.key.to_string() },
+        });
+        #[cfg(feature = "std")]
+        let on_expire = self.on_expire.as_ref().filter(|_| reason == EvictionReason::Expired);
+        #[cfg(not(feature = "std"))]
+        let on_expire = None::<()>;
+        if self.evicted.is_none() && on_expire.is_none() {
+            return;
+        }
+        let CacheEntity { key, value, source, .. } = entity;
+        let value = match value {
+            Slot::Hot(value) => Some(value),
+            Slot::Cold(bytes) => self.cold.as_ref().and_then(|cold| cold.codec.decode(&bytes)).map(Arc::new),
+            Slot::Negative => None,
+            Slot::Weak(value) => value.upgrade(),
+        };
+        #[cfg(feature = "std")]
+        if let (Some(on_expire), Some(value)) = (on_expire, &value) {
+            // The hook's thread only stops once every shard is gone.
+            let _ = on_expire.send((key.to_string(), value.clone()));
+        }
+        if let Some(evicted) = &mut self.evicted {
+            evicted.push(Evicted { key: key.to_string(), value, reason, source });
+        }
+    }
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// Like [`put`](Self::put), returning the entries evicted to make room
+    /// for it (spilled to the store, if there is one) or cleaned up as
+    /// expired along the way. Always applied directly, even with an insert queue.
+    pub fn put_with_report(&self, key: String, value: impl Into<Arc<T>>) -> Vec<Evicted<T>> {
+        let value = value.into();
+        let mut local_cache = self.lock(&key);
+        local_cache.evicted = Some(Vec::new());
+        local_cache.put(key.into(), Some(value));
+        local_cache.evicted.take().unwrap_or_default()
+    }
+}
+
+#[test]
+fn test_put_with_report() {
+    use std::time::Duration;
+
+    let local_cache: LocalCache<usize> = LocalCache::new(2, 360);
+    local_cache.put_with_ttl(String::from("a"), Arc::new(0), Duration::from_millis(5));
+    local_cache.put(String::from("b"), Arc::new(1));
+    std::thread::sleep(Duration::from_millis(10));
+    let evicted = local_cache.put_with_report(String::from("c"), Arc::new(2));
+    assert_eq!(1, evicted.len());
+    assert_eq!(("a", Some(Arc::new(0)), EvictionReason::Expired), (&*evicted[0].key, evicted[0].value.clone(), evicted[0].reason));
+
+    let evicted = local_cache.put_with_report(String::from("d"), Arc::new(3));
+    let b = Evicted { key: String::from("b"), value: Some(Arc::new(1)), reason: EvictionReason::Capacity, source: "put" };
+    assert_eq!(vec![b], evicted);
+    assert!(local_cache.put_with_report(String::from("d"), Arc::new(4)).is_empty());
+}
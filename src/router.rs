@@ -0,0 +1,52 @@
+use core::hash::BuildHasher;
+
+use crate::DefaultHashBuilder;
+
+/// Picks the shard a key lives on, see
+/// [`LocalCacheBuilder::shard_router`](crate::LocalCacheBuilder::shard_router).
+/// Routing related keys (e.g. one tenant's) to the same shard keeps
+/// operations over them to a single lock.
+pub trait ShardRouter: Send + Sync {
+    /// Returns the shard for `key`, in `0..shards`; larger values wrap.
+    /// Must always return the same shard for the same key.
+    fn shard(&self, key: &str, shards: usize) -> usize;
+}
+
+/// Routes by a hash of the whole key, spreading keys evenly.
+#[derive(Default)]
+pub struct HashRouter {
+    hasher: DefaultHashBuilder,
+}
+
+impl ShardRouter for HashRouter {
+    fn shard(&self, key: &str, shards: usize) -> usize {
+        (BuildHasher::hash_one(&self.hasher, key) % shards as u64) as usize
+    }
+}
+
+impl<F: Fn(&str, usize) -> usize + Send + Sync> ShardRouter for F {
+    fn shard(&self, key: &str, shards: usize) -> usize {
+        self(key, shards)
+    }
+}
+
+#[test]
+fn test_shard_router() {
+    use std::sync::Arc;
+
+    use crate::LocalCache;
+
+    // Keys are "<tenant>:<id>"; each tenant gets its own shard.
+    let local_cache: LocalCache<usize> = LocalCache::builder()
+        .shards(4)
+        .shard_router(|key: &str, _: usize| key.split(':').next().unwrap().parse().unwrap())
+        .build();
+    for tenant in 0..4 {
+        for id in 0..8 {
+            local_cache.put(format!("{}:{}", tenant, id), Arc::new(id));
+        }
+    }
+    assert!(local_cache.shards.iter().all(|shard| shard.lock().map.len() == 8));
+    assert_eq!(Some(Arc::new(5)), local_cache.get("2:5"));
+    assert!(local_cache.shards[2].lock().map.get("2:5").is_some());
+}
@@ -0,0 +1,83 @@
+use core::hash::BuildHasher;
+use core::sync::atomic::Ordering;
+use core::time::Duration;
+
+use crate::{share, ttl_ns, EvictionPolicy, InnerLocalCache, LocalCache};
+
+impl<T, S: BuildHasher> InnerLocalCache<T, S> {
+    fn resize(&mut self, max_numbers: usize) {
+        self.max_numbers = max_numbers;
+        self.window_max = (max_numbers / 100).max(1);
+        if let EvictionPolicy::Slru { protected_percent } = self.policy {
+            self.protected_max = max_numbers * protected_percent.min(100) as usize / 100;
+        }
+        if self.hot_len() > self.max_numbers {
+            let now = self.now();
+            self.evict_expired(now, usize::MAX);
+        }
+        self.trim();
+    }
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// Changes the capacity, split between shards as at build time: evenly
+    /// unless built with
+    /// [`shard_weights`](crate::LocalCacheBuilder::shard_weights). Shrinking
+    /// evicts expired entries, then least recently used ones, down to the
+    /// new limit straight away.
+    pub fn set_max_entries(&self, max_numbers: usize) {
+        self.max_numbers.store(max_numbers, Ordering::Relaxed);
+        for (i, shard) in self.shards.iter().enumerate() {
+            shard.lock().resize(share(max_numbers, self.shards.len(), self.shard_weights.as_deref(), i));
+        }
+    }
+
+    /// Changes the TTL given to entries inserted from now on without one of
+    /// their own. Entries already cached keep their expiry, and the negative
+    /// TTL stays as built.
+    pub fn set_default_ttl(&self, ttl: Duration) {
+        for shard in self.shards.iter() {
+            shard.lock().max_age_ns = ttl_ns(ttl);
+        }
+    }
+}
+
+#[test]
+fn test_set_max_entries() {
+    use std::sync::Arc;
+
+    let local_cache: LocalCache<usize> = LocalCache::new(8, 360);
+    for i in 0..8 {
+        local_cache.put(i.to_string(), Arc::new(i));
+    }
+    local_cache.set_max_entries(3);
+    assert_eq!(3, local_cache.stats().entries);
+    assert_eq!(None, local_cache.get("4"));
+    assert_eq!(Some(Arc::new(5)), local_cache.get("5"));
+
+    local_cache.set_max_entries(4);
+    local_cache.put(String::from("x"), Arc::new(8));
+    assert_eq!(4, local_cache.stats().entries);
+
+    // The weights survive shrinking to less than one entry a shard.
+    let weighted: LocalCache<usize> = LocalCache::builder().max_entries(10).shard_weights(&[7, 1, 2]).build();
+    let capacities = |local_cache: &LocalCache<usize>| local_cache.shards.iter().map(|shard| shard.lock().max_numbers).collect::<Vec<_>>();
+    weighted.set_max_entries(1);
+    assert_eq!(vec![0, 0, 1], capacities(&weighted));
+    weighted.set_max_entries(100);
+    assert_eq!(vec![70, 10, 20], capacities(&weighted));
+}
+
+#[test]
+fn test_set_default_ttl() {
+    use std::sync::Arc;
+    use std::thread;
+
+    let local_cache: LocalCache<usize> = LocalCache::new(8, 360);
+    local_cache.put(String::from("old"), Arc::new(0));
+    local_cache.set_default_ttl(Duration::from_millis(20));
+    local_cache.put(String::from("new"), Arc::new(1));
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(None, local_cache.get("new"));
+    assert_eq!(Some(Arc::new(0)), local_cache.get("old"));
+}
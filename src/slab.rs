@@ -0,0 +1,200 @@
+use alloc::vec::Vec;
+use core::mem;
+use core::ops::{Index, IndexMut};
+
+use crate::{CacheError, HashMap};
+
+// A node's place in its shard's `Slab`, which the lists, the timer wheel and
+// the indexes link by instead of by pointer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct NodeId(u32);
+
+impl NodeId {
+    // For storing ids in atomics.
+    pub(crate) fn to_bits(self) -> u32 {
+        self.0
+    }
+
+    pub(crate) fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+}
+
+enum Entry<E> {
+    Occupied(E),
+    // The next vacant slot.
+    Vacant(Option<NodeId>),
+}
+
+// A shard's nodes, in one `Vec`. Removed nodes leave their slot on a free
+// list, threaded through the vacant slots themselves, to be reused before
+// the `Vec` grows; an id stays valid until its node is removed.
+pub(crate) struct Slab<E> {
+    entries: Vec<Entry<E>>,
+    free: Option<NodeId>,
+    len: usize,
+}
+
+// Set by tests to fail the inserts made on their thread as if memory had
+// run out.
+#[cfg(test)]
+thread_local! {
+    pub(crate) static FAIL_INSERTS: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+impl<E> Slab<E> {
+    pub(crate) fn new() -> Self {
+        Self { entries: Vec::new(), free: None, len: 0 }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    // Slots allocated, occupied or not.
+    pub(crate) fn capacity(&self) -> usize {
+        self.entries.capacity()
+    }
+
+    // Reports allocation failure instead of aborting.
+    pub(crate) fn try_insert(&mut self, node: E) -> Result<NodeId, CacheError> {
+        #[cfg(test)]
+        if FAIL_INSERTS.get() {
+            return Err(CacheError::AllocFailed);
+        }
+        if let Some(id) = self.free {
+            let Entry::Vacant(next) = mem::replace(&mut self.entries[id.0 as usize], Entry::Occupied(node)) else {
+                unreachable!("free list points to an occupied slot");
+            };
+            self.free = next;
+            self.len += 1;
+            return Ok(id);
+        }
+        let id = NodeId(u32::try_from(self.entries.len()).map_err(|_| CacheError::AllocFailed)?);
+        self.entries.try_reserve(1).map_err(|_| CacheError::AllocFailed)?;
+        self.entries.push(Entry::Occupied(node));
+        self.len += 1;
+        Ok(id)
+    }
+
+    pub(crate) fn remove(&mut self, id: NodeId) -> E {
+        match mem::replace(&mut self.entries[id.0 as usize], Entry::Vacant(self.free)) {
+            Entry::Occupied(node) => {
+                self.free = Some(id);
+                self.len -= 1;
+                node
+            }
+            Entry::Vacant(_) => panic!("node {} removed twice", id.0),
+        }
+    }
+
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        let vacant = self.entries.len() - self.len;
+        self.entries.reserve(additional.saturating_sub(vacant));
+    }
+
+    pub(crate) fn try_reserve(&mut self, additional: usize) -> Result<(), CacheError> {
+        let vacant = self.entries.len() - self.len;
+        self.entries.try_reserve(additional.saturating_sub(vacant)).map_err(|_| CacheError::AllocFailed)
+    }
+
+    // Moves the last nodes into the vacant slots before them, so no slot
+    // is left vacant, and frees the spare room. Returns the old and new id
+    // of each node moved, for the links to it to be updated.
+    pub(crate) fn compact(&mut self) -> HashMap<NodeId, NodeId> {
+        let mut moved = HashMap::new();
+        let mut vacant = 0;
+        while self.len < self.entries.len() {
+            match self.entries.pop() {
+                Some(Entry::Occupied(node)) => {
+                    while matches!(self.entries[vacant], Entry::Occupied(_)) {
+                        vacant += 1;
+                    }
+                    self.entries[vacant] = Entry::Occupied(node);
+                    moved.insert(NodeId(self.entries.len() as u32), NodeId(vacant as u32));
+                }
+                Some(Entry::Vacant(_)) | None => {}
+            }
+        }
+        self.free = None;
+        self.entries.shrink_to_fit();
+        moved
+    }
+
+    // Like indexing, but `None` for a vacant or out of range slot.
+    pub(crate) fn get(&self, id: NodeId) -> Option<&E> {
+        match self.entries.get(id.0 as usize)? {
+            Entry::Occupied(node) => Some(node),
+            Entry::Vacant(_) => None,
+        }
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (NodeId, &E)> {
+        self.entries.iter().enumerate().filter_map(|(i, entry)| match entry {
+            Entry::Occupied(node) => Some((NodeId(i as u32), node)),
+            Entry::Vacant(_) => None,
+        })
+    }
+
+    pub(crate) fn values_mut(&mut self) -> impl Iterator<Item = &mut E> {
+        self.entries.iter_mut().filter_map(|entry| match entry {
+            Entry::Occupied(node) => Some(node),
+            Entry::Vacant(_) => None,
+        })
+    }
+
+    // The same slab over copies of the nodes, under the same ids.
+    pub(crate) fn fork(&self, copy: impl Fn(&E) -> E) -> Self {
+        let entries = self
+            .entries
+            .iter()
+            .map(|entry| match entry {
+                Entry::Occupied(node) => Entry::Occupied(copy(node)),
+                &Entry::Vacant(next) => Entry::Vacant(next),
+            })
+            .collect();
+        Self { entries, free: self.free, len: self.len }
+    }
+}
+
+impl<E> Index<NodeId> for Slab<E> {
+    type Output = E;
+
+    fn index(&self, id: NodeId) -> &E {
+        match &self.entries[id.0 as usize] {
+            Entry::Occupied(node) => node,
+            Entry::Vacant(_) => panic!("node {} was removed", id.0),
+        }
+    }
+}
+
+impl<E> IndexMut<NodeId> for Slab<E> {
+    fn index_mut(&mut self, id: NodeId) -> &mut E {
+        match &mut self.entries[id.0 as usize] {
+            Entry::Occupied(node) => node,
+            Entry::Vacant(_) => panic!("node {} was removed", id.0),
+        }
+    }
+}
+
+#[test]
+fn test_slab() {
+    let mut slab = Slab::new();
+    let ids: Vec<_> = (0..4).map(|i| slab.try_insert(i).unwrap()).collect();
+    assert_eq!(1, slab.remove(ids[1]));
+    assert_eq!(3, slab.remove(ids[3]));
+    // Freed slots are reused, the last freed first.
+    assert_eq!(ids[3], slab.try_insert(30).unwrap());
+    assert_eq!(vec![(ids[0], &0), (ids[2], &2), (ids[3], &30)], slab.iter().collect::<Vec<_>>());
+    slab[ids[2]] += 20;
+    assert_eq!((Some(&22), None), (slab.get(ids[2]), slab.get(ids[1])));
+    assert_eq!(22, slab.remove(ids[2]));
+    assert_eq!(30, slab.remove(ids[3]));
+
+    assert_eq!(3, slab.try_insert(3).unwrap().0);
+    let moved = slab.compact();
+    // 3 took the place of 1.
+    assert_eq!(HashMap::from([(NodeId(3), ids[1])]), moved);
+    assert_eq!((2, 2), (slab.len(), slab.capacity()));
+    assert_eq!(vec![&0, &3], slab.fork(|&i| i).iter().map(|(_, i)| i).collect::<Vec<_>>());
+}
@@ -0,0 +1,196 @@
+use std::fs::File;
+use std::hash::BuildHasher;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{LocalCache, Slot, NEVER};
+
+// Entries are written coldest first, so replaying them through `put`
+// restores the LRU order. `value: None` is a negative entry.
+#[derive(Serialize)]
+struct SnapshotRef<'a, T> {
+    entries: Vec<SnapshotEntry<&'a str, SnapshotValue<'a, T>>>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum SnapshotValue<'a, T> {
+    Hot(&'a T),
+    Decoded(T),
+    Shared(Arc<T>),
+}
+
+#[derive(Deserialize)]
+struct SnapshotOwned<T> {
+    entries: Vec<SnapshotEntry<String, T>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotEntry<K, V> {
+    key: K,
+    value: Option<V>,
+    // `u64::MAX` for entries that never expire.
+    remaining_ns: u64,
+}
+
+#[derive(Serialize)]
+struct CacheRef<'a, 'b, T> {
+    max_entries: usize,
+    ttl_ns: u64,
+    shards: usize,
+    #[serde(flatten)]
+    snapshot: &'b SnapshotRef<'a, T>,
+}
+
+#[derive(Deserialize)]
+struct CacheOwned<T> {
+    max_entries: usize,
+    ttl_ns: u64,
+    shards: usize,
+    #[serde(flatten)]
+    snapshot: SnapshotOwned<T>,
+}
+
+impl<T: Serialize, S: BuildHasher> LocalCache<T, S> {
+    // Calls `f` with all live entries while holding every shard's lock.
+    fn with_snapshot<R>(&self, f: impl FnOnce(&SnapshotRef<T>) -> R) -> R {
+        let shards: Vec<_> = self.shards.iter().map(|shard| shard.lock()).collect();
+        let now = self.clock.now_ns();
+        let mut snapshot = SnapshotRef { entries: Vec::with_capacity(shards.iter().map(|s| s.map.len()).sum()) };
+        for (local_cache, tail) in shards.iter().flat_map(|s| s.lru_tails().map(|tail| (s, tail))) {
+            let mut cur = tail;
+            while let Some(e) = cur {
+                let b = &local_cache.map[e];
+                cur = b.lru_prev;
+                if b.exp <= now {
+                    continue;
+                }
+                let value = match &b.value {
+                    Slot::Hot(value) => Some(SnapshotValue::Hot(&**value)),
+                    Slot::Weak(value) => match value.upgrade() {
+                        Some(value) => Some(SnapshotValue::Shared(value)),
+                        None => continue,
+                    },
+                    Slot::Negative => None,
+                    Slot::Cold(bytes) => match local_cache.cold.as_ref().and_then(|cold| cold.codec.decode(bytes)) {
+                        Some(value) => Some(SnapshotValue::Decoded(value)),
+                        None => continue,
+                    },
+                };
+                snapshot.entries.push(SnapshotEntry {
+                    key: &b.key,
+                    value,
+                    remaining_ns: u64::try_from(b.exp - now).unwrap_or(u64::MAX),
+                });
+            }
+        }
+        f(&snapshot)
+    }
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    fn restore(&self, snapshot: SnapshotOwned<T>) -> usize {
+        let loaded = snapshot.entries.len();
+        for entry in snapshot.entries {
+            let mut local_cache = self.lock(&entry.key);
+            let ttl_ns = if entry.remaining_ns == u64::MAX { NEVER } else { entry.remaining_ns as u128 };
+            local_cache.fill(entry.key.into(), entry.value.map(Arc::new), ttl_ns, "import")
+        }
+        loaded
+    }
+}
+
+impl<T: Serialize + DeserializeOwned, S: BuildHasher> LocalCache<T, S> {
+    /// Writes all live entries and their remaining TTLs to `path` as JSON.
+    /// Returns the number of entries written.
+    pub fn save_snapshot<P: AsRef<Path>>(&self, path: P) -> io::Result<usize> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let written = self.with_snapshot(|snapshot| {
+            serde_json::to_writer(&mut writer, snapshot).map(|()| snapshot.entries.len())
+        })?;
+        writer.flush()?;
+        Ok(written)
+    }
+
+    /// Inserts the entries saved by [`save_snapshot`](Self::save_snapshot),
+    /// each keeping the TTL it had left when saved.
+    /// Returns the number of entries loaded.
+    pub fn load_snapshot<P: AsRef<Path>>(&self, path: P) -> io::Result<usize> {
+        let snapshot: SnapshotOwned<T> = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        Ok(self.restore(snapshot))
+    }
+}
+
+/// The capacity, default TTL, shard count and live entries, in the same
+/// form as [`save_snapshot`](LocalCache::save_snapshot).
+impl<T: Serialize, S: BuildHasher> Serialize for LocalCache<T, S> {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        let max_entries = self.max_numbers.load(Ordering::Relaxed);
+        let ttl_ns = u64::try_from(self.shards[0].lock().max_age_ns).unwrap_or(u64::MAX);
+        let shards = self.shards.len();
+        self.with_snapshot(|snapshot| CacheRef { max_entries, ttl_ns, shards, snapshot }.serialize(serializer))
+    }
+}
+
+/// A cache built with the serialized capacity, default TTL and shard count
+/// and otherwise default settings, holding the serialized entries.
+impl<'de, T: DeserializeOwned, S: BuildHasher + Clone + Default> Deserialize<'de> for LocalCache<T, S> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let cache = CacheOwned::<T>::deserialize(deserializer)?;
+        let local_cache = LocalCache::builder_with_hasher(S::default())
+            .max_entries(cache.max_entries)
+            .ttl((cache.ttl_ns != u64::MAX).then(|| Duration::from_nanos(cache.ttl_ns)))
+            .shards(cache.shards)
+            .build();
+        local_cache.restore(cache.snapshot);
+        Ok(local_cache)
+    }
+}
+
+#[test]
+fn test_snapshot() {
+    let local_cache: LocalCache<String> = LocalCache::new(4, 360);
+    local_cache.put(String::from("x"), Arc::new(String::from("abc")));
+    local_cache.put_negative(String::from("y"));
+    local_cache.put(String::from("z"), Arc::new(String::from("123")));
+    local_cache.get("x");
+
+    let path = std::env::temp_dir().join(format!("local-cache-snapshot-{}", std::process::id()));
+    assert_eq!(3, local_cache.save_snapshot(&path).unwrap());
+
+    let restored: LocalCache<String> = LocalCache::new(2, 360);
+    assert_eq!(3, restored.load_snapshot(&path).unwrap());
+    let _ = std::fs::remove_file(&path);
+
+    // Capacity 2 keeps the two most recently used entries.
+    assert_eq!(crate::Lookup::Miss, restored.lookup("y"));
+    assert_eq!(Some(Arc::new(String::from("abc"))), restored.get("x"));
+    assert_eq!(Some(Arc::new(String::from("123"))), restored.get("z"));
+
+    let local_cache = restored.shards[0].lock();
+    let now = local_cache.now();
+    let remaining = local_cache.map.get("x").map(|e| e.exp - now).unwrap();
+    assert!(remaining <= Duration::from_secs(360).as_nanos());
+}
+
+#[test]
+fn test_serde() {
+    let local_cache: LocalCache<String> = LocalCache::builder().max_entries(10).shards(4).build();
+    local_cache.put(String::from("x"), Arc::new(String::from("abc")));
+    local_cache.put_negative(String::from("y"));
+
+    let json = serde_json::to_string(&local_cache).unwrap();
+    let restored: LocalCache<String> = serde_json::from_str(&json).unwrap();
+    // Round trips keep the capacity as configured.
+    let restored: LocalCache<String> = serde_json::from_str(&serde_json::to_string(&restored).unwrap()).unwrap();
+    assert_eq!(10, restored.stats().max_entries);
+    assert_eq!(4, restored.shards.len());
+    assert_eq!(Some(Arc::new(String::from("abc"))), restored.get("x"));
+    assert_eq!(crate::Lookup::Negative, restored.lookup("y"));
+}
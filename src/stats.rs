@@ -0,0 +1,393 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::hash::BuildHasher;
+use core::mem;
+use core::time::Duration;
+
+use crate::{CacheEntity, LocalCache, Lookup};
+
+// Minutes of hits and misses kept for `CacheStats::last_15_minutes`.
+const WINDOW_MINUTES: usize = 15;
+const MINUTE_NS: u128 = 60_000_000_000;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Bucket {
+    minute: u128,
+    hits: u64,
+    misses: u64,
+}
+
+// Per-shard counters, updated under the shard lock.
+#[derive(Default, Clone, Copy)]
+pub(crate) struct Counters {
+    pub(crate) hits: u64,
+    pub(crate) misses: u64,
+    pub(crate) inserts: u64,
+    pub(crate) evictions: u64,
+    pub(crate) expirations: u64,
+    pub(crate) repair_checks: u64,
+    pub(crate) repair_divergences: u64,
+    // Sums over all inserts, for the mean TTL and key length.
+    pub(crate) ttl_ns: u128,
+    pub(crate) key_bytes: u64,
+    // Lookups per minute of clock time, a ring indexed by minute.
+    recent: [Bucket; WINDOW_MINUTES],
+}
+
+impl Counters {
+    pub(crate) fn record<T>(&mut self, lookup: &Lookup<T>, now: u128) {
+        let minute = now / MINUTE_NS;
+        let bucket = &mut self.recent[(minute % WINDOW_MINUTES as u128) as usize];
+        if bucket.minute != minute {
+            *bucket = Bucket { minute, hits: 0, misses: 0 };
+        }
+        match lookup {
+            Lookup::Hit(_) | Lookup::Negative => {
+                self.hits += 1;
+                bucket.hits += 1;
+            }
+            Lookup::Miss | Lookup::Corrupted => {
+                self.misses += 1;
+                bucket.misses += 1;
+            }
+        }
+    }
+
+    // Lookups in the current minute and the `minutes - 1` before it.
+    fn window(&self, now: u128, minutes: usize) -> WindowStats {
+        let current = now / MINUTE_NS;
+        let mut window = WindowStats::default();
+        for bucket in &self.recent {
+            if bucket.minute <= current && current - bucket.minute < minutes as u128 {
+                window.hits += bucket.hits;
+                window.misses += bucket.misses;
+            }
+        }
+        window
+    }
+
+    pub(crate) fn inserted(&mut self, key: &str, ttl_ns: u128) {
+        self.inserts += 1;
+        self.ttl_ns = self.ttl_ns.saturating_add(ttl_ns);
+        self.key_bytes += key.len() as u64;
+    }
+
+    // Leaves out the per-minute buckets, which are summed by `window`.
+    fn add(&mut self, other: &Counters) {
+        self.hits += other.hits;
+        self.misses += other.misses;
+        self.inserts += other.inserts;
+        self.evictions += other.evictions;
+        self.expirations += other.expirations;
+        self.repair_checks += other.repair_checks;
+        self.repair_divergences += other.repair_divergences;
+        self.ttl_ns = self.ttl_ns.saturating_add(other.ttl_ns);
+        self.key_bytes += other.key_bytes;
+    }
+}
+
+/// Counters since the cache was built, see [`LocalCache::stats`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub inserts: u64,
+    /// Entries removed for capacity.
+    pub evictions: u64,
+    /// Expired entries removed.
+    pub expirations: u64,
+    /// Hits re-checked against the backing store, see
+    /// [`LocalCacheBuilder::read_repair`](crate::LocalCacheBuilder::read_repair),
+    /// and how many of those had a different value there.
+    pub repair_checks: u64,
+    pub repair_divergences: u64,
+    pub entries: usize,
+    pub max_entries: usize,
+    pub elapsed: Duration,
+    /// Mean TTL given to inserted entries.
+    pub mean_ttl: Duration,
+    /// Rough per-entry footprint: node, value and mean key length.
+    pub entry_bytes: usize,
+    /// Lookups in the current minute of clock time, and in it and the 4
+    /// or 14 minutes before.
+    pub last_minute: WindowStats,
+    pub last_5_minutes: WindowStats,
+    pub last_15_minutes: WindowStats,
+}
+
+/// Lookups over a recent window, see [`CacheStats::last_minute`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct WindowStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl WindowStats {
+    /// The share of lookups that hit, `None` without any lookups.
+    pub fn hit_ratio(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| self.hits as f64 / lookups as f64)
+    }
+}
+
+/// One shard's entries and lookups, see [`LocalCache::shard_stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShardStats {
+    pub entries: usize,
+    pub max_entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub inserts: u64,
+    pub evictions: u64,
+}
+
+impl ShardStats {
+    /// The share of the shard's lookups that hit, `None` without any.
+    pub fn hit_ratio(&self) -> Option<f64> {
+        WindowStats { hits: self.hits, misses: self.misses }.hit_ratio()
+    }
+}
+
+/// Estimated cache size at the end of a horizon, see [`CacheStats::forecast`].
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Forecast {
+    pub entries: usize,
+    pub memory_bytes: usize,
+    /// Inserts arrive faster than entries expire, so the cache will be
+    /// evicting live entries for capacity.
+    pub thrashing: bool,
+}
+
+// `f64::exp` and `f64::round` need std.
+#[cfg(feature = "std")]
+impl CacheStats {
+    /// Projects the entry count `horizon` from now, assuming inserts keep
+    /// arriving at their average rate so far and live for the mean TTL: the
+    /// count decays towards `insert rate * mean TTL` (Little's law), capped
+    /// at the capacity.
+    pub fn forecast(&self, horizon: Duration) -> Forecast {
+        let elapsed = self.elapsed.as_secs_f64();
+        let insert_rate = if elapsed > 0.0 { self.inserts as f64 / elapsed } else { 0.0 };
+        let mean_ttl = self.mean_ttl.as_secs_f64();
+        let steady = insert_rate * mean_ttl;
+        let limit = steady.min(self.max_entries as f64);
+        let entries = if mean_ttl > 0.0 {
+            limit + (self.entries as f64 - limit) * (-horizon.as_secs_f64() / mean_ttl).exp()
+        } else {
+            limit
+        };
+        let entries = entries.round().clamp(0.0, self.max_entries as f64) as usize;
+        Forecast {
+            entries,
+            memory_bytes: entries * self.entry_bytes,
+            thrashing: steady > self.max_entries as f64,
+        }
+    }
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// Cache-wide counters, summed over the shards.
+    pub fn stats(&self) -> CacheStats {
+        let mut counters = Counters::default();
+        let (mut entries, mut max_entries) = (0, 0);
+        let now = self.clock.now_ns();
+        let mut windows = [WindowStats::default(); 3];
+        for shard in self.shards.iter() {
+            let local_cache = shard.lock();
+            counters.add(&local_cache.counters);
+            for (window, minutes) in windows.iter_mut().zip([1, 5, WINDOW_MINUTES]) {
+                let shard_window = local_cache.counters.window(now, minutes);
+                window.hits += shard_window.hits;
+                window.misses += shard_window.misses;
+            }
+            entries += local_cache.map.len();
+            max_entries += local_cache.max_numbers;
+        }
+        #[cfg(feature = "read-mostly")]
+        for snapshot in self.snapshots.iter().flatten() {
+            counters.hits += snapshot.hits.load(std::sync::atomic::Ordering::Relaxed);
+        }
+        let mean = |sum: u128| if counters.inserts == 0 { 0 } else { sum / counters.inserts as u128 };
+        CacheStats {
+            hits: counters.hits,
+            misses: counters.misses,
+            inserts: counters.inserts,
+            evictions: counters.evictions,
+            expirations: counters.expirations,
+            repair_checks: counters.repair_checks,
+            repair_divergences: counters.repair_divergences,
+            entries,
+            max_entries,
+            elapsed: Duration::from_nanos(u64::try_from(now.saturating_sub(self.created)).unwrap_or(u64::MAX)),
+            mean_ttl: Duration::from_nanos(u64::try_from(mean(counters.ttl_ns)).unwrap_or(u64::MAX)),
+            entry_bytes: mem::size_of::<CacheEntity<T>>()
+                + mem::size_of::<T>()
+                + mem::size_of::<usize>()
+                + mean(counters.key_bytes as u128) as usize,
+            last_minute: windows[0],
+            last_5_minutes: windows[1],
+            last_15_minutes: windows[2],
+        }
+    }
+
+    /// Counters of each shard, in shard order, to spot a hot or overfull
+    /// shard under a skewed key distribution.
+    pub fn shard_stats(&self) -> Vec<ShardStats> {
+        #[cfg_attr(not(feature = "read-mostly"), allow(unused_mut))]
+        let mut shards: Vec<_> = self
+            .shards
+            .iter()
+            .map(|shard| {
+                let local_cache = shard.lock();
+                ShardStats {
+                    entries: local_cache.map.len(),
+                    max_entries: local_cache.max_numbers,
+                    hits: local_cache.counters.hits,
+                    misses: local_cache.counters.misses,
+                    inserts: local_cache.counters.inserts,
+                    evictions: local_cache.counters.evictions,
+                }
+            })
+            .collect();
+        #[cfg(feature = "read-mostly")]
+        for (shard, snapshot) in shards.iter_mut().zip(self.snapshots.iter().flatten()) {
+            shard.hits += snapshot.hits.load(std::sync::atomic::Ordering::Relaxed);
+        }
+        shards
+    }
+
+    /// How many live entries expire within `within` from now, negative ones
+    /// included: the loads a read-through setup can expect to refresh.
+    pub fn expiring_within(&self, within: Duration) -> usize {
+        self.expiry_histogram(within, 1)[0]
+    }
+
+    /// Live entries by when they expire: `buckets` counts, the first of
+    /// those expiring within `bucket` from now, the next within the
+    /// `bucket` after that and so on. Later expiries aren't counted.
+    pub fn expiry_histogram(&self, bucket: Duration, buckets: usize) -> Vec<usize> {
+        let mut counts = vec![0; buckets];
+        let bucket_ns = bucket.as_nanos().max(1);
+        for shard in self.shards.iter() {
+            let local_cache = shard.lock();
+            let now = local_cache.now();
+            for entity in local_cache.map.values() {
+                let exp = entity.exp;
+                if now > exp {
+                    continue;
+                }
+                if let Some(count) = usize::try_from((exp - now) / bucket_ns).ok().and_then(|i| counts.get_mut(i)) {
+                    *count += 1;
+                }
+            }
+        }
+        counts
+    }
+}
+
+#[test]
+fn test_stats_forecast() {
+    use std::sync::Arc;
+
+    let local_cache: LocalCache<usize> = LocalCache::new(100, 1);
+    for i in 0..10 {
+        local_cache.put(i.to_string(), Arc::new(i));
+    }
+    local_cache.get("1");
+    local_cache.get("x");
+    let stats = local_cache.stats();
+    assert_eq!((1, 1, 10, 10), (stats.hits, stats.misses, stats.inserts, stats.entries));
+    assert_eq!(Duration::from_secs(1), stats.mean_ttl);
+
+    // Ten inserts in well under a second, each living a second: the rate
+    // alone would fill the cache.
+    let forecast = stats.forecast(Duration::from_secs(60));
+    assert!(forecast.thrashing);
+    assert_eq!(100, forecast.entries);
+    assert_eq!(100 * stats.entry_bytes, forecast.memory_bytes);
+
+    let idle = CacheStats { inserts: 10, elapsed: Duration::from_secs(100), ..stats };
+    assert!(!idle.forecast(Duration::from_secs(60)).thrashing);
+    assert_eq!(0, idle.forecast(Duration::from_secs(60)).entries);
+}
+
+#[test]
+fn test_shard_stats() {
+    use std::sync::Arc;
+
+    use crate::ShardRouter;
+
+    // Keys starting with "hot" all go to shard 0.
+    struct Skewed;
+    impl ShardRouter for Skewed {
+        fn shard(&self, key: &str, shards: usize) -> usize {
+            if key.starts_with("hot") {
+                0
+            } else {
+                1 + key.len() % (shards - 1)
+            }
+        }
+    }
+
+    let local_cache: LocalCache<usize> = LocalCache::builder().max_entries(100).shard_weights(&[7, 1, 2]).shard_router(Skewed).build();
+    let capacities: Vec<_> = local_cache.shard_stats().iter().map(|shard| shard.max_entries).collect();
+    assert_eq!(vec![70, 10, 20], capacities);
+    for i in 0..50 {
+        local_cache.put(format!("hot{}", i), Arc::new(i));
+    }
+    local_cache.put(String::from("c"), Arc::new(0));
+    local_cache.get("hot1");
+    local_cache.get("c");
+    local_cache.get("ccc");
+    let shards = local_cache.shard_stats();
+    assert_eq!((50, 50, 1, 0), (shards[0].entries, shards[0].inserts, shards[0].hits, shards[0].misses));
+    assert_eq!((1, Some(0.5)), (shards[2].entries, shards[2].hit_ratio()));
+    assert_eq!(None, shards[1].hit_ratio());
+    assert_eq!(100, shards.iter().map(|shard| shard.max_entries).sum::<usize>());
+
+    local_cache.set_max_entries(10);
+    let capacities: Vec<_> = local_cache.shard_stats().iter().map(|shard| shard.max_entries).collect();
+    assert_eq!(vec![7, 1, 2], capacities);
+}
+
+#[test]
+fn test_expiry_histogram() {
+    use std::sync::Arc;
+
+    let local_cache: LocalCache<usize> = LocalCache::builder().shards(2).ttl(Duration::from_secs(3600)).build();
+    for i in 0..6 {
+        local_cache.put_with_ttl(i.to_string(), Arc::new(i), Duration::from_secs(10 + i as u64 * 20));
+    }
+    local_cache.put_negative(String::from("n"));
+    assert_eq!(3, local_cache.expiring_within(Duration::from_secs(60)));
+    assert_eq!(vec![3, 3, 0], local_cache.expiry_histogram(Duration::from_secs(60), 3));
+    assert!(local_cache.expiry_histogram(Duration::from_secs(60), 0).is_empty());
+}
+
+#[test]
+fn test_windowed_stats() {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Arc;
+
+    let minutes = Arc::new(AtomicU64::new(100));
+    let clock = minutes.clone();
+    let clock = move || u128::from(clock.load(Ordering::Relaxed)) * MINUTE_NS;
+    let local_cache: LocalCache<usize> = LocalCache::builder().shards(2).ttl(Duration::from_secs(86400)).clock(clock).build();
+    local_cache.put(String::from("a"), Arc::new(1));
+    for _ in 0..3 {
+        local_cache.get("a");
+    }
+    minutes.store(103, Ordering::Relaxed);
+    local_cache.get("x");
+    let stats = local_cache.stats();
+    assert_eq!(WindowStats { hits: 0, misses: 1 }, stats.last_minute);
+    assert_eq!(WindowStats { hits: 3, misses: 1 }, stats.last_5_minutes);
+    assert_eq!(Some(0.75), stats.last_5_minutes.hit_ratio());
+
+    minutes.store(116, Ordering::Relaxed);
+    let stats = local_cache.stats();
+    assert_eq!(WindowStats { hits: 0, misses: 1 }, stats.last_15_minutes);
+    assert_eq!(None, stats.last_5_minutes.hit_ratio());
+    assert_eq!((3, 1), (stats.hits, stats.misses));
+}
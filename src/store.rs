@@ -0,0 +1,312 @@
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::collections::hash_map::DefaultHasher;
+#[cfg(feature = "std")]
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "std")]
+use std::fs;
+#[cfg(feature = "std")]
+use std::future::{self, Future};
+#[cfg(feature = "std")]
+use std::hash::{Hash, Hasher};
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "std")]
+use std::pin::Pin;
+#[cfg(feature = "std")]
+use std::sync::Mutex;
+#[cfg(feature = "std")]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[cfg(feature = "std")]
+use crate::{lock, ValueCodec};
+
+#[cfg(feature = "std")]
+/// An entry spilled to a [`Store`].
+pub struct Spilled<V> {
+    pub value: V,
+    pub expires_at: SystemTime,
+    /// Set when the cache has [checksums](crate::LocalCacheBuilder::checksums)
+    /// enabled; stores should hand it back unchanged from `load`.
+    pub checksum: Option<u32>,
+}
+
+#[cfg(feature = "std")]
+/// Second tier behind the in-memory cache, see [`LocalCacheBuilder::store`](crate::LocalCacheBuilder::store).
+///
+/// Calls are made under the shard lock, except for `load_async`. The tier
+/// is best-effort, so failures are not reported: a failed `save` just loses
+/// the entry.
+pub trait Store<T>: Send + Sync {
+    fn load(&self, key: &str) -> Option<Spilled<T>>;
+    fn save(&self, key: &str, entry: Spilled<&T>);
+    fn remove(&self, key: &str);
+    /// What [`get_async`](crate::LocalCache::get_async) loads misses with,
+    /// awaited without holding the shard lock. Defaults to `load`; stores
+    /// with slow reads can do them elsewhere, e.g. on a blocking thread.
+    fn load_async<'a>(&'a self, key: &'a str) -> Pin<Box<dyn Future<Output = Option<Spilled<T>>> + Send + 'a>>
+    where
+        T: Send + 'a,
+    {
+        Box::pin(future::ready(self.load(key)))
+    }
+}
+
+/// Source of truth behind a read/write-through cache, see
+/// [`LocalCacheBuilder::backing_store`](crate::LocalCacheBuilder::backing_store).
+///
+/// Calls are made synchronously under the shard lock, so the cache and the
+/// store see writes to a key in the same order.
+pub trait BackingStore<K, V>: Send + Sync {
+    fn load(&self, key: &K) -> Option<V>;
+    fn store(&self, key: &K, value: &V);
+    fn delete(&self, key: &K);
+    /// Called instead of `store` with the TTL the value is cached for,
+    /// `Duration::MAX` for none, for stores that can expire it themselves.
+    fn store_with_ttl(&self, key: &K, value: &V, ttl: Duration) {
+        let _ = ttl;
+        self.store(key, value)
+    }
+}
+
+#[cfg(feature = "std")]
+/// [`Store`] keeping one file per entry in a directory.
+///
+/// Files are named after a hash of the key and hold the expiry, the
+/// checksum, the key itself and the encoded value, so they survive restarts
+/// of the process.
+pub struct FileStore<C> {
+    dir: PathBuf,
+    codec: C,
+    // Set by `max_bytes`.
+    bound: Option<(u64, Mutex<SpillIndex>)>,
+}
+
+#[cfg(feature = "std")]
+// The files of a bounded store, oldest first.
+#[derive(Default)]
+struct SpillIndex {
+    // Each file's place in `order` and size.
+    files: HashMap<PathBuf, (u64, u64)>,
+    order: BTreeMap<u64, PathBuf>,
+    bytes: u64,
+    next: u64,
+}
+
+#[cfg(feature = "std")]
+impl SpillIndex {
+    fn add(&mut self, path: PathBuf, size: u64) {
+        self.remove(&path);
+        self.order.insert(self.next, path.clone());
+        self.files.insert(path, (self.next, size));
+        self.next += 1;
+        self.bytes += size;
+    }
+
+    fn remove(&mut self, path: &Path) {
+        if let Some((seq, size)) = self.files.remove(path) {
+            self.order.remove(&seq);
+            self.bytes -= size;
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<C> FileStore<C> {
+    /// Creates `dir` if needed.
+    pub fn new<P: AsRef<Path>>(dir: P, codec: C) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir: dir.as_ref().to_path_buf(),
+            codec,
+            bound: None,
+        })
+    }
+
+    /// Keeps the files under `max_bytes` in all, deleting the oldest ones
+    /// to make room for new ones. Files already in the directory count
+    /// too, oldest modified first.
+    pub fn max_bytes(mut self, max_bytes: u64) -> io::Result<Self> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_file() && entry.path().extension().is_none() {
+                files.push((metadata.modified()?, entry.path(), metadata.len()));
+            }
+        }
+        files.sort();
+        let mut index = SpillIndex::default();
+        for (_, path, size) in files {
+            index.add(path, size);
+        }
+        self.bound = Some((max_bytes, Mutex::new(index)));
+        self.shrink();
+        Ok(self)
+    }
+
+    /// Bytes taken by the files, as far as a store with
+    /// [`max_bytes`](Self::max_bytes) keeps track; 0 without.
+    pub fn disk_usage(&self) -> u64 {
+        self.bound.as_ref().map_or(0, |(_, index)| lock(index).bytes)
+    }
+
+    // Deletes the oldest files while over the bound.
+    fn shrink(&self) {
+        let Some((max_bytes, index)) = &self.bound else {
+            return;
+        };
+        let mut index = lock(index);
+        while index.bytes > *max_bytes {
+            let Some((_, path)) = index.order.pop_first() else {
+                break;
+            };
+            let _ = fs::remove_file(&path);
+            index.remove(&path);
+        }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        self.dir.join(format!("{:016x}", hasher.finish()))
+    }
+}
+
+#[cfg(feature = "std")]
+// A spilled entry as stored: the expiry, the checksum, the key itself and
+// the encoded value. `None` if the value doesn't encode.
+pub(crate) fn encode_spilled<T>(codec: &impl ValueCodec<T>, key: &str, entry: Spilled<&T>) -> Option<Vec<u8>> {
+    let exp = entry.expires_at.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+    let value = codec.encode(entry.value)?;
+    let mut bytes = Vec::with_capacity(25 + key.len() + value.len());
+    bytes.extend_from_slice(&exp.to_le_bytes());
+    bytes.push(entry.checksum.is_some() as u8);
+    bytes.extend_from_slice(&entry.checksum.unwrap_or(0).to_le_bytes());
+    bytes.extend_from_slice(&(key.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(key.as_bytes());
+    bytes.extend_from_slice(&value);
+    Some(bytes)
+}
+
+#[cfg(feature = "std")]
+// The entry `encode_spilled` made `bytes` of, if they are for `key`.
+pub(crate) fn decode_spilled<T>(codec: &impl ValueCodec<T>, key: &str, bytes: &[u8]) -> Option<Spilled<T>> {
+    let (exp, rest) = bytes.split_first_chunk::<16>()?;
+    let (has_checksum, rest) = rest.split_first()?;
+    let (checksum, rest) = rest.split_first_chunk::<4>()?;
+    let (key_len, rest) = rest.split_first_chunk::<4>()?;
+    let (stored_key, value) = rest.split_at_checked(u32::from_le_bytes(*key_len) as usize)?;
+    if stored_key != key.as_bytes() {
+        return None;
+    }
+    let exp = u64::try_from(u128::from_le_bytes(*exp)).ok()?;
+    Some(Spilled {
+        value: codec.decode(value)?,
+        expires_at: UNIX_EPOCH + Duration::from_nanos(exp),
+        checksum: (*has_checksum == 1).then(|| u32::from_le_bytes(*checksum)),
+    })
+}
+
+#[cfg(feature = "std")]
+impl<T, C: ValueCodec<T>> Store<T> for FileStore<C> {
+    fn load(&self, key: &str) -> Option<Spilled<T>> {
+        // Another key with the same hash may have overwritten the file.
+        decode_spilled(&self.codec, key, &fs::read(self.path(key)).ok()?)
+    }
+
+    fn save(&self, key: &str, entry: Spilled<&T>) {
+        let Some(bytes) = encode_spilled(&self.codec, key, entry) else {
+            // Don't leave an older value behind to be loaded instead.
+            self.remove(key);
+            return;
+        };
+        // Write then rename, so a crash never leaves a torn file behind.
+        let path = self.path(key);
+        let tmp = path.with_extension("tmp");
+        let size = bytes.len() as u64;
+        if fs::write(&tmp, bytes).is_err() || fs::rename(&tmp, &path).is_err() {
+            let _ = fs::remove_file(&tmp);
+            return;
+        }
+        if let Some((_, index)) = &self.bound {
+            lock(index).add(path, size);
+            self.shrink();
+        }
+    }
+
+    fn remove(&self, key: &str) {
+        let path = self.path(key);
+        let _ = fs::remove_file(&path);
+        if let Some((_, index)) = &self.bound {
+            lock(index).remove(&path);
+        }
+    }
+}
+
+#[test]
+fn test_file_store() {
+    use std::sync::Arc;
+
+    use crate::testing::Utf8;
+    use crate::LocalCache;
+
+    let dir = std::env::temp_dir().join(format!("local-cache-store-{}", std::process::id()));
+    let local_cache: LocalCache<String> = LocalCache::builder()
+        .max_entries(1)
+        .store(FileStore::new(&dir, Utf8).unwrap())
+        .build();
+    local_cache.put(String::from("x"), Arc::new(String::from("abc")));
+    local_cache.put(String::from("y"), Arc::new(String::from("123")));
+    assert_eq!(1, fs::read_dir(&dir).unwrap().count());
+
+    assert_eq!(Some(Arc::new(String::from("abc"))), local_cache.get("x"));
+    assert_eq!(Some(Arc::new(String::from("123"))), local_cache.get("y"));
+    assert_eq!(None, local_cache.get("z"));
+
+    // A fresh cache over the same directory picks up what was spilled.
+    let restarted: LocalCache<String> = LocalCache::builder()
+        .store(FileStore::new(&dir, Utf8).unwrap())
+        .build();
+    assert_eq!(Some(Arc::new(String::from("abc"))), restarted.get("x"));
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_file_store_max_bytes() {
+    use std::sync::Arc;
+
+    use crate::LocalCache;
+
+    struct Bytes;
+    impl ValueCodec<Vec<u8>> for Bytes {
+        fn encode(&self, value: &Vec<u8>) -> Option<Vec<u8>> {
+            Some(value.clone())
+        }
+        fn decode(&self, bytes: &[u8]) -> Option<Vec<u8>> {
+            Some(bytes.to_vec())
+        }
+    }
+
+    let dir = std::env::temp_dir().join(format!("local-cache-bounded-store-{}", std::process::id()));
+    // Each file takes 25 bytes of header, 1 of key and 100 of value.
+    let local_cache: LocalCache<Vec<u8>> = LocalCache::builder()
+        .max_entries(1)
+        .store(FileStore::new(&dir, Bytes).unwrap().max_bytes(300).unwrap())
+        .build();
+    for i in 0..5 {
+        local_cache.put(i.to_string(), vec![i; 100]);
+    }
+    // "4" is in memory, "2" and "3" on disk; "0" and "1" made room for them.
+    assert_eq!(2, fs::read_dir(&dir).unwrap().count());
+    assert_eq!(None, local_cache.get("0"));
+    assert_eq!(Some(Arc::new(vec![2; 100])), local_cache.get("2"));
+
+    // Files left by an earlier run count against the bound.
+    let store = FileStore::new(&dir, Bytes).unwrap().max_bytes(200).unwrap();
+    assert_eq!(1, fs::read_dir(&dir).unwrap().count());
+    assert_eq!(126, store.disk_usage());
+    let _ = fs::remove_dir_all(&dir);
+}
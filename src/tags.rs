@@ -0,0 +1,96 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::hash::BuildHasher;
+
+#[cfg(feature = "std")]
+use crate::Invalidation;
+use crate::{InnerLocalCache, LocalCache};
+
+impl<T, S: BuildHasher> InnerLocalCache<T, S> {
+    fn set_tags(&mut self, key: &str, tags: Box<[String]>) {
+        let Some(id) = self.map.find(key) else {
+            return;
+        };
+        for tag in tags.iter() {
+            self.tags.entry(tag.clone()).or_default().insert(self.map[id].key.clone());
+        }
+        self.map[id].tags = tags;
+        self.recount(id);
+    }
+
+    // Drops `key` from the index of each of `tags`; called on removal.
+    pub(crate) fn untag(&mut self, key: &str, tags: &[String]) {
+        for tag in tags {
+            if let Some(keys) = self.tags.get_mut(tag) {
+                keys.remove(key);
+                if keys.is_empty() {
+                    self.tags.remove(tag);
+                }
+            }
+        }
+    }
+
+    // Removes the shard's entries tagged with `tag` from memory and the
+    // spill store. Returns how many there were.
+    pub(crate) fn remove_tagged(&mut self, tag: &str) -> usize {
+        let Some(keys) = self.tags.remove(tag) else {
+            return 0;
+        };
+        let mut removed = 0;
+        for key in keys {
+            #[cfg(feature = "std")]
+            if let Some(store) = &self.store {
+                store.remove(&key);
+            }
+            if self.remove(&key).is_some() {
+                removed += 1;
+            }
+        }
+        removed
+    }
+}
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// Like [`put`](Self::put), attaching `tags` to the entry for
+    /// [`invalidate_tag`](Self::invalidate_tag). Replacing the entry drops its tags.
+    pub fn put_tagged<I, G>(&self, key: String, value: Arc<T>, tags: I)
+    where
+        I: IntoIterator<Item = G>,
+        G: Into<String>,
+    {
+        let tags: Box<[String]> = tags.into_iter().map(Into::into).collect();
+        let mut local_cache = self.lock(&key);
+        local_cache.put(key.as_str().into(), Some(value));
+        local_cache.set_tags(&key, tags);
+    }
+
+    /// Removes every entry tagged with `tag`, here and in the spill store
+    /// (the backing store is left alone), and on the other caches of the
+    /// [invalidation bus](crate::LocalCacheBuilder::invalidation_bus).
+    /// Returns the number of entries removed here.
+    pub fn invalidate_tag(&self, tag: &str) -> usize {
+        #[cfg(feature = "std")]
+        self.publish(Invalidation::Tag(tag.to_string()));
+        self.shards.iter().map(|shard| shard.lock().remove_tagged(tag)).sum()
+    }
+}
+
+#[test]
+fn test_invalidate_tag() {
+    let local_cache: LocalCache<usize> = LocalCache::builder().shards(2).build();
+    local_cache.put_tagged(String::from("a"), Arc::new(0), ["tenant:7", "feed"]);
+    local_cache.put_tagged(String::from("b"), Arc::new(1), ["tenant:7"]);
+    local_cache.put_tagged(String::from("c"), Arc::new(2), ["feed"]);
+    local_cache.put(String::from("d"), Arc::new(3));
+
+    assert_eq!(2, local_cache.invalidate_tag("tenant:7"));
+    assert_eq!(None, local_cache.get("a"));
+    assert_eq!(Some(Arc::new(2)), local_cache.get("c"));
+    assert_eq!(0, local_cache.invalidate_tag("tenant:7"));
+
+    // A replaced entry leaves the index.
+    local_cache.put(String::from("c"), Arc::new(4));
+    assert_eq!(0, local_cache.invalidate_tag("feed"));
+    assert!(local_cache.shards.iter().all(|shard| shard.lock().tags.is_empty()));
+}
@@ -0,0 +1,44 @@
+// Fixtures shared by the tests of several modules.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::{lock, BackingStore, ValueCodec};
+
+// Strings stored as their UTF-8 bytes.
+pub(crate) struct Utf8;
+
+impl ValueCodec<String> for Utf8 {
+    fn encode(&self, value: &String) -> Option<Vec<u8>> {
+        Some(value.as_bytes().to_vec())
+    }
+    fn decode(&self, bytes: &[u8]) -> Option<String> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+// A backing store in memory: its rows, and how many stores and deletes it
+// has been sent. Clones share both.
+#[derive(Clone)]
+pub(crate) struct Db<V>(pub(crate) Arc<Mutex<HashMap<String, V>>>, pub(crate) Arc<AtomicUsize>);
+
+impl<V> Default for Db<V> {
+    fn default() -> Self {
+        Self(Arc::default(), Arc::default())
+    }
+}
+
+impl<V: Clone + Send> BackingStore<String, V> for Db<V> {
+    fn load(&self, key: &String) -> Option<V> {
+        lock(&self.0).get(key).cloned()
+    }
+    fn store(&self, key: &String, value: &V) {
+        self.1.fetch_add(1, Ordering::Relaxed);
+        lock(&self.0).insert(key.clone(), value.clone());
+    }
+    fn delete(&self, key: &String) {
+        self.1.fetch_add(1, Ordering::Relaxed);
+        lock(&self.0).remove(key);
+    }
+}
@@ -0,0 +1,79 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::any::{Any, TypeId};
+use core::hash::BuildHasher;
+use core::time::Duration;
+
+use crate::{DefaultHashBuilder, LocalCache};
+
+/// A value of a [`TypedLocalCache`], of whatever type it was put as.
+pub type AnyValue = Arc<dyn Any + Send + Sync>;
+
+/// One cache for values of many types, keyed by name and type: the same
+/// name holds one value per type, and `get::<U>` only ever sees values put
+/// as a `U`.
+pub struct TypedLocalCache<S = DefaultHashBuilder> {
+    local_cache: LocalCache<AnyValue, S>,
+}
+
+impl<S: BuildHasher> TypedLocalCache<S> {
+    /// Wraps `local_cache`, which is configured as usual; its capacity and
+    /// stats cover the values of every type.
+    pub fn new(local_cache: LocalCache<AnyValue, S>) -> Self {
+        Self { local_cache }
+    }
+
+    /// The wrapped cache, for stats and anything not mirrored here. Its
+    /// keys are the names followed by a NUL and the type's id.
+    pub fn cache(&self) -> &LocalCache<AnyValue, S> {
+        &self.local_cache
+    }
+
+    fn key<U: Any>(key: &str) -> String {
+        format!("{key}\0{:?}", TypeId::of::<U>())
+    }
+
+    pub fn get<U: Any + Send + Sync>(&self, key: &str) -> Option<Arc<U>> {
+        let value = self.local_cache.get(&Self::key::<U>(key))?;
+        // Only a `U` is ever put under a `U`'s key.
+        (*value).clone().downcast().ok()
+    }
+
+    pub fn put<U: Any + Send + Sync>(&self, key: &str, value: impl Into<Arc<U>>) {
+        let value: AnyValue = value.into();
+        self.local_cache.put(Self::key::<U>(key), value)
+    }
+
+    pub fn put_with_ttl<U: Any + Send + Sync>(&self, key: &str, value: impl Into<Arc<U>>, ttl: Duration) {
+        let value: AnyValue = value.into();
+        self.local_cache.put_with_ttl(Self::key::<U>(key), value, ttl)
+    }
+
+    pub fn remove<U: Any + Send + Sync>(&self, key: &str) -> Option<Arc<U>> {
+        let value = self.local_cache.remove(&Self::key::<U>(key))?;
+        (*value).clone().downcast().ok()
+    }
+}
+
+#[test]
+fn test_typed_local_cache() {
+    #[derive(Debug, PartialEq)]
+    struct User {
+        name: String,
+    }
+
+    let typed = TypedLocalCache::new(LocalCache::new(16, 360));
+    typed.put("42", User { name: String::from("ann") });
+    typed.put("42", 7u32);
+    typed.put::<String>("42", String::from("answer"));
+    assert_eq!(Some(Arc::new(User { name: String::from("ann") })), typed.get::<User>("42"));
+    assert_eq!(Some(Arc::new(7)), typed.get::<u32>("42"));
+    assert_eq!(Some(Arc::new(String::from("answer"))), typed.get::<String>("42"));
+    assert_eq!(None, typed.get::<u64>("42"));
+
+    assert_eq!(Some(Arc::new(7)), typed.remove::<u32>("42"));
+    assert_eq!(None, typed.get::<u32>("42"));
+    assert!(typed.get::<User>("42").is_some());
+    assert_eq!(2, typed.cache().stats().entries);
+}
@@ -0,0 +1,88 @@
+use alloc::string::String;
+use alloc::sync::Arc;
+use core::future::Future;
+use core::hash::BuildHasher;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+
+use crate::{ttl_ns, LocalCache};
+
+// Entries inserted per lock acquisition by `warm_async`.
+const WARM_ASYNC_CHUNK: usize = 1024;
+
+impl<T, S: BuildHasher> LocalCache<T, S> {
+    /// Bulk-loads entries under a single lock acquisition, using the default TTL.
+    /// They only go into memory, not to the backing store or the bus.
+    /// Returns the number of entries inserted.
+    pub fn warm<I: IntoIterator<Item = (String, T)>>(&self, iter: I) -> usize {
+        self.warm_with_ttl(iter.into_iter().map(|(key, value)| (key, value, None)))
+    }
+
+    /// Like [`warm`](Self::warm), with an optional TTL per entry.
+    pub fn warm_with_ttl<I: IntoIterator<Item = (String, T, Option<Duration>)>>(&self, iter: I) -> usize {
+        if self.shards.len() == 1 {
+            return self.warm_shard(0, iter);
+        }
+        let buckets = self.bucket(iter, |item| &item.0);
+        buckets.into_iter().enumerate().map(|(i, bucket)| self.warm_shard(i, bucket)).sum()
+    }
+
+    fn warm_shard<I: IntoIterator<Item = (String, T, Option<Duration>)>>(&self, shard: usize, iter: I) -> usize {
+        let mut local_cache = self.shards[shard].lock();
+        let mut inserted = 0;
+        for (key, value, ttl) in iter {
+            let ttl_ns = ttl.map_or(local_cache.max_age_ns, ttl_ns);
+            local_cache.fill(key.into(), Some(Arc::new(value)), ttl_ns, "warmup");
+            inserted += 1;
+        }
+        inserted
+    }
+
+    /// Like [`warm_with_ttl`](Self::warm_with_ttl), but releases the lock and
+    /// yields to the executor every 1024 entries so a large boot-time
+    /// load doesn't stall other tasks or cache users.
+    pub async fn warm_async<I: IntoIterator<Item = (String, T, Option<Duration>)>>(&self, iter: I) -> usize {
+        let mut iter = iter.into_iter().peekable();
+        let mut inserted = 0;
+        while iter.peek().is_some() {
+            inserted += self.warm_with_ttl(iter.by_ref().take(WARM_ASYNC_CHUNK));
+            YieldNow(false).await;
+        }
+        inserted
+    }
+}
+
+struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+#[test]
+fn test_warm() {
+    use std::task::Waker;
+
+    let local_cache: LocalCache<usize> = LocalCache::new(4, 360);
+    assert_eq!(2, local_cache.warm([("x".to_string(), 1), ("y".to_string(), 2)]));
+    assert_eq!(Some(Arc::new(2)), local_cache.get("y"));
+
+    let mut future = std::pin::pin!(local_cache.warm_async((0..3000).map(|i| (i.to_string(), i, Some(Duration::from_secs(1))))));
+    let mut cx = Context::from_waker(Waker::noop());
+    let mut polls = 1;
+    while future.as_mut().poll(&mut cx).is_pending() {
+        polls += 1;
+    }
+    assert_eq!(4, polls);
+    assert_eq!(Some(Arc::new(2999)), local_cache.get("2999"));
+    assert_eq!(None, local_cache.get("x"));
+}
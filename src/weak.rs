@@ -0,0 +1,36 @@
+use crate::LocalCacheBuilder;
+
+impl<T, S> LocalCacheBuilder<T, S> {
+    /// Holds values as `Weak` references: once nothing outside the cache
+    /// holds a value any more, it is dropped and its entry reads as a miss
+    /// (and is removed on that read). Until then an entry counts against
+    /// `max_entries` like any other. Entries demoted to cold storage are
+    /// encoded and so no longer weak.
+    pub fn weak_values(mut self) -> Self {
+        self.weak_values = true;
+        self
+    }
+}
+
+#[test]
+fn test_weak_values() {
+    use std::sync::Arc;
+
+    use crate::{LocalCache, Lookup};
+
+    let local_cache: LocalCache<Vec<u8>> = LocalCache::builder().weak_values().build();
+    let value = Arc::new(vec![0; 1024]);
+    local_cache.put(String::from("x"), value.clone());
+    local_cache.put(String::from("y"), Arc::new(vec![1]));
+    assert_eq!(1, Arc::strong_count(&value));
+    assert_eq!(Some(value.clone()), local_cache.get("x"));
+    assert_eq!(Lookup::Miss, local_cache.lookup("y"));
+    assert_eq!(1, local_cache.shards[0].lock().map.len());
+
+    drop(value);
+    assert_eq!(None, local_cache.get("x"));
+    assert_eq!(0, local_cache.shards[0].lock().map.len());
+
+    let loaded = local_cache.get_or_insert_with(String::from("z"), || vec![2]);
+    assert_eq!(Some(loaded), local_cache.get("z"));
+}
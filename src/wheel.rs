@@ -0,0 +1,165 @@
+use alloc::boxed::Box;
+use alloc::vec;
+
+use crate::keymap::KeyMap;
+use crate::slab::NodeId;
+use crate::{Link, NEVER};
+
+// Ticks of 2^20 ns, about a millisecond.
+const TICK_SHIFT: u32 = 20;
+const SLOT_BITS: u32 = 6;
+const SLOTS: usize = 1 << SLOT_BITS;
+const LEVELS: usize = 6;
+// About 2.3 years. Later expiries are filed at this distance and re-filed
+// when it comes up.
+const MAX_TICKS: u64 = (1 << (SLOT_BITS * LEVELS as u32)) - 1;
+// `wheel_slot` of entries that never expire, which are not filed at all.
+const UNFILED: usize = usize::MAX;
+
+fn tick(ns: u128) -> u64 {
+    u64::try_from(ns >> TICK_SHIFT).unwrap_or(u64::MAX)
+}
+
+// Hierarchical hashed timer wheel holding every entry by expiry, linked
+// through `exp_prev`/`exp_next`. Level `l` has 64 slots of 64^l ticks each;
+// an entry goes on the level of the highest 6-bit group in which its expiry
+// tick differs from `elapsed`, so filing and unfiling are O(1). Sweeping
+// cascades a higher slot down once `elapsed` reaches it; only level 0 slots
+// are ever expired from.
+#[derive(Clone)]
+pub(crate) struct TimerWheel {
+    slots: Box<[Link]>,
+    // Bit `s` of level `l` is set while slot `s` is non-empty.
+    occupied: [u64; LEVELS],
+    // Every slot before this tick has been processed.
+    elapsed: u64,
+}
+
+impl TimerWheel {
+    pub(crate) fn new(now: u128) -> Self {
+        Self { slots: vec![None; LEVELS * SLOTS].into_boxed_slice(), occupied: [0; LEVELS], elapsed: tick(now) }
+    }
+
+    // Updates every slot's head after nodes moved, see `KeyMap::shrink_to_fit`.
+    pub(crate) fn relink(&mut self, link: impl Fn(Link) -> Link) {
+        for head in self.slots.iter_mut() {
+            *head = link(*head);
+        }
+    }
+
+    pub(crate) fn insert<T, S>(&mut self, map: &mut KeyMap<T, S>, id: NodeId) {
+        let entity = &mut map[id];
+        if entity.exp == NEVER {
+            entity.wheel_slot = UNFILED;
+            return;
+        }
+        let when = tick(entity.exp).clamp(self.elapsed, self.elapsed.saturating_add(MAX_TICKS));
+        let masked = (self.elapsed ^ when) | (SLOTS as u64 - 1);
+        let level = ((63 - masked.leading_zeros()) / SLOT_BITS).min(LEVELS as u32 - 1);
+        let slot = (when >> (level * SLOT_BITS)) as usize & (SLOTS - 1);
+        let index = level as usize * SLOTS + slot;
+        entity.wheel_slot = index;
+        entity.exp_prev = None;
+        entity.exp_next = self.slots[index];
+        if let Some(head) = self.slots[index] {
+            map[head].exp_prev = Some(id);
+        }
+        self.slots[index] = Some(id);
+        self.occupied[level as usize] |= 1 << slot;
+    }
+
+    pub(crate) fn remove<T, S>(&mut self, map: &mut KeyMap<T, S>, id: NodeId) {
+        let entity = &mut map[id];
+        if entity.wheel_slot == UNFILED {
+            return;
+        }
+        let (prev, next, index) = (entity.exp_prev.take(), entity.exp_next.take(), entity.wheel_slot);
+        if let Some(e) = next {
+            map[e].exp_prev = prev;
+        }
+        match prev {
+            Some(e) => map[e].exp_next = next,
+            None => {
+                self.slots[index] = next;
+                if next.is_none() {
+                    self.occupied[index / SLOTS] &= !(1 << (index % SLOTS));
+                }
+            }
+        }
+    }
+
+    // The lowest occupied level, its next occupied slot after `elapsed` and
+    // the tick that slot starts at. Lower levels always come due first.
+    fn next_slot(&self) -> Option<(usize, usize, u64)> {
+        let level = self.occupied.iter().position(|&occupied| occupied != 0)?;
+        let slot_ticks = 1u64 << (level as u32 * SLOT_BITS);
+        let level_ticks = slot_ticks << SLOT_BITS;
+        let current = (self.elapsed >> (level as u32 * SLOT_BITS)) as usize & (SLOTS - 1);
+        // Above level 0, the slot `elapsed` is in only holds entries of the
+        // next lap, everything sooner being on a lower level, so it comes last.
+        let first = if level == 0 { current } else { (current + 1) % SLOTS };
+        let slot = (self.occupied[level].rotate_right(first as u32).trailing_zeros() as usize + first) % SLOTS;
+        let mut start = (self.elapsed & !(level_ticks - 1)) + slot as u64 * slot_ticks;
+        if start < self.elapsed || (level > 0 && start <= self.elapsed) {
+            start += level_ticks;
+        }
+        Some((level, slot, start))
+    }
+
+    // An entry expired by `now`, soonest slot first, cascading slots down
+    // on the way. It stays filed until removed.
+    pub(crate) fn peek_expired<T, S>(&mut self, map: &mut KeyMap<T, S>, now: u128) -> Link {
+        let now_tick = tick(now);
+        while let Some((level, slot, start)) = self.next_slot() {
+            if start > now_tick {
+                break;
+            }
+            self.elapsed = start;
+            let index = level * SLOTS + slot;
+            if level == 0 {
+                let mut cur = self.slots[index];
+                while let Some(e) = cur {
+                    if map[e].exp <= now {
+                        return Some(e);
+                    }
+                    cur = map[e].exp_next;
+                }
+                // Only the current tick's slot holds entries not yet due.
+                break;
+            }
+            let mut cur = self.slots[index].take();
+            self.occupied[level] &= !(1 << slot);
+            while let Some(e) = cur {
+                cur = map[e].exp_next;
+                self.insert(map, e);
+            }
+        }
+        self.elapsed = self.elapsed.max(now_tick);
+        None
+    }
+}
+
+#[test]
+fn test_timer_wheel() {
+    use std::sync::Arc;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use crate::LocalCache;
+
+    let local_cache: LocalCache<usize> = LocalCache::new(64, 360);
+    let secs = [3600 * 24 * 365 * 10, 5, 3600, 1, 3600 * 24 * 30, 60, 2];
+    for (i, &ttl) in secs.iter().enumerate() {
+        local_cache.put_with_ttl(i.to_string(), Arc::new(i), Duration::from_secs(ttl));
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    let mut local_cache = local_cache.shards[0].lock();
+    let mut after = |secs: u64| local_cache.evict_expired(now + Duration::from_secs(secs).as_nanos(), usize::MAX);
+    assert_eq!(0, after(0));
+    assert_eq!(2, after(3));
+    assert_eq!(0, after(4));
+    assert_eq!(2, after(61));
+    assert_eq!(1, after(3601));
+    assert_eq!(1, after(3600 * 24 * 31));
+    assert_eq!(0, after(3600 * 24 * 365 * 9));
+    assert_eq!(1, after(3600 * 24 * 365 * 11));
+}
@@ -0,0 +1,218 @@
+use std::collections::HashSet;
+use std::hash::BuildHasher;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::{lock, BackingStore, InnerLocalCache, LocalCache, LocalCacheBuilder, ShardGuard, ShardLock};
+
+type Shards<T, S> = Arc<[ShardLock<InnerLocalCache<T, S>>]>;
+
+/// Batching of backing store writes, see [`LocalCacheBuilder::write_behind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteBehind {
+    batch_size: usize,
+    interval: Duration,
+}
+
+impl WriteBehind {
+    /// Writes a batch once it holds `batch_size` writes, or `interval` after
+    /// its first one, whichever comes first.
+    pub fn new(batch_size: usize, interval: Duration) -> Self {
+        Self { batch_size: batch_size.max(1), interval }
+    }
+}
+
+pub(crate) enum Write<T> {
+    Store(String, Arc<T>, Duration),
+    Delete(String),
+    Flush(SyncSender<()>),
+}
+
+impl<T, S> InnerLocalCache<T, S> {
+    // Queues a write for the write-behind thread. Returns false if there is
+    // no such thread, and the write is the caller's to make.
+    pub(crate) fn write_behind(&self, write: impl FnOnce() -> Write<T>) -> bool {
+        self.write_behind.as_ref().is_some_and(|sender| sender.send(write()).is_ok())
+    }
+}
+
+// The write-behind thread. Dropping it drains the queue, like `shutdown`.
+pub(crate) struct WriteBehindWorker<T, S> {
+    shards: Shards<T, S>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl<T: Send + Sync + 'static, S> WriteBehindWorker<T, S> {
+    pub(crate) fn spawn(shards: &Shards<T, S>, backing: Arc<dyn BackingStore<String, T>>, config: WriteBehind) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        for shard in shards.iter() {
+            shard.lock().write_behind = Some(sender.clone());
+        }
+        let worker = thread::Builder::new()
+            .name("local-cache-write-behind".to_string())
+            .spawn(move || run(&*backing, receiver, config))
+            .expect("failed to spawn write-behind thread");
+        Self { shards: shards.clone(), worker: Mutex::new(Some(worker)) }
+    }
+}
+
+impl<T, S> WriteBehindWorker<T, S> {
+    fn sender(&self) -> Option<Sender<Write<T>>> {
+        self.shards[0].lock().write_behind.clone()
+    }
+
+    // Detaches every shard, all locked at once so no write made directly
+    // afterwards can overtake a queued one, and waits for the thread to
+    // write what is left.
+    fn stop(&self) {
+        let mut shards: Vec<ShardGuard<'_, InnerLocalCache<T, S>>> = self.shards.iter().map(|shard| shard.lock()).collect();
+        for local_cache in shards.iter_mut() {
+            local_cache.write_behind = None;
+        }
+        if let Some(worker) = lock(&self.worker).take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl<T, S> Drop for WriteBehindWorker<T, S> {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn run<T>(backing: &dyn BackingStore<String, T>, receiver: Receiver<Write<T>>, config: WriteBehind) {
+    let mut batch = Vec::new();
+    let mut due = Instant::now();
+    loop {
+        let write = if batch.is_empty() {
+            receiver.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            receiver.recv_timeout(due.saturating_duration_since(Instant::now()))
+        };
+        match write {
+            Ok(Write::Flush(done)) => {
+                apply(backing, &mut batch);
+                let _ = done.send(());
+            }
+            Ok(write) => {
+                if batch.is_empty() {
+                    due = Instant::now() + config.interval;
+                }
+                batch.push(write);
+                if batch.len() >= config.batch_size {
+                    apply(backing, &mut batch);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => apply(backing, &mut batch),
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    apply(backing, &mut batch);
+}
+
+// Makes the last write of each key in the batch.
+fn apply<T>(backing: &dyn BackingStore<String, T>, batch: &mut Vec<Write<T>>) {
+    let mut written = HashSet::new();
+    for write in batch.drain(..).rev() {
+        match write {
+            Write::Store(key, value, ttl) => {
+                if !written.contains(&key) {
+                    backing.store_with_ttl(&key, &value, ttl);
+                    written.insert(key);
+                }
+            }
+            Write::Delete(key) => {
+                if !written.contains(&key) {
+                    backing.delete(&key);
+                    written.insert(key);
+                }
+            }
+            Write::Flush(done) => {
+                let _ = done.send(());
+            }
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static, S: BuildHasher> LocalCacheBuilder<T, S> {
+    /// Makes the [`backing_store`](Self::backing_store) write-behind:
+    /// stores and deletes are queued and made in batches on a thread of
+    /// their own, keeping only the last write of each key in a batch.
+    /// Beware that an entry evicted before its write is made reads the
+    /// older value back from the store. Has no effect without a backing
+    /// store.
+    pub fn write_behind(mut self, config: WriteBehind) -> Self {
+        self.write_behind = Some((config, WriteBehindWorker::spawn));
+        self
+    }
+}
+
+impl<T, S> LocalCache<T, S> {
+    /// Blocks until every write queued for the backing store before this
+    /// call has been made. Returns immediately without
+    /// [`write_behind`](LocalCacheBuilder::write_behind).
+    pub fn flush(&self) {
+        let Some(sender) = self.write_behind.as_ref().and_then(|worker| worker.sender()) else {
+            return;
+        };
+        let (done, wait) = mpsc::sync_channel(1);
+        if sender.send(Write::Flush(done)).is_ok() {
+            let _ = wait.recv();
+        }
+    }
+
+    /// Makes every queued write and stops the write-behind thread; writes
+    /// go straight to the backing store from then on. Dropping the cache
+    /// does the same.
+    pub fn shutdown(&self) {
+        if let Some(worker) = &self.write_behind {
+            worker.stop();
+        }
+    }
+}
+
+#[test]
+fn test_write_behind() {
+    use std::sync::atomic::Ordering;
+
+    use crate::testing::Db;
+
+    let db = Db::default();
+    let (rows, writes) = (db.0.clone(), db.1.clone());
+    let local_cache: LocalCache<usize> = LocalCache::builder()
+        .backing_store(db)
+        .write_behind(WriteBehind::new(100, Duration::from_secs(60)))
+        .build();
+    for i in 0..5 {
+        local_cache.put(String::from("a"), Arc::new(i));
+    }
+    local_cache.put(String::from("b"), Arc::new(1));
+    local_cache.remove("b");
+    assert!(lock(&rows).is_empty());
+    local_cache.flush();
+    assert_eq!(Some(&4), lock(&rows).get("a"));
+    assert!(!lock(&rows).contains_key("b"));
+    assert_eq!(2, writes.load(Ordering::Relaxed));
+
+    local_cache.put(String::from("c"), Arc::new(3));
+    local_cache.shutdown();
+    assert_eq!(Some(&3), lock(&rows).get("c"));
+    local_cache.put(String::from("d"), Arc::new(4));
+    assert_eq!(Some(&4), lock(&rows).get("d"));
+
+    let db = Db::default();
+    let rows = db.0.clone();
+    let local_cache: LocalCache<usize> = LocalCache::builder()
+        .backing_store(db)
+        .write_behind(WriteBehind::new(2, Duration::from_millis(5)))
+        .build();
+    local_cache.put(String::from("x"), Arc::new(1));
+    let started = Instant::now();
+    while lock(&rows).is_empty() && started.elapsed() < Duration::from_secs(5) {
+        thread::sleep(Duration::from_millis(1));
+    }
+    assert_eq!(Some(&1), lock(&rows).get("x"));
+}